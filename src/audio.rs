@@ -0,0 +1,536 @@
+//! # Audio
+//!
+//! Gameplay events are turned into sound cues, each with its own volume,
+//! pitch variation and a limit on how many can play at once, so a hail of
+//! lasers does not drown out everything else. Music follows the app state and
+//! crossfades between the menu and the game.
+//!
+//! The sounds are loaded from `assets/sounds` and `assets/music`, see
+//! `SoundCue::config` and `MusicTrack::path`. The shipped files are
+//! synthesized placeholders. Missing files are logged and then skipped.
+//!
+//! Without bevy's `AudioPlugin`, when running headless or in tests, nothing is
+//! played. The cues are still limited and counted in `PlayedCues`, as if they
+//! lasted `CueConfig::length` seconds.
+
+use crate::game::events::{HighScoreEvent, PlayerHit};
+use crate::game::game_entity::GameEntityType;
+use crate::game::score::GameScore;
+use crate::game::vitality::DeathEvent;
+use crate::game::weapon::WeaponFired;
+use crate::settings::AudioSettings;
+use crate::state::AppState;
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use rand::Rng;
+use std::collections::HashMap;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        // There is no audio device when running headless
+        let backend = if app.is_plugin_added::<bevy::audio::AudioPlugin>() {
+            AudioBackend::Bevy
+        } else {
+            AudioBackend::Null
+        };
+
+        app.insert_resource(backend)
+            .init_resource::<AudioSettings>()
+            .init_resource::<PlayedCues>()
+            .add_event::<PlayCue>()
+            .add_systems(
+                Startup,
+                load_sounds.run_if(resource_equals(AudioBackend::Bevy)),
+            )
+            .add_systems(
+                Update,
+                (
+                    (
+                        cue_weapon_fire,
+                        cue_deaths,
+                        cue_player_hits,
+                        cue_high_scores,
+                        cue_multiplier,
+                    ),
+                    expire_silent_voices,
+                    play_cues,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, (switch_music, fade_music).chain());
+    }
+}
+
+/// What plays the sounds.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioBackend {
+    Bevy,
+    /// Plays nothing.
+    Null,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Cues
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundCue {
+    Shot,
+    Explosion,
+    PlayerHit,
+    PlayerDeath,
+    HighScore,
+    Multiplier,
+}
+
+const CUES: [SoundCue; 6] = [
+    SoundCue::Shot,
+    SoundCue::Explosion,
+    SoundCue::PlayerHit,
+    SoundCue::PlayerDeath,
+    SoundCue::HighScore,
+    SoundCue::Multiplier,
+];
+
+/// How a cue is played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CueConfig {
+    pub path: &'static str,
+    /// From 0 to 1, scaled by the effects and master volume.
+    pub volume: f32,
+    /// The pitch is changed at random by up to this fraction, so repeated
+    /// sounds are not all the same.
+    pub pitch_variation: f32,
+    /// More cues than this at once are skipped.
+    pub max_concurrent: usize,
+    /// Seconds a cue counts towards `max_concurrent` when nothing is played.
+    pub length: f32,
+}
+
+impl SoundCue {
+    pub fn config(&self) -> CueConfig {
+        match self {
+            SoundCue::Shot => CueConfig {
+                path: "sounds/shot.ogg",
+                volume: 0.4,
+                pitch_variation: 0.1,
+                max_concurrent: 4,
+                length: 0.3,
+            },
+            SoundCue::Explosion => CueConfig {
+                path: "sounds/explosion.ogg",
+                volume: 0.8,
+                pitch_variation: 0.15,
+                max_concurrent: 3,
+                length: 1.0,
+            },
+            SoundCue::PlayerHit => CueConfig {
+                path: "sounds/player_hit.ogg",
+                volume: 0.7,
+                pitch_variation: 0.05,
+                max_concurrent: 1,
+                length: 0.4,
+            },
+            SoundCue::PlayerDeath => CueConfig {
+                path: "sounds/player_death.ogg",
+                volume: 1.0,
+                pitch_variation: 0.0,
+                max_concurrent: 1,
+                length: 2.0,
+            },
+            SoundCue::HighScore => CueConfig {
+                path: "sounds/high_score.ogg",
+                volume: 1.0,
+                pitch_variation: 0.0,
+                max_concurrent: 1,
+                length: 2.0,
+            },
+            SoundCue::Multiplier => CueConfig {
+                path: "sounds/multiplier.ogg",
+                volume: 0.6,
+                pitch_variation: 0.0,
+                max_concurrent: 1,
+                length: 0.5,
+            },
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Music
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MusicTrack {
+    Menu,
+    Game,
+}
+
+const TRACKS: [MusicTrack; 2] = [MusicTrack::Menu, MusicTrack::Game];
+
+/// Seconds it takes for one track to fade into the next.
+const CROSSFADE_SECONDS: f32 = 2.0;
+
+impl MusicTrack {
+    pub fn path(&self) -> &'static str {
+        match self {
+            MusicTrack::Menu => "music/menu.ogg",
+            MusicTrack::Game => "music/game.ogg",
+        }
+    }
+
+    /// The track to play in a state, if any.
+    pub fn for_state(state: &AppState) -> Option<MusicTrack> {
+        match state {
+            AppState::Boot => None,
+            AppState::MainMenu | AppState::GameOver => Some(MusicTrack::Menu),
+            AppState::Playing | AppState::Paused => Some(MusicTrack::Game),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+/// Play a cue. `pitch` is multiplied with the random pitch variation.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct PlayCue {
+    pub cue: SoundCue,
+    pub pitch: f32,
+}
+
+impl PlayCue {
+    pub fn new(cue: SoundCue) -> Self {
+        Self { cue, pitch: 1.0 }
+    }
+
+    pub fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Resources
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Resource)]
+struct SoundHandles {
+    cues: HashMap<SoundCue, Handle<AudioSource>>,
+    music: HashMap<MusicTrack, Handle<AudioSource>>,
+}
+
+/// The number of times each cue was played, after the concurrency limits.
+#[derive(Resource, Debug, Default)]
+pub struct PlayedCues(HashMap<SoundCue, u32>);
+
+impl PlayedCues {
+    pub fn count(&self, cue: SoundCue) -> u32 {
+        self.0.get(&cue).copied().unwrap_or_default()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+/// A cue that is playing.
+#[derive(Component)]
+struct Voice(SoundCue);
+
+/// A voice of the null backend, despawned when the timer finishes.
+#[derive(Component)]
+struct SilentVoice(Timer);
+
+#[derive(Component)]
+struct MusicPlayer {
+    track: MusicTrack,
+    /// From 0 to 1, multiplied with the music volume.
+    fade: f32,
+    /// Fading in, or out to be despawned.
+    fading_in: bool,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SoundHandles {
+        cues: CUES
+            .iter()
+            .map(|cue| (*cue, asset_server.load(cue.config().path)))
+            .collect(),
+        music: TRACKS
+            .iter()
+            .map(|track| (*track, asset_server.load(track.path())))
+            .collect(),
+    });
+}
+
+fn cue_weapon_fire(mut fired_events: EventReader<WeaponFired>, mut cues: EventWriter<PlayCue>) {
+    for _ in fired_events.read() {
+        cues.send(PlayCue::new(SoundCue::Shot));
+    }
+}
+
+fn cue_deaths(mut death_events: EventReader<DeathEvent>, mut cues: EventWriter<PlayCue>) {
+    for death in death_events.read() {
+        let cue = match death._type() {
+            GameEntityType::Player => SoundCue::PlayerDeath,
+            _ => SoundCue::Explosion,
+        };
+        cues.send(PlayCue::new(cue));
+    }
+}
+
+fn cue_player_hits(mut hit_events: EventReader<PlayerHit>, mut cues: EventWriter<PlayCue>) {
+    for _ in hit_events.read() {
+        cues.send(PlayCue::new(SoundCue::PlayerHit));
+    }
+}
+
+fn cue_high_scores(
+    mut high_score_events: EventReader<HighScoreEvent>,
+    mut cues: EventWriter<PlayCue>,
+) {
+    for _ in high_score_events.read() {
+        cues.send(PlayCue::new(SoundCue::HighScore));
+    }
+}
+
+/// The pitch goes up with the multiplier.
+fn cue_multiplier(
+    game_score: Option<Res<GameScore>>,
+    mut last_multiplier: Local<u64>,
+    mut cues: EventWriter<PlayCue>,
+) {
+    let multiplier = game_score.map_or(0, |score| score.multiplier());
+    if multiplier > *last_multiplier {
+        let pitch = 1.0 + 0.05 * (multiplier - 1) as f32;
+        cues.send(PlayCue::new(SoundCue::Multiplier).with_pitch(pitch));
+    }
+    *last_multiplier = multiplier;
+}
+
+fn expire_silent_voices(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut voice_query: Query<(Entity, &mut SilentVoice)>,
+) {
+    for (entity, mut voice) in voice_query.iter_mut() {
+        if voice.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn play_cues(
+    mut commands: Commands,
+    audio: Res<AudioSettings>,
+    handles: Option<Res<SoundHandles>>,
+    sources: Option<Res<Assets<AudioSource>>>,
+    mut cue_events: EventReader<PlayCue>,
+    voice_query: Query<&Voice>,
+    mut played: ResMut<PlayedCues>,
+) {
+    let mut playing: HashMap<SoundCue, usize> = HashMap::new();
+    for Voice(cue) in voice_query.iter() {
+        *playing.entry(*cue).or_default() += 1;
+    }
+
+    // Not the game rng, sounds must not change how the game plays out
+    let mut rng = rand::thread_rng();
+
+    for PlayCue { cue, pitch } in cue_events.read() {
+        let config = cue.config();
+        let count = playing.entry(*cue).or_default();
+        if *count >= config.max_concurrent {
+            continue;
+        }
+
+        let variation = rng.gen_range(-config.pitch_variation..=config.pitch_variation);
+        let speed = pitch * (1.0 + variation);
+        let volume = audio.master_volume * audio.sfx_volume * config.volume;
+
+        if let (Some(handles), Some(sources)) = (&handles, &sources) {
+            let source = &handles.cues[cue];
+            // Missing files were logged when they failed to load
+            if !sources.contains(source) {
+                continue;
+            }
+            commands.spawn((
+                Voice(*cue),
+                AudioBundle {
+                    source: source.clone(),
+                    settings: PlaybackSettings::DESPAWN
+                        .with_volume(Volume::new_absolute(volume))
+                        .with_speed(speed),
+                },
+            ));
+        } else {
+            commands.spawn((
+                Voice(*cue),
+                SilentVoice(Timer::from_seconds(config.length, TimerMode::Once)),
+            ));
+        }
+
+        *count += 1;
+        *played.0.entry(*cue).or_default() += 1;
+    }
+}
+
+/// Start the track for the current state, and fade out the others.
+fn switch_music(
+    mut commands: Commands,
+    state: Option<Res<State<AppState>>>,
+    handles: Option<Res<SoundHandles>>,
+    mut player_query: Query<&mut MusicPlayer>,
+) {
+    let track = state.and_then(|state| MusicTrack::for_state(state.get()));
+    let current = player_query
+        .iter()
+        .find(|player| player.fading_in)
+        .map(|player| player.track);
+    if current == track {
+        return;
+    }
+
+    for mut player in player_query.iter_mut() {
+        player.fading_in = false;
+    }
+
+    if let Some(track) = track {
+        let mut player = commands.spawn(MusicPlayer {
+            track,
+            fade: 0.0,
+            fading_in: true,
+        });
+        if let Some(handles) = handles {
+            player.insert(AudioBundle {
+                source: handles.music[&track].clone(),
+                settings: PlaybackSettings::LOOP.with_volume(Volume::new_absolute(0.0)),
+            });
+        }
+    }
+}
+
+fn fade_music(
+    mut commands: Commands,
+    time: Res<Time>,
+    audio: Res<AudioSettings>,
+    mut player_query: Query<(Entity, &mut MusicPlayer, Option<&AudioSink>)>,
+) {
+    let step = time.delta_seconds() / CROSSFADE_SECONDS;
+
+    for (entity, mut player, sink) in player_query.iter_mut() {
+        if player.fading_in {
+            player.fade = (player.fade + step).min(1.0);
+        } else {
+            player.fade -= step;
+            if player.fade <= 0.0 {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        }
+
+        // Also picks up changes to the volume settings
+        if let Some(sink) = sink {
+            sink.set_volume(audio.master_volume * audio.music_volume * player.fade);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                0.1,
+            )))
+            .add_state::<AppState>()
+            .add_event::<WeaponFired>()
+            .add_event::<DeathEvent>()
+            .add_event::<PlayerHit>()
+            .add_event::<HighScoreEvent>()
+            .add_plugins(GameAudioPlugin);
+        app.update();
+        app
+    }
+
+    fn music(app: &mut App) -> Vec<(MusicTrack, bool)> {
+        let mut query = app.world.query::<&MusicPlayer>();
+        query
+            .iter(&app.world)
+            .map(|player| (player.track, player.fading_in))
+            .collect()
+    }
+
+    #[test]
+    fn test_cues_are_limited_while_playing() {
+        let mut app = app();
+        assert_eq!(*app.world.resource::<AudioBackend>(), AudioBackend::Null);
+
+        for _ in 0..10 {
+            app.world.send_event(WeaponFired {
+                position: Vec2::ZERO,
+            });
+        }
+        app.update();
+        assert_eq!(app.world.resource::<PlayedCues>().count(SoundCue::Shot), 4);
+
+        // The shots are over after 0.3 seconds
+        for _ in 0..5 {
+            app.update();
+        }
+        app.world.send_event(WeaponFired {
+            position: Vec2::ZERO,
+        });
+        app.update();
+        assert_eq!(app.world.resource::<PlayedCues>().count(SoundCue::Shot), 5);
+    }
+
+    #[test]
+    fn test_music_crossfades_between_states() {
+        let mut app = app();
+        assert!(music(&mut app).is_empty());
+
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::MainMenu);
+        app.update();
+        assert_eq!(music(&mut app), vec![(MusicTrack::Menu, true)]);
+        for _ in 0..10 {
+            app.update();
+        }
+
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Playing);
+        app.update();
+        let mut tracks = music(&mut app);
+        tracks.sort_by_key(|(track, _)| *track as u8);
+        assert_eq!(
+            tracks,
+            vec![(MusicTrack::Menu, false), (MusicTrack::Game, true)]
+        );
+
+        // Faded out after the crossfade
+        for _ in 0..20 {
+            app.update();
+        }
+        assert_eq!(music(&mut app), vec![(MusicTrack::Game, true)]);
+    }
+}
@@ -1,7 +1,10 @@
 use clap::{Parser, ValueEnum};
+use std::error::Error;
+use toml::Table;
 
 use crate::{
-    game::debug::VisualDebug,
+    file_save::{self, Migrated, Versioned},
+    game::{debug::VisualDebug, score::HighScores},
     scene::GameScene,
     settings::{ResolutionSetting, Settings},
 };
@@ -17,6 +20,21 @@ pub struct Cli {
     #[arg(long, value_enum)]
     pub scene: Option<GameScene>,
 
+    /// Seed for all gameplay randomness. Running twice with the same seed
+    /// spawns the same arena and enemies.
+    ///
+    /// Example: `--seed 42`
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// The level to play in the main game. Either the name of a level in the
+    /// `levels/` folder or a path to a `.toml` file. Defaults to
+    /// `levels/default.toml`.
+    ///
+    /// Example: `--level default`
+    #[arg(long)]
+    pub level: Option<String>,
+
     /// Show visual debug information.
     /// Takes a list of flags.
     /// CLI flags are joined with the settings file flags.
@@ -26,10 +44,18 @@ pub struct Cli {
     pub visual_debug: Vec<VisualDebug>, // NOTE: HashSet<VisualDebug> is not supported by clap
 
     /// Path to settings file.
-    /// If not specified, the default settings will be used
+    /// If not specified, the settings file in the config directory is used.
     #[arg(long)]
     pub settings: Option<String>,
 
+    /// Keep the settings, high scores, replays and screenshots in this
+    /// directory instead of the platform's config and data directories.
+    /// Can also be set with the `SPACE_GAME_DATA_DIR` environment variable.
+    ///
+    /// Example: `--data-dir ./portable`
+    #[arg(long)]
+    pub data_dir: Option<String>,
+
     /// Set the x resolution.
     #[arg(long, short)]
     pub x_pixels: Option<u32>,
@@ -42,6 +68,45 @@ pub struct Cli {
     /// Overrides the x and y resolution settings.
     #[arg(long, value_enum)]
     pub social: Option<SocialMediaFormat>,
+
+    /// Run the game without a window or GPU. The scene is simulated for
+    /// `--ticks` fixed time steps and a summary is printed on exit.
+    ///
+    /// Example: `--headless --ticks 600`
+    #[arg(long)]
+    pub headless: bool,
+
+    /// The number of ticks to simulate when running headless.
+    /// There are 60 ticks per simulated second.
+    #[arg(long, default_value_t = 3600)]
+    pub ticks: u32,
+
+    /// Record the player's actions and write them to a replay file on exit.
+    /// A bare file name is saved in the replays directory. Recording starts
+    /// when a scene is entered, also from the main menu, and stops when it is
+    /// left. The game runs at a fixed 60 ticks per second, slower if it can
+    /// not keep up.
+    ///
+    /// Example: `--record session.replay`
+    #[arg(long, conflicts_with = "replay")]
+    pub record: Option<String>,
+
+    /// Play back a replay file. The scene and seed are taken from the replay.
+    /// When running headless the whole replay is simulated as fast as
+    /// possible, with a window at 60 ticks per second. A file that does not
+    /// exist is looked up in the replays directory.
+    ///
+    /// Example: `--replay session.replay`
+    #[arg(long)]
+    pub replay: Option<String>,
+
+    /// Check a settings or high scores file and print it migrated to the
+    /// current version, without starting the game. A file with a list of
+    /// `scores` is checked as high scores, anything else as settings.
+    ///
+    /// Example: `--check-config settings.toml`
+    #[arg(long, value_name = "PATH")]
+    pub check_config: Option<String>,
 }
 
 impl Cli {
@@ -52,6 +117,14 @@ impl Cli {
             new_config.scene = Some(scene);
         }
 
+        if let Some(seed) = self.seed {
+            new_config.seed = Some(seed);
+        }
+
+        if let Some(level) = &self.level {
+            new_config.level = Some(level.clone());
+        }
+
         if let Some(x) = self.x_pixels {
             new_config.window.resolution.x = x;
         }
@@ -72,6 +145,43 @@ impl Cli {
     }
 }
 
+/// Load a settings or high scores file, see `Cli::check_config`. Returns a
+/// report with the unknown fields and the migrated document.
+pub fn check_config(path: &str) -> Result<String, Box<dyn Error>> {
+    let contents = file_save::load_from_file(path)?;
+
+    if is_high_scores(&contents)? {
+        check_document::<HighScores>(path, &contents)
+    } else {
+        check_document::<Settings>(path, &contents)
+    }
+}
+
+/// High scores are the only file with a top level `scores` list.
+fn is_high_scores(contents: &str) -> Result<bool, Box<dyn Error>> {
+    let document: Table = contents.parse()?;
+    Ok(document
+        .get("scores")
+        .is_some_and(|scores| scores.is_array()))
+}
+
+fn check_document<T: Versioned>(path: &str, contents: &str) -> Result<String, Box<dyn Error>> {
+    let migrated: Migrated<T> = file_save::parse_versioned(contents)?;
+
+    let mut report = format!(
+        "{} is valid, version {} migrated to {}\n",
+        path,
+        migrated.from_version,
+        T::VERSION
+    );
+    for field in migrated.unknown_fields.iter() {
+        report += &format!("Warning: unknown field `{}` is ignored\n", field);
+    }
+    report += "\n";
+    report += &file_save::to_versioned_string(&migrated.item)?;
+    Ok(report)
+}
+
 /// Optimize the game for a specific social media platform
 ///
 /// For now, it only means changing the resolution to match the platform's
@@ -96,3 +206,19 @@ impl SocialMediaFormat {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_is_chosen_by_content() {
+        let high_scores = file_save::to_versioned_string(&HighScores::default()).unwrap();
+        let settings = file_save::to_versioned_string(&Settings::default()).unwrap();
+
+        assert!(is_high_scores(&high_scores).unwrap());
+        assert!(is_high_scores("[[scores]]\nplayer_name = \"A\"\nscore = 1\n").unwrap());
+        assert!(!is_high_scores(&settings).unwrap());
+        assert!(!is_high_scores("").unwrap());
+    }
+}
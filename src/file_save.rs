@@ -1,6 +1,35 @@
-use std::{error::Error, fs::File, io::Read};
+//! # File Save
+//!
+//! Saves are written so that a crash can never leave a half written file
+//! behind: the content goes to a temporary file that is synced to disk and
+//! then renamed over the old file in one step. The previous versions are kept
+//! as rotating backups next to the file (`settings.toml.bak.1` is the newest).
+//!
+//! Every saved file starts with a header comment holding the save format
+//! version and a checksum of the content:
+//!
+//! ```toml
+//! # space-game save v1 checksum=9f2c4e1a0b3d5f67 (delete this line after editing by hand)
+//! ```
+//!
+//! On load a file with a wrong checksum, or one that fails to parse, is
+//! skipped in favour of the newest valid backup. Files without a header, like
+//! hand written levels, are loaded as they are.
+//!
+//! Documents that change over time, like the settings, implement `Versioned`.
+//! They are saved with a `version` field and older documents are upgraded one
+//! version at a time before they are deserialized.
 
-use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, File},
+    io::{Read, Write},
+    path::Path,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use toml::{Table, Value};
 
 /// Trait for saving and loading from a file
 pub trait FileSave {
@@ -9,14 +38,442 @@ pub trait FileSave {
     fn save_to_file(&self, path: &str) -> Result<(), Box<dyn Error>>;
 }
 
+/// A document with a `version` field that is migrated to the current version
+/// when it is loaded.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// The current version. Documents without a `version` field are version 0.
+    const VERSION: u32;
+
+    /// Upgrade a document from `version` to `version + 1`.
+    fn migrate(version: u32, document: &mut Table) -> Result<(), Box<dyn Error>>;
+}
+
+/// A document loaded and migrated to the current version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migrated<T> {
+    pub item: T,
+    /// The version the document was saved with.
+    pub from_version: u32,
+    /// Fields that are in the document but not known to this version, for
+    /// example `scores[2].colour`. They are ignored.
+    pub unknown_fields: Vec<String>,
+}
+
+/// Parse a document, running the migrations from the version it was saved
+/// with up to the current version.
+pub fn parse_versioned<T: Versioned>(contents: &str) -> Result<Migrated<T>, Box<dyn Error>> {
+    let mut document: Table = toml::from_str(contents)?;
+
+    let from_version = match document.remove("version") {
+        None => 0,
+        Some(Value::Integer(version)) => u32::try_from(version)?,
+        Some(value) => return Err(format!("version must be a number, not {}", value).into()),
+    };
+    if from_version > T::VERSION {
+        return Err(format!(
+            "version {} is newer than the supported version {}",
+            from_version,
+            T::VERSION
+        )
+        .into());
+    }
+
+    for version in from_version..T::VERSION {
+        T::migrate(version, &mut document)
+            .map_err(|err| format!("migrating from version {}: {}", version, err))?;
+    }
+
+    let item: T = Value::Table(document.clone()).try_into()?;
+    let known = Table::try_from(&item)?;
+    let mut unknown_fields = Vec::new();
+    find_unknown_fields("", &document, &known, &mut unknown_fields);
+
+    Ok(Migrated {
+        item,
+        from_version,
+        unknown_fields,
+    })
+}
+
+/// Serialize a document with the current version.
+pub fn to_versioned_string<T: Versioned>(item: &T) -> Result<String, Box<dyn Error>> {
+    // The version goes first, top level values must come before any table
+    Ok(format!(
+        "version = {}\n{}",
+        T::VERSION,
+        toml::to_string(item)?
+    ))
+}
+
+/// Load a versioned document with `load_with_backups`. Unknown fields are
+/// reported on stderr.
+pub fn load_versioned<T: Versioned>(path: &str) -> Result<T, Box<dyn Error>> {
+    let migrated = load_with_backups(path, parse_versioned::<T>)?;
+    for field in migrated.unknown_fields.iter() {
+        eprintln!("Warning: unknown field `{}` in {} is ignored.", field, path);
+    }
+    Ok(migrated.item)
+}
+
+/// Collect the fields of `document` that are missing from `known`, the same
+/// document after a round trip through its type.
+fn find_unknown_fields(prefix: &str, document: &Table, known: &Table, unknown: &mut Vec<String>) {
+    for (key, value) in document.iter() {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match (value, known.get(key)) {
+            (_, None) => unknown.push(path),
+            (Value::Table(table), Some(Value::Table(known_table))) => {
+                find_unknown_fields(&path, table, known_table, unknown);
+            }
+            (Value::Array(array), Some(Value::Array(known_array))) => {
+                for (index, (value, known_value)) in array.iter().zip(known_array).enumerate() {
+                    if let (Value::Table(table), Value::Table(known_table)) = (value, known_value) {
+                        let path = format!("{}[{}]", path, index);
+                        find_unknown_fields(&path, table, known_table, unknown);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The version of the save format, written in the header of every save.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+/// How many previous versions of a file are kept.
+pub const BACKUP_COUNT: usize = 3;
+
+const HEADER_PREFIX: &str = "# space-game save v";
+const HEADER_HINT: &str = "(delete this line after editing by hand)";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileSaveError {
+    /// The content does not match the checksum in the header, the file was
+    /// only partly written or changed without removing the header.
+    ChecksumMismatch { path: String },
+    /// The file was saved by a newer version of the game.
+    UnsupportedVersion { path: String, version: u32 },
+    /// The header could not be read.
+    InvalidHeader { path: String },
+}
+
+impl fmt::Display for FileSaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileSaveError::ChecksumMismatch { path } => {
+                write!(f, "{}: checksum does not match the content", path)
+            }
+            FileSaveError::UnsupportedVersion { path, version } => write!(
+                f,
+                "{}: save format v{} is newer than the supported v{}",
+                path, version, SAVE_FORMAT_VERSION
+            ),
+            FileSaveError::InvalidHeader { path } => write!(f, "{}: invalid save header", path),
+        }
+    }
+}
+
+impl Error for FileSaveError {}
+
+/// Read a file and check its header. Returns the content without the header.
 pub fn load_from_file(path: &str) -> Result<String, Box<dyn Error>> {
     let mut file = File::open(path)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
-    Ok(contents)
+    Ok(strip_header(path, &contents)?.to_string())
+}
+
+/// Load a file with `parse`, falling back to the newest backup that loads if
+/// the file is missing, corrupt or does not parse. A recovery is reported on
+/// stderr. The error of the file itself is returned if no backup loads.
+pub fn load_with_backups<T>(
+    path: &str,
+    parse: impl Fn(&str) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    let load = |path: &str| load_from_file(path).and_then(|contents| parse(&contents));
+
+    let err = match load(path) {
+        Ok(item) => return Ok(item),
+        Err(err) => err,
+    };
+
+    for backup in (1..=BACKUP_COUNT).map(|number| backup_path(path, number)) {
+        if let Ok(item) = load(&backup) {
+            eprintln!("Could not load {}: {}", path, err);
+            eprintln!("Recovered from backup {}.", backup);
+            return Ok(item);
+        }
+    }
+
+    Err(err)
 }
 
+/// Save the content with a header, keeping the previous versions as backups.
+/// The file is replaced in one step, so it is never left half written.
 pub fn save_to_file(path: &str, content: &str) -> Result<(), Box<dyn Error>> {
-    std::fs::write(path, content)?;
+    let temp_path = format!("{}.tmp", path);
+    {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(header(content).as_bytes())?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+
+    rotate_backups(path)?;
+    fs::rename(&temp_path, path)?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+/// The path of the `number`th newest backup of a file, starting at 1.
+pub fn backup_path(path: &str, number: usize) -> String {
+    format!("{}.bak.{}", path, number)
+}
+
+/// Shift the backups one place older and copy the current file to the newest
+/// backup. The oldest backup is dropped.
+fn rotate_backups(path: &str) -> Result<(), Box<dyn Error>> {
+    if BACKUP_COUNT == 0 || !Path::new(path).exists() {
+        return Ok(());
+    }
+
+    for number in (1..BACKUP_COUNT).rev() {
+        let from = backup_path(path, number);
+        if Path::new(&from).exists() {
+            fs::rename(&from, backup_path(path, number + 1))?;
+        }
+    }
+
+    // Copied rather than moved, so the file exists at all times
+    fs::copy(path, backup_path(path, 1))?;
     Ok(())
 }
+
+/// Make the rename durable. Not every platform can sync a directory, so
+/// failures are ignored.
+fn sync_parent_dir(path: &str) {
+    let parent = match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
+
+fn header(content: &str) -> String {
+    format!(
+        "{}{} checksum={:016x} {}\n",
+        HEADER_PREFIX,
+        SAVE_FORMAT_VERSION,
+        checksum(content),
+        HEADER_HINT
+    )
+}
+
+/// Check the header of a file, if it has one, and return the content after it.
+fn strip_header<'a>(path: &str, contents: &'a str) -> Result<&'a str, FileSaveError> {
+    let Some(rest) = contents.strip_prefix(HEADER_PREFIX) else {
+        return Ok(contents);
+    };
+    let (line, content) = rest.split_once('\n').unwrap_or((rest, ""));
+
+    let invalid = || FileSaveError::InvalidHeader {
+        path: path.to_string(),
+    };
+    let mut words = line.split_whitespace();
+    let version: u32 = words
+        .next()
+        .and_then(|version| version.parse().ok())
+        .ok_or_else(invalid)?;
+    let expected = words
+        .next()
+        .and_then(|word| word.strip_prefix("checksum="))
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+        .ok_or_else(invalid)?;
+
+    if version > SAVE_FORMAT_VERSION {
+        return Err(FileSaveError::UnsupportedVersion {
+            path: path.to_string(),
+            version,
+        });
+    }
+    if checksum(content) != expected {
+        return Err(FileSaveError::ChecksumMismatch {
+            path: path.to_string(),
+        });
+    }
+
+    Ok(content)
+}
+
+/// 64 bit FNV-1a hash. Not cryptographic, it only needs to catch files that
+/// were cut short or damaged.
+fn checksum(content: &str) -> u64 {
+    content.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for a test to save into.
+    fn test_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "space-game-file-save-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn parse_number(contents: &str) -> Result<u32, Box<dyn Error>> {
+        Ok(contents.trim().parse()?)
+    }
+
+    #[test]
+    fn test_saved_file_loads_back_without_header() {
+        let path = format!("{}/save.toml", test_dir("round-trip"));
+        save_to_file(&path, "a = 1\n").unwrap();
+
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .starts_with(HEADER_PREFIX));
+        assert_eq!(load_from_file(&path).unwrap(), "a = 1\n");
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+    }
+
+    #[test]
+    fn test_file_without_header_loads_as_is() {
+        let path = format!("{}/hand_written.toml", test_dir("no-header"));
+        fs::write(&path, "a = 1\n").unwrap();
+
+        assert_eq!(load_from_file(&path).unwrap(), "a = 1\n");
+    }
+
+    #[test]
+    fn test_backups_rotate_and_oldest_is_dropped() {
+        let path = format!("{}/save.toml", test_dir("rotate"));
+        for number in 0..=BACKUP_COUNT + 1 {
+            save_to_file(&path, &number.to_string()).unwrap();
+        }
+
+        let newest = BACKUP_COUNT + 1;
+        assert_eq!(load_from_file(&path).unwrap(), newest.to_string());
+        for number in 1..=BACKUP_COUNT {
+            let backup = load_from_file(&backup_path(&path, number)).unwrap();
+            assert_eq!(backup, (newest - number).to_string());
+        }
+        assert!(!Path::new(&backup_path(&path, BACKUP_COUNT + 1)).exists());
+    }
+
+    #[test]
+    fn test_corrupt_file_falls_back_to_newest_valid_backup() {
+        let path = format!("{}/save.toml", test_dir("recover"));
+        save_to_file(&path, "1").unwrap();
+        save_to_file(&path, "2").unwrap();
+        save_to_file(&path, "3").unwrap();
+
+        // Cut short in the middle of a save, and a damaged newest backup
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &saved[..saved.len() - 1]).unwrap();
+        fs::write(backup_path(&path, 1), "garbage").unwrap();
+
+        assert!(matches!(
+            load_from_file(&path)
+                .unwrap_err()
+                .downcast_ref::<FileSaveError>(),
+            Some(FileSaveError::ChecksumMismatch { .. })
+        ));
+        assert_eq!(load_with_backups(&path, parse_number).unwrap(), 1);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Ship {
+        name: String,
+        speed: u32,
+    }
+
+    impl Versioned for Ship {
+        const VERSION: u32 = 2;
+
+        fn migrate(version: u32, document: &mut Table) -> Result<(), Box<dyn Error>> {
+            match version {
+                // Version 1 had no speed
+                0 => (),
+                // Version 2 renamed `title` to `name`
+                1 => {
+                    let title = document.remove("title").ok_or("missing title")?;
+                    document.insert("name".to_string(), title);
+                }
+                _ => unreachable!(),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_old_document_is_migrated_step_by_step() {
+        let migrated: Migrated<Ship> = parse_versioned(
+            r#"
+            title = "Hawk"
+            speed = 3
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(migrated.from_version, 0);
+        assert_eq!(migrated.item.name, "Hawk");
+        assert!(migrated.unknown_fields.is_empty());
+
+        let saved = to_versioned_string(&migrated.item).unwrap();
+        let reloaded: Migrated<Ship> = parse_versioned(&saved).unwrap();
+        assert_eq!(reloaded.from_version, Ship::VERSION);
+        assert_eq!(reloaded.item, migrated.item);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_reported_not_rejected() {
+        let migrated: Migrated<Ship> = parse_versioned(
+            r#"
+            version = 2
+            name = "Hawk"
+            speed = 3
+            colour = "red"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(migrated.unknown_fields, vec!["colour".to_string()]);
+        assert!(parse_versioned::<Ship>("version = 3\nname = \"Hawk\"\nspeed = 3").is_err());
+    }
+
+    #[test]
+    fn test_newer_save_format_is_rejected() {
+        let path = format!("{}/save.toml", test_dir("newer"));
+        let content = "a = 1\n";
+        fs::write(
+            &path,
+            format!(
+                "{}{} checksum={:016x}\n{}",
+                HEADER_PREFIX,
+                SAVE_FORMAT_VERSION + 1,
+                checksum(content),
+                content
+            ),
+        )
+        .unwrap();
+
+        assert!(load_from_file(&path).is_err());
+    }
+}
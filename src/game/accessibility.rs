@@ -0,0 +1,312 @@
+//! # Accessibility
+//!
+//! Colorblind-safe palettes for projectiles, enemies and the health bar, and
+//! high contrast outlines around enemies and projectiles. The colorblind
+//! palettes are based on the Okabe-Ito palette.
+//!
+//! Both are set in the `[accessibility]` section of the settings, and can be
+//! changed while playing. Outlines of enemies and projectiles of factions
+//! hostile to the player use the hostile color, so they can be told apart from
+//! your own without relying on red and green.
+
+use super::faction::{Faction, FactionRelations};
+use super::game_entity::Enemy;
+use super::projectile::{mine_fill, Projectile, ProjectileType};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColorPalette>()
+            .init_resource::<HighContrast>()
+            // After everything spawned this frame, so it is never drawn in
+            // the wrong color
+            .add_systems(
+                PostUpdate,
+                (color_projectiles, color_enemies, update_outlines),
+            );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Palettes
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    #[default]
+    Default,
+    /// Red and green are hard to tell apart.
+    Deuteranopia,
+    /// Red and green are hard to tell apart, and red looks dark.
+    Protanopia,
+    /// Blue and yellow are hard to tell apart.
+    Tritanopia,
+}
+
+/// The colors of a palette.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaletteColors {
+    pub laser: Color,
+    pub beam: Color,
+    pub missile: Color,
+    pub mine: Color,
+    /// Outlines of your own projectiles.
+    pub friendly: Color,
+    /// Outlines of enemies and their projectiles.
+    pub hostile: Color,
+    /// Multiplied with the enemy sprites.
+    pub enemy_tint: Color,
+    pub health_low: Color,
+    pub health_medium: Color,
+    pub health_full: Color,
+}
+
+const ORANGE: Color = Color::rgb(0.9, 0.62, 0.0);
+const SKY_BLUE: Color = Color::rgb(0.34, 0.71, 0.91);
+const BLUISH_GREEN: Color = Color::rgb(0.0, 0.62, 0.45);
+const YELLOW: Color = Color::rgb(0.94, 0.89, 0.26);
+const VERMILLION: Color = Color::rgb(0.84, 0.37, 0.0);
+const REDDISH_PURPLE: Color = Color::rgb(0.8, 0.47, 0.65);
+
+impl Palette {
+    pub fn colors(&self) -> PaletteColors {
+        match self {
+            Palette::Default => PaletteColors {
+                laser: Color::WHITE,
+                beam: Color::rgb(0.6, 1.0, 1.0),
+                missile: Color::rgb(1.0, 0.6, 0.2),
+                mine: Color::rgb(1.0, 0.2, 0.2),
+                friendly: Color::rgb(0.3, 0.7, 1.0),
+                hostile: Color::rgb(1.0, 0.25, 0.25),
+                enemy_tint: Color::WHITE,
+                health_low: Color::RED,
+                health_medium: Color::ORANGE,
+                health_full: Color::WHITE,
+            },
+            Palette::Deuteranopia => PaletteColors {
+                laser: Color::WHITE,
+                beam: SKY_BLUE,
+                missile: YELLOW,
+                mine: REDDISH_PURPLE,
+                friendly: SKY_BLUE,
+                hostile: ORANGE,
+                enemy_tint: Color::rgb(1.0, 0.85, 0.6),
+                health_low: VERMILLION,
+                health_medium: YELLOW,
+                health_full: Color::WHITE,
+            },
+            Palette::Protanopia => PaletteColors {
+                laser: Color::WHITE,
+                beam: SKY_BLUE,
+                missile: ORANGE,
+                mine: REDDISH_PURPLE,
+                friendly: SKY_BLUE,
+                hostile: YELLOW,
+                enemy_tint: Color::rgb(1.0, 1.0, 0.7),
+                health_low: ORANGE,
+                health_medium: SKY_BLUE,
+                health_full: Color::WHITE,
+            },
+            Palette::Tritanopia => PaletteColors {
+                laser: Color::WHITE,
+                beam: BLUISH_GREEN,
+                missile: REDDISH_PURPLE,
+                mine: VERMILLION,
+                friendly: Color::rgb(0.3, 0.9, 0.9),
+                hostile: Color::rgb(0.9, 0.3, 0.5),
+                enemy_tint: Color::rgb(1.0, 0.7, 0.8),
+                health_low: VERMILLION,
+                health_medium: REDDISH_PURPLE,
+                health_full: Color::WHITE,
+            },
+        }
+    }
+}
+
+impl PaletteColors {
+    pub fn projectile(&self, projectile_type: ProjectileType) -> Color {
+        match projectile_type {
+            ProjectileType::Laser => self.laser,
+            ProjectileType::Beam => self.beam,
+            ProjectileType::Missile => self.missile,
+            ProjectileType::Mine => self.mine,
+        }
+    }
+
+    /// The color of the health text, red when low.
+    pub fn health(&self, health: u32) -> Color {
+        if health < 33 {
+            self.health_low
+        } else if health < 66 {
+            self.health_medium
+        } else {
+            self.health_full
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Resources
+////////////////////////////////////////////////////////////////////////////////
+
+/// The palette the game is drawn with.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ColorPalette(pub Palette);
+
+/// Draw outlines around enemies and projectiles.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HighContrast(pub bool);
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+struct Outline;
+
+const OUTLINE_WIDTH: f32 = 2.0;
+/// Space between the collider and the outline.
+const OUTLINE_PADDING: f32 = 3.0;
+
+fn build_outline(collider: &Collider, color: Color) -> impl Bundle {
+    let aabb = collider.raw.compute_local_aabb();
+    let shape = shapes::Rectangle {
+        extents: Vec2::new(aabb.extents().x, aabb.extents().y) + OUTLINE_PADDING * 2.0,
+        origin: RectangleOrigin::CustomCenter(Vec2::new(aabb.center().x, aabb.center().y)),
+    };
+
+    (
+        Outline,
+        ShapeBundle {
+            path: GeometryBuilder::build_as(&shape),
+            // Above the sprite it outlines
+            spatial: SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, 0.5)),
+            ..default()
+        },
+        Stroke::new(color, OUTLINE_WIDTH),
+    )
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+type ProjectileColorQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Ref<'static, Projectile>,
+        Option<&'static mut Sprite>,
+        Option<&'static mut Fill>,
+        Option<&'static mut Stroke>,
+    ),
+>;
+
+fn color_projectiles(palette: Res<ColorPalette>, mut query: ProjectileColorQuery) {
+    let colors = palette.0.colors();
+
+    for (projectile, sprite, fill, stroke) in query.iter_mut() {
+        if !palette.is_changed() && !projectile.is_added() {
+            continue;
+        }
+
+        let color = colors.projectile(projectile.projectile_type());
+        if let Some(mut sprite) = sprite {
+            sprite.color = color;
+        }
+        // Mines are drawn as shapes
+        if let Some(mut fill) = fill {
+            fill.color = mine_fill(color);
+        }
+        if let Some(mut stroke) = stroke {
+            stroke.color = color;
+        }
+    }
+}
+
+fn color_enemies(palette: Res<ColorPalette>, mut query: Query<(Ref<Enemy>, &mut Sprite)>) {
+    let colors = palette.0.colors();
+
+    for (enemy, mut sprite) in query.iter_mut() {
+        if palette.is_changed() || enemy.is_added() {
+            sprite.color = colors.enemy_tint;
+        }
+    }
+}
+
+type OutlinedQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static Collider, Option<&'static Faction>),
+    Or<(With<Projectile>, With<Enemy>)>,
+>;
+
+/// Outline everything new, or everything again when the setting, palette or
+/// faction relations changed.
+fn update_outlines(
+    mut commands: Commands,
+    high_contrast: Res<HighContrast>,
+    palette: Res<ColorPalette>,
+    relations: Res<FactionRelations>,
+    outlined_query: OutlinedQuery,
+    added_query: Query<(), Added<Collider>>,
+    outline_query: Query<Entity, With<Outline>>,
+) {
+    let redraw = high_contrast.is_changed() || palette.is_changed() || relations.is_changed();
+    if redraw {
+        for entity in outline_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    if !high_contrast.0 {
+        return;
+    }
+
+    let colors = palette.0.colors();
+    for (entity, collider, faction) in outlined_query.iter() {
+        if !redraw && !added_query.contains(entity) {
+            continue;
+        }
+
+        let hostile =
+            faction.is_some_and(|faction| relations.is_hostile(Faction::Player, *faction));
+        let color = if hostile {
+            colors.hostile
+        } else {
+            colors.friendly
+        };
+
+        let outline = commands.spawn(build_outline(collider, color)).id();
+        commands.entity(entity).add_child(outline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palettes_tell_health_and_sides_apart() {
+        for palette in [
+            Palette::Default,
+            Palette::Deuteranopia,
+            Palette::Protanopia,
+            Palette::Tritanopia,
+        ] {
+            let colors = palette.colors();
+
+            assert_ne!(colors.health(10), colors.health(50), "{:?}", palette);
+            assert_ne!(colors.health(50), colors.health(100), "{:?}", palette);
+            assert_ne!(colors.friendly, colors.hostile, "{:?}", palette);
+        }
+    }
+}
@@ -1,40 +1,28 @@
 use super::game_entity::Enemy;
-use super::meteors::MeteorSize;
+use super::level::Level;
 use super::meteors::{self, Meteor};
+use super::meteors::{MeteorSize, MeteorSizeWeights};
 use super::player;
 use super::player_camera::PlayerCameraLabel;
+use super::rng::{GameRng, RngStream};
 use super::screen_bounds::ScreenBounds;
-use super::turret;
 use super::{assets, player_camera};
 use crate::misc::random;
 use crate::prelude::*;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use rand::distributions::Uniform;
 use rand::prelude::*;
 use std::collections::VecDeque;
 use std::f32::consts::PI;
-use std::time::Duration;
 
-////////////////////////////////////////////////////////////////////////////////
-// Plugin
-////////////////////////////////////////////////////////////////////////////////
-
-pub struct ArenaPlugin;
-
-impl Plugin for ArenaPlugin {
-    fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                tick_enemy_spawn_timer,
-                update_spawn_enemy.after(tick_enemy_spawn_timer),
-            ),
-        );
-    }
-}
 pub const PLAYER_SPAWN_RADIUS: f32 = 100.0;
 
+/// The smallest arena that leaves room for meteors around the player's spawn
+/// location.
+pub const MIN_ARENA_RADIUS: f32 = PLAYER_SPAWN_RADIUS + assets::METEOR_BIG_RADIUS;
+
 ////////////////////////////////////////////////////////////////////////////////
 // Spawn & Despawn
 ////////////////////////////////////////////////////////////////////////////////
@@ -52,91 +40,43 @@ pub fn despawn(
     >,
 ) {
     commands.remove_resource::<Arena>();
-    commands.remove_resource::<EnemySpawnTimer>();
     commands.despawn_all(&query);
 }
 
-pub fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let arena = Arena::new(2000.0, 400.0);
-
-    arena.spawn_asteroid_bounds(&mut commands, &asset_server);
-    arena.spawn_random_asteroids(&mut commands, &asset_server, 100);
-    let player_entity =
-        player::spawn_player(&mut commands, &asset_server, Vec2::new(0.0, 0.0), 0.0);
+pub fn spawn(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut game_rng: ResMut<GameRng>,
+    level: Res<Level>,
+) {
+    let arena = Arena::new(level.arena.radius, level.arena.width);
+    let rng = game_rng.stream(RngStream::Arena);
+
+    arena.spawn_asteroid_bounds(&mut commands, &asset_server, rng);
+    arena.spawn_meteors(
+        &mut commands,
+        &asset_server,
+        rng,
+        level.meteors.count(level.arena.radius),
+        &level.meteors.sizes,
+    );
+    let player_entity = player::spawn_player_with_loadout(
+        &mut commands,
+        &asset_server,
+        Vec2::new(0.0, 0.0),
+        0.0,
+        &level.player,
+    );
 
     player_camera::spawn(&mut commands, player_entity);
 
     commands.insert_resource(arena);
-    commands.insert_resource(EnemySpawnTimer::from_seconds(10.0));
-}
-
-////////////////////////////////////////////////////////////////////////////////
-// Components
-////////////////////////////////////////////////////////////////////////////////
-
-#[derive(Resource)]
-pub struct EnemySpawnTimer {
-    pub timer: Timer,
-}
-
-impl Default for EnemySpawnTimer {
-    fn default() -> EnemySpawnTimer {
-        EnemySpawnTimer {
-            timer: Timer::from_seconds(10.0, TimerMode::Once),
-        }
-    }
-}
-
-impl EnemySpawnTimer {
-    pub fn from_seconds(secs: f32) -> Self {
-        Self {
-            timer: Timer::from_seconds(secs, TimerMode::Once),
-        }
-    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 // Systems
 ////////////////////////////////////////////////////////////////////////////////
 
-fn tick_enemy_spawn_timer(mut enemy_spawn_timer: Option<ResMut<EnemySpawnTimer>>, time: Res<Time>) {
-    if let Some(enemy_spawn_timer) = enemy_spawn_timer.as_mut() {
-        enemy_spawn_timer.timer.tick(time.delta());
-    }
-}
-
-fn update_spawn_enemy(
-    screen_bounds_opt: Option<Res<ScreenBounds>>,
-    enemy_query: Query<&Enemy>,
-    mut enemy_spawn_timer_opt: Option<ResMut<EnemySpawnTimer>>,
-    arena_opt: Option<Res<Arena>>,
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    rapier_context: Res<RapierContext>,
-) {
-    if let (Some(enemy_spawn_timer), Some(screen_bounds), Some(arena)) =
-        (enemy_spawn_timer_opt.as_mut(), screen_bounds_opt, arena_opt)
-    {
-        if enemy_spawn_timer.timer.finished() && enemy_query.iter().len() < 100 {
-            // Reduce the duration of the timer by 10% each time it finishes
-            let duration = enemy_spawn_timer.timer.duration();
-            enemy_spawn_timer
-                .timer
-                .set_duration(duration.mul_f32(0.9).max(Duration::from_secs_f32(1.0)));
-            enemy_spawn_timer.timer.reset();
-
-            // Spawn a new enemy
-            spawn_enemy(
-                &screen_bounds,
-                &arena,
-                &mut commands,
-                &asset_server,
-                rapier_context,
-            );
-        }
-    }
-}
-
 fn hollow_circle(radius: f32, number_of_points: u32) -> Collider {
     // Generate
     let mut vertices: Vec<Vect> = Vec::new();
@@ -195,7 +135,7 @@ impl Arena {
             player_spawn_locations: PlayerSpawnLocation {
                 position: Vec2::ZERO,
                 rotation: 0.0,
-                protcted_radius: 100.0,
+                protcted_radius: PLAYER_SPAWN_RADIUS,
             },
         }
     }
@@ -208,7 +148,14 @@ impl Arena {
         &self.player_spawn_locations
     }
 
-    pub fn spawn_asteroid_bounds(&self, commands: &mut Commands, asset_server: &Res<AssetServer>) {
+    pub fn spawn_asteroid_bounds<R>(
+        &self,
+        commands: &mut Commands,
+        asset_server: &Res<AssetServer>,
+        rng: &mut R,
+    ) where
+        R: Rng + ?Sized,
+    {
         let asteroid_bounds = &self.asteroid_bounds;
 
         let inner_radius = asteroid_bounds.radius + assets::METEOR_BIG_RADIUS * 2.0;
@@ -219,15 +166,13 @@ impl Arena {
 
         let uniform: Uniform<f32> = Uniform::new(0.0, 1.0);
 
-        let mut rng = rand::thread_rng();
-
         while let Some((candidate_pos, candidate_size)) = candidates.pop_front() {
             if candidate_pos.length() > outer_radius || candidate_pos.length() < inner_radius {
                 continue;
             }
 
             // random rotation
-            let rotation = uniform.sample(&mut rng) * 2.0 * PI;
+            let rotation = uniform.sample(rng) * 2.0 * PI;
 
             // Check if the candidate is valid (a.k.a. not colliding with anything)
             let valid = added.iter().all(|added_pos| {
@@ -250,13 +195,13 @@ impl Arena {
 
                 let number_of_candidates = 6;
 
-                let angle_offset = uniform.sample(&mut rng) * 2.0 * PI;
+                let angle_offset = uniform.sample(rng) * 2.0 * PI;
 
                 for i in 1..=number_of_candidates {
                     let angle = 2.0 * PI * (i as f32 / number_of_candidates as f32) + angle_offset;
                     // 2 is so that there is no overlapp, 0.1 to add a bit of padding.
                     let distance = assets::METEOR_BIG_RADIUS * 2.1
-                        + uniform.sample(&mut rng) * assets::METEOR_BIG_RADIUS * 0.2;
+                        + uniform.sample(rng) * assets::METEOR_BIG_RADIUS * 0.2;
                     let offset = Vec2::new(angle.cos() * distance, angle.sin() * distance);
 
                     candidates.push_back((offset + candidate_pos, MeteorSize::Big));
@@ -274,25 +219,49 @@ impl Arena {
         );
     }
 
-    pub fn spawn_random_asteroids(
+    pub fn spawn_random_asteroids<R>(
         &self,
         commands: &mut Commands,
 
         asset_server: &Res<AssetServer>,
+        rng: &mut R,
         number_of_meteors: usize,
-    ) {
-        let arena_center = Vec2::new(0.0, 0.0);
+    ) where
+        R: Rng + ?Sized,
+    {
+        self.spawn_meteors(
+            commands,
+            asset_server,
+            rng,
+            number_of_meteors,
+            &MeteorSizeWeights::default(),
+        );
+    }
 
-        let mut rng = rand::thread_rng();
+    /// Spawn meteors at random locations in the arena, outside of the player's
+    /// spawn location. The sizes are picked using the given weights.
+    pub fn spawn_meteors<R>(
+        &self,
+        commands: &mut Commands,
+        asset_server: &Res<AssetServer>,
+        rng: &mut R,
+        number_of_meteors: usize,
+        sizes: &MeteorSizeWeights,
+    ) where
+        R: Rng + ?Sized,
+    {
+        let arena_center = Vec2::new(0.0, 0.0);
 
         for _ in 1..=number_of_meteors {
-            let size = rng.gen_range(0..10);
+            let Some(meteor_size) = sizes.sample(rng) else {
+                return;
+            };
 
-            let (meteor_size, meteor_radius) = match size {
-                1..=2 => (MeteorSize::Tiny, assets::METEOR_TINY_RADIUS),
-                3..=5 => (MeteorSize::Small, assets::METEOR_SMALL_RADIUS),
-                6..=8 => (MeteorSize::Medium, assets::METEOR_MEDIUM_RADIUS),
-                _ => (MeteorSize::Big, assets::METEOR_BIG_RADIUS),
+            let meteor_radius = match meteor_size {
+                MeteorSize::Tiny => assets::METEOR_TINY_RADIUS,
+                MeteorSize::Small => assets::METEOR_SMALL_RADIUS,
+                MeteorSize::Medium => assets::METEOR_MEDIUM_RADIUS,
+                MeteorSize::Big => assets::METEOR_BIG_RADIUS,
             };
 
             // Subtract the meteor radius from the arena radius to ensure that the meteor is spawned
@@ -300,7 +269,7 @@ impl Arena {
 
             let candidate = arena_center
                 + random::uniform_donut(
-                    &mut rng,
+                    rng,
                     self.asteroid_bounds.radius() - meteor_radius,
                     self.player_spawn_locations.protcted_radius,
                 );
@@ -327,60 +296,62 @@ impl Arena {
     }
 }
 
-fn circle_area(radius: f32) -> f32 {
+pub fn circle_area(radius: f32) -> f32 {
     radius * radius * std::f32::consts::PI
 }
 
-fn spawn_enemy(
-    screen_bounds: &Res<ScreenBounds>,
-    arena: &Res<Arena>,
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    rapier_context: Res<RapierContext>,
-) {
-    let mut has_spawn_location = false;
-
-    let turret_asset = assets::TURRET_BASE_BIG;
-    let mut candidate_spawn_location = Vec2::new(0.0, 0.0);
-    let filter = QueryFilter::default();
-
-    // If we can't find a spawn location after 100 attempts, give up. The arena is probably full.
-    let mut attempts = 0;
-    let max_attempts = 100;
-
-    // Try to find a valid spawn location
-    while !has_spawn_location && attempts < max_attempts {
-        attempts += 1;
-        // Generate a candidate spawn location
-        let mut rng = rand::thread_rng();
-        candidate_spawn_location = random::uniform_circle(&mut rng, arena.asteroid_bounds.radius);
-
-        // Spawn the turret outside of the screen
-        if screen_bounds.contains(candidate_spawn_location) {
-            continue;
-        }
+/// Finds locations to spawn enemies at. A location is inside the arena,
+/// outside of the screen and the enemy's collider does not overlap anything.
+#[derive(SystemParam)]
+pub struct SpawnLocationFinder<'w> {
+    screen_bounds: Option<Res<'w, ScreenBounds>>,
+    arena: Option<Res<'w, Arena>>,
+    rapier_context: Res<'w, RapierContext>,
+}
 
-        has_spawn_location = true;
-
-        // Check if the candidate spawn location is valid
-        rapier_context.intersections_with_shape(
-            candidate_spawn_location,
-            0.0,
-            &turret_asset.collider(),
-            filter,
-            |_| {
-                has_spawn_location = false;
-                false // Return `false` to stop the query.
-            },
-        );
-    }
+impl SpawnLocationFinder<'_> {
+    /// Find a location for the given collider.
+    ///
+    /// Returns `None` if there is no arena, or if no location is found after
+    /// 100 attempts, the arena is probably full.
+    pub fn find<R>(&self, collider: &Collider, rng: &mut R) -> Option<Vec2>
+    where
+        R: Rng + ?Sized,
+    {
+        let screen_bounds = self.screen_bounds.as_ref()?;
+        let arena = self.arena.as_ref()?;
+        let filter = QueryFilter::default();
+        let max_attempts = 100;
+
+        for _ in 0..max_attempts {
+            // Generate a candidate spawn location
+            let candidate_spawn_location =
+                random::uniform_circle(rng, arena.asteroid_bounds.radius);
+
+            // Spawn the enemy outside of the screen
+            if screen_bounds.contains(candidate_spawn_location) {
+                continue;
+            }
 
-    if attempts < max_attempts {
-        turret::spawn(
-            commands,
-            asset_server,
-            &turret::TurretConfig::default(),
-            Transform::from_xyz(candidate_spawn_location.x, candidate_spawn_location.y, 0.0),
-        );
+            let mut is_free = true;
+
+            // Check if the candidate spawn location is valid
+            self.rapier_context.intersections_with_shape(
+                candidate_spawn_location,
+                0.0,
+                collider,
+                filter,
+                |_| {
+                    is_free = false;
+                    false // Return `false` to stop the query.
+                },
+            );
+
+            if is_free {
+                return Some(candidate_spawn_location);
+            }
+        }
+
+        None
     }
 }
@@ -5,30 +5,40 @@ pub const PLAYER_GROUP: Group = Group::GROUP_1;
 pub const PLAYER_PROJECTILE_GROUP: Group = Group::GROUP_2;
 pub const METEOR_GROUP: Group = Group::GROUP_3;
 pub const ARENA_GROUP: Group = Group::GROUP_4;
+/// The ships of `Faction::Enemy1`.
 pub const ENEMY_GROUP: Group = Group::GROUP_5;
 pub const ENEMY_PROJECTILE_GROUP: Group = Group::GROUP_6;
 pub const KAMIKAZE_DRONE_GROUP: Group = Group::GROUP_7;
 pub const SENSOR_GROUP: Group = Group::GROUP_8;
+pub const PICKUP_GROUP: Group = Group::GROUP_9;
+pub const ENEMY2_GROUP: Group = Group::GROUP_10;
+pub const ENEMY2_PROJECTILE_GROUP: Group = Group::GROUP_11;
+pub const ENEMY3_GROUP: Group = Group::GROUP_12;
+pub const ENEMY3_PROJECTILE_GROUP: Group = Group::GROUP_13;
+pub const NEUTRAL_GROUP: Group = Group::GROUP_14;
+pub const NEUTRAL_PROJECTILE_GROUP: Group = Group::GROUP_15;
 
-// Entity Collision Filters
-pub const PLAYER_FILTER_MASK: Group = METEOR_GROUP
-    .union(ARENA_GROUP)
+/// The ships of every faction.
+pub const SHIP_GROUPS: Group = PLAYER_GROUP
     .union(ENEMY_GROUP)
-    .union(SENSOR_GROUP)
-    .union(ENEMY_PROJECTILE_GROUP);
-pub const METEOR_FILTER_MASK: Group = PLAYER_GROUP
-    .union(METEOR_GROUP)
-    .union(PLAYER_PROJECTILE_GROUP)
+    .union(ENEMY2_GROUP)
+    .union(ENEMY3_GROUP)
+    .union(NEUTRAL_GROUP);
+/// The projectiles of every faction.
+pub const PROJECTILE_GROUPS: Group = PLAYER_PROJECTILE_GROUP
     .union(ENEMY_PROJECTILE_GROUP)
-    .union(SENSOR_GROUP)
-    .union(ARENA_GROUP)
-    .union(ENEMY_GROUP);
-pub const PLAYER_PROJECTILE_FILTER_MASK: Group = METEOR_GROUP.union(ARENA_GROUP).union(ENEMY_GROUP);
-pub const ARENA_FILTER_MASK: Group = PLAYER_GROUP.union(METEOR_GROUP);
-pub const ENEMY_FILTER_MASK: Group = PLAYER_GROUP
+    .union(ENEMY2_PROJECTILE_GROUP)
+    .union(ENEMY3_PROJECTILE_GROUP)
+    .union(NEUTRAL_PROJECTILE_GROUP);
+
+// Entity Collision Filters
+//
+// Ships, projectiles and sensors of a faction get theirs from the relations
+// between the factions, see `FactionRelations::collision_groups`.
+pub const METEOR_FILTER_MASK: Group = SHIP_GROUPS
+    .union(PROJECTILE_GROUPS)
     .union(METEOR_GROUP)
     .union(SENSOR_GROUP)
-    .union(ENEMY_GROUP)
-    .union(PLAYER_PROJECTILE_GROUP);
-pub const ENEMY_PROJECTILE_FILTER_MASK: Group = METEOR_GROUP.union(PLAYER_GROUP);
-pub const KAMIKAZE_DRONE_FILTER_MASK: Group = SENSOR_GROUP;
+    .union(ARENA_GROUP);
+pub const ARENA_FILTER_MASK: Group = PLAYER_GROUP.union(METEOR_GROUP);
+pub const PICKUP_FILTER_MASK: Group = PLAYER_GROUP;
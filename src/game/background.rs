@@ -1,9 +1,7 @@
-use bevy::{
-    prelude::*,
-    window::{PrimaryWindow, WindowResized},
-};
+use bevy::{prelude::*, window::WindowResized};
 
 use super::debug::{self, BackgroundGridDebugFlagLabel};
+use super::screen_bounds::Viewport;
 
 const BACKGROUND_TILE_WIDTH: f32 = 256.0;
 const BACKGROUND_TILE_HEIGHT: f32 = 256.0;
@@ -44,15 +42,13 @@ impl Plugin for BackgroundPlugin {
 pub fn spawn(
     mut commands: Commands,
     background_grid: Res<BackgroundGrid>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    viewport: Viewport,
     asset_server: Res<AssetServer>,
 ) {
-    let window = window_query.get_single().unwrap();
-
     spawn_background_tiles(
         &mut commands,
         &background_grid,
-        (window.width(), window.height()),
+        (viewport.width(), viewport.height()),
         &asset_server,
     )
 }
@@ -1,5 +1,209 @@
-use bevy::prelude::Component;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
 
 /// Means an entity is a KamikazeDrone
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
 pub struct BoidLabel;
+
+////////////////////////////////////////////////////////////////////////////////
+// Flocking
+////////////////////////////////////////////////////////////////////////////////
+
+/// Makes an entity steer by the other members of its flock.
+///
+/// The flocking systems only compute the velocity the entity wants, see
+/// [`Flocking::velocity`]. How to get there is up to whoever owns the entity,
+/// drones set their velocity and ships push themselves towards it.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Flocking {
+    /// The leader of the flock. Only members of the same flock steer by each
+    /// other, an entity that leads itself is a flock of one.
+    pub flock: Entity,
+    pub mode: FlockMode,
+    pub weights: FlockWeights,
+    /// Steer around meteors, if set.
+    pub avoidance: Option<ObstacleAvoidance>,
+    velocity: Option<Vec2>,
+}
+
+impl Flocking {
+    pub fn new(flock: Entity, mode: FlockMode, weights: FlockWeights) -> Self {
+        Self {
+            flock,
+            mode,
+            weights,
+            avoidance: None,
+            velocity: None,
+        }
+    }
+
+    pub fn with_avoidance(mut self, avoidance: ObstacleAvoidance) -> Self {
+        self.avoidance = Some(avoidance);
+        self
+    }
+
+    /// The velocity the entity wants, none until it was first computed, and
+    /// none for the leader of a formation, which goes its own way.
+    pub fn velocity(&self) -> Option<Vec2> {
+        self.velocity
+    }
+
+    pub(super) fn set_velocity(&mut self, velocity: Option<Vec2>) {
+        self.velocity = velocity;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlockMode {
+    /// Every member steers by its neighbours: separation, alignment and
+    /// cohesion.
+    Swarm,
+    /// The members keep their slot in a formation around the leader, and stay
+    /// clear of each other.
+    Formation { formation: Formation, slot: usize },
+}
+
+/// How followers are placed around the leader of a flock.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Formation {
+    /// Two lines trailing behind the leader.
+    V { spacing: f32 },
+    /// Side by side with the leader.
+    Line { spacing: f32 },
+    /// A ring around the leader.
+    Circle { radius: f32, slots: usize },
+}
+
+impl Formation {
+    /// Where follower `slot` flies relative to the leader, when the leader
+    /// faces up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use space_game::game::boids::Formation;
+    ///
+    /// let v = Formation::V { spacing: 50.0 };
+    ///
+    /// assert_eq!(v.slot_offset(0), Vec2::new(-50.0, -50.0));
+    /// assert_eq!(v.slot_offset(1), Vec2::new(50.0, -50.0));
+    /// assert_eq!(v.slot_offset(2), Vec2::new(-100.0, -100.0));
+    /// ```
+    pub fn slot_offset(&self, slot: usize) -> Vec2 {
+        // Alternate left and right, further out every two slots
+        let side = if slot % 2 == 0 { -1.0 } else { 1.0 };
+        let row = (slot / 2 + 1) as f32;
+
+        match *self {
+            Formation::V { spacing } => Vec2::new(side * row, -row) * spacing,
+            Formation::Line { spacing } => Vec2::new(side * row * spacing, 0.0),
+            Formation::Circle { radius, slots } => {
+                let angle = 2.0 * PI * slot as f32 / slots.max(1) as f32;
+                // Start behind the leader
+                Vec2::from_angle(angle).rotate(-Vec2::Y) * radius
+            }
+        }
+    }
+}
+
+/// How strongly each rule steers a member of a flock.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlockWeights {
+    /// Steer away from members closer than `separation_radius`.
+    pub separation: f32,
+    /// Match the velocity of the members within `neighbour_radius`.
+    pub alignment: f32,
+    /// Steer towards the center of the members within `neighbour_radius`.
+    pub cohesion: f32,
+    /// Steer towards the slot in a formation.
+    pub formation: f32,
+    pub separation_radius: f32,
+    pub neighbour_radius: f32,
+    /// Swarms never fly slower than this, formations can come to a stop.
+    pub min_speed: f32,
+    pub max_speed: f32,
+}
+
+impl FlockWeights {
+    /// Weights for kamikaze drone swarms.
+    pub fn drones() -> Self {
+        Self {
+            separation: 0.5,
+            alignment: 0.1,
+            cohesion: 0.005,
+            separation_radius: 30.0,
+            neighbour_radius: 200.0,
+            ..Default::default()
+        }
+    }
+
+    /// Weights for enemy ship squads.
+    pub fn ships() -> Self {
+        Self {
+            separation: 0.1,
+            separation_radius: 120.0,
+            neighbour_radius: 300.0,
+            min_speed: 0.0,
+            max_speed: 300.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// The weights of the boids in the boid scene.
+impl Default for FlockWeights {
+    fn default() -> Self {
+        Self {
+            separation: 0.05,
+            alignment: 0.05,
+            cohesion: 0.0005,
+            formation: 1.0,
+            separation_radius: 40.0,
+            neighbour_radius: 200.0,
+            min_speed: 20.0,
+            max_speed: 200.0,
+        }
+    }
+}
+
+/// Look for a way around meteors in the direction the entity wants to go.
+/// See `rapier_extension::find_unobstructed_path`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObstacleAvoidance {
+    /// Start looking this far out, past the entity's own collider.
+    pub inner_distance: f32,
+    pub outer_distance: f32,
+    /// The width of the entity.
+    pub width: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formation_slots_do_not_overlap() {
+        let formations = [
+            Formation::V { spacing: 50.0 },
+            Formation::Line { spacing: 50.0 },
+            Formation::Circle {
+                radius: 100.0,
+                slots: 4,
+            },
+        ];
+
+        for formation in formations {
+            let offsets: Vec<Vec2> = (0..4).map(|slot| formation.slot_offset(slot)).collect();
+
+            for (i, a) in offsets.iter().enumerate() {
+                // Not on the leader either
+                assert!(a.length() > 1.0, "{:?} slot {}", formation, i);
+                for b in offsets.iter().skip(i + 1) {
+                    assert!(a.distance(*b) > 1.0, "{:?} slot {}", formation, i);
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,24 @@
+//! # Boids
+//!
+//! Flocking for anything that moves in groups. Give an entity a [`Flocking`]
+//! component and it gets the velocity it wants from the other members of its
+//! flock: either swarming by separation, alignment and cohesion, or keeping a
+//! slot in a V, line or circle formation around the leader. Meteors in the way
+//! are steered around.
+//!
+//! The boid scene has decorative boids, which wrap around the window.
+
 mod components;
 mod systems;
 
 use bevy::prelude::*;
 use bevy_rapier2d::dynamics::Velocity;
 
+pub use self::components::{FlockMode, FlockWeights, Flocking, Formation, ObstacleAvoidance};
+pub use self::systems::{flock_velocity, Boid};
+
 use self::components::BoidLabel;
+use super::debug::{self, VisionConeFlagLabel};
 use crate::game::assets;
 use crate::misc::transform::from_location_angle;
 
@@ -16,7 +30,46 @@ pub struct BoidsPlugin;
 
 impl Plugin for BoidsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, systems::update_boid);
+        app.add_systems(
+            Update,
+            (
+                systems::update_boid,
+                (
+                    systems::update_flocking.run_if(not(debug::flag_is_on::<VisionConeFlagLabel>)),
+                    systems::update_flocking_with_vision_cone
+                        .run_if(debug::flag_is_on::<VisionConeFlagLabel>),
+                )
+                    .in_set(FlockingSystem),
+            ),
+        );
+    }
+}
+
+/// Computes the velocity every member of a flock wants. Run what reads
+/// [`Flocking::velocity`] after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FlockingSystem;
+
+/// Make `members` a flock led by the first one, the others keep their slot in
+/// `formation`.
+pub fn form_squad(
+    commands: &mut Commands,
+    members: &[Entity],
+    formation: Formation,
+    weights: FlockWeights,
+) {
+    let Some(&leader) = members.first() else {
+        return;
+    };
+
+    for (i, member) in members.iter().enumerate() {
+        // The leader has no slot of its own
+        let slot = i.saturating_sub(1);
+        commands.entity(*member).insert(Flocking::new(
+            leader,
+            FlockMode::Formation { formation, slot },
+            weights,
+        ));
     }
 }
 
@@ -1,97 +1,124 @@
-use std::{cell::RefCell, collections::HashMap};
+use super::components::{BoidLabel, FlockMode, FlockWeights, Flocking};
+use crate::game::assets::groups;
+use crate::game::screen_bounds::Viewport;
+use crate::misc::rapier_extension;
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_rapier2d::prelude::*;
 
-use super::components::BoidLabel;
-use bevy::{prelude::*, window::PrimaryWindow};
-use bevy_rapier2d::prelude::Velocity;
+/// Where a boid is and where it is going.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Boid {
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+/// The velocity `boid` wants from separation, alignment and cohesion with its
+/// neighbours. Neighbours too close are only steered away from.
+///
+/// boids: https://vanhunteradams.com/Pico/Animal_Movement/Boids-algorithm.html
+pub fn flock_velocity(
+    boid: Boid,
+    neighbours: impl IntoIterator<Item = Boid>,
+    weights: &FlockWeights,
+) -> Vec2 {
+    let mut close = Vec2::ZERO;
+    let mut position_sum = Vec2::ZERO;
+    let mut velocity_sum = Vec2::ZERO;
+    let mut count = 0.0;
+
+    for neighbour in neighbours {
+        let diff = boid.position - neighbour.position;
+        let distance = diff.length();
+
+        if distance < weights.separation_radius {
+            // Seperation
+            close += diff;
+        } else if distance < weights.neighbour_radius {
+            // Alignment & cohesion
+            position_sum += neighbour.position;
+            velocity_sum += neighbour.velocity;
+            count += 1.0;
+        }
+    }
+
+    let mut velocity_change = close * weights.separation;
+
+    if count > 0.0 {
+        velocity_change += (velocity_sum / count - boid.velocity) * weights.alignment;
+        velocity_change += (position_sum / count - boid.position) * weights.cohesion;
+    }
 
-const BOID_MAX_SPEED: f32 = 200.0;
-const BOID_MIN_SPEED: f32 = 20.0;
+    boid.velocity + velocity_change
+}
 
-const BOID_MAX_Y: f32 = 200.0;
+/// Only steer away from flock members that are too close.
+fn separation(
+    boid: Boid,
+    neighbours: impl IntoIterator<Item = Boid>,
+    weights: &FlockWeights,
+) -> Vec2 {
+    neighbours
+        .into_iter()
+        .map(|neighbour| boid.position - neighbour.position)
+        .filter(|diff| diff.length() < weights.separation_radius)
+        .sum::<Vec2>()
+        * weights.separation
+}
 
-const FOLLOW_RADIUS: f32 = 200.0;
-const AVOID_RADIUS: f32 = 40.0;
-const AVOID_FACTOR: f32 = 0.05;
-const MATCHING_FACTOR: f32 = 0.05;
-const CENTERING_FACTOR: f32 = 0.0005;
+/// Like `flock_velocity`, at a speed between the min and max speed. Keeps
+/// going the way the boid faces rather than stop.
+fn swarm_velocity(
+    boid: Boid,
+    neighbours: impl IntoIterator<Item = Boid>,
+    weights: &FlockWeights,
+    forward: Vec2,
+) -> Vec2 {
+    let wanted = flock_velocity(boid, neighbours, weights);
+    let speed = wanted.length().clamp(weights.min_speed, weights.max_speed);
 
-#[derive(Debug, PartialEq, Default)]
-struct BoidCompute {
-    close: Vec2,
-    velocity_sum: Vec2,
-    position_sum: Vec2,
-    neighbors: f32,
+    wanted.try_normalize().unwrap_or(forward) * speed
 }
 
-// boids: https://vanhunteradams.com/Pico/Animal_Movement/Boids-algorithm.html
+/// Decorative boids, wrapping around the edges of the window.
 pub fn update_boid(
     time: Res<Time>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    viewport: Viewport,
     mut boid_query: Query<(Entity, &mut Transform, &mut Velocity), With<BoidLabel>>,
 ) {
-    let window = window_query.single();
-    let max_y = window.height() / 2.0;
+    let max_y = viewport.height() / 2.0;
     let min_y = -max_y;
-    let max_x = window.width() / 2.0;
+    let max_x = viewport.width() / 2.0;
     let min_x = -max_x;
 
-    let mut compute_table = HashMap::<Entity, RefCell<BoidCompute>>::default();
-
-    // Apply the computation
-    for [boid_1, boid_2] in boid_query.iter_combinations() {
-        let diff = (boid_1.1.translation - boid_2.1.translation).truncate();
-        let distance = diff.length();
-
-        if distance < FOLLOW_RADIUS {
-            let (compute1_cell, compute2_cell) =
-                get_compute(&boid_1.0, &boid_2.0, &mut compute_table);
-
-            let compute1 = &mut *compute1_cell.borrow_mut();
-            let compute2 = &mut *compute2_cell.borrow_mut();
-
-            if distance < AVOID_RADIUS {
-                // Seperation
-                compute1.close += diff;
-                compute2.close -= diff;
-            } else {
-                // Alignment
-                compute1.position_sum += boid_2.1.translation.truncate();
-                compute2.position_sum += boid_1.1.translation.truncate();
-                compute1.velocity_sum += boid_2.2.linvel;
-                compute2.velocity_sum += boid_1.2.linvel;
-                compute1.neighbors += 1.0;
-                compute2.neighbors += 1.0;
-            }
-        }
-    }
-
-    // Apply the computation
-    for (entity, mut t, mut v) in boid_query.iter_mut() {
-        if let Some(compute_cell) = compute_table.get(&entity) {
-            let compute = compute_cell.borrow();
-
-            let mut velocity_change = compute.close * AVOID_FACTOR;
+    let weights = FlockWeights::default();
+    let boids: Vec<(Entity, Boid)> = boid_query
+        .iter()
+        .map(|(entity, transform, velocity)| {
+            (
+                entity,
+                Boid {
+                    position: transform.translation.xy(),
+                    velocity: velocity.linvel,
+                },
+            )
+        })
+        .collect();
 
-            if compute.neighbors > 0.0 {
-                velocity_change +=
-                    ((compute.velocity_sum / compute.neighbors) - v.linvel) * MATCHING_FACTOR;
-
-                velocity_change += ((compute.position_sum / compute.neighbors)
-                    - t.translation.truncate())
-                    * CENTERING_FACTOR;
-            }
-
-            v.linvel += velocity_change;
-        }
+    for ((entity, mut t, mut v), (_, boid)) in boid_query.iter_mut().zip(boids.iter()) {
+        let neighbours = boids
+            .iter()
+            .filter(|(other, _)| *other != entity)
+            .map(|(_, other)| *other);
+        v.linvel = flock_velocity(*boid, neighbours, &weights);
 
         let speed = v.linvel.length();
 
         if speed == 0.0 {
-            v.linvel = Vec2::Y * BOID_MAX_SPEED;
-        } else if speed < BOID_MIN_SPEED {
-            v.linvel = v.linvel.normalize() * BOID_MIN_SPEED;
-        } else if speed > BOID_MAX_SPEED {
-            v.linvel = v.linvel.normalize() * BOID_MAX_SPEED;
+            v.linvel = Vec2::Y * weights.max_speed;
+        } else if speed < weights.min_speed {
+            v.linvel = v.linvel.normalize() * weights.min_speed;
+        } else if speed > weights.max_speed {
+            v.linvel = v.linvel.normalize() * weights.max_speed;
         }
 
         // Update position
@@ -101,7 +128,7 @@ pub fn update_boid(
         let new_rotation = Quat::from_rotation_z(Vec2::Y.angle_between(v.linvel));
 
         if new_rotation.angle_between(t.rotation) < 0.1 {
-            v.linvel = (v.linvel * 1.1).clamp_length(BOID_MIN_SPEED, BOID_MAX_SPEED)
+            v.linvel = (v.linvel * 1.1).clamp_length(weights.min_speed, weights.max_speed)
         }
 
         t.rotation = new_rotation;
@@ -120,22 +147,123 @@ pub fn update_boid(
     }
 }
 
-// This is wrong right? The the returned reference is only valid until insert is called on the HashMap...
-// Or... The values live... Like you can't remove the entity from the HashMap without dropping the value...
-fn get_compute<'a>(
-    entity1: &Entity,
-    entity2: &Entity,
-    compute_table: &'a mut HashMap<Entity, RefCell<BoidCompute>>,
-) -> (&'a RefCell<BoidCompute>, &'a RefCell<BoidCompute>) {
-    if !compute_table.contains_key(entity1) {
-        compute_table.insert(*entity1, RefCell::new(BoidCompute::default()));
+type FlockingQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Flocking,
+        &'static Transform,
+        &'static Velocity,
+    ),
+>;
+
+pub fn update_flocking(mut flocking_query: FlockingQuery, rapier_context: Res<RapierContext>) {
+    steer_flocks(&mut None, &mut flocking_query, &rapier_context);
+}
+
+/// Same as `update_flocking` but also draws the paths checked for meteors.
+pub fn update_flocking_with_vision_cone(
+    gizmos: Gizmos,
+    mut flocking_query: FlockingQuery,
+    rapier_context: Res<RapierContext>,
+) {
+    steer_flocks(&mut Some(gizmos), &mut flocking_query, &rapier_context);
+}
+
+/// Works in arena coordinates, members can be anywhere.
+fn steer_flocks(
+    giz: &mut Option<Gizmos>,
+    flocking_query: &mut FlockingQuery,
+    rapier_context: &RapierContext,
+) {
+    struct Member {
+        entity: Entity,
+        flock: Entity,
+        boid: Boid,
+        rotation: Quat,
     }
 
-    if !compute_table.contains_key(entity2) {
-        compute_table.insert(*entity2, RefCell::new(BoidCompute::default()));
+    let members: Vec<Member> = flocking_query
+        .iter()
+        .map(|(entity, flocking, transform, velocity)| Member {
+            entity,
+            flock: flocking.flock,
+            boid: Boid {
+                position: transform.translation.xy(),
+                velocity: velocity.linvel,
+            },
+            rotation: transform.rotation,
+        })
+        .collect();
+
+    for (entity, mut flocking, transform, velocity) in flocking_query.iter_mut() {
+        let boid = Boid {
+            position: transform.translation.xy(),
+            velocity: velocity.linvel,
+        };
+        let weights = flocking.weights;
+        let neighbours = members
+            .iter()
+            .filter(|member| member.flock == flocking.flock && member.entity != entity)
+            .map(|member| member.boid);
+        let forward = (transform.rotation * Vec3::Y).xy();
+
+        let wanted = match flocking.mode {
+            FlockMode::Swarm => Some(swarm_velocity(boid, neighbours, &weights, forward)),
+            FlockMode::Formation { formation, slot } => {
+                let leader = members
+                    .iter()
+                    .find(|member| member.entity == flocking.flock);
+
+                match leader {
+                    // The leader goes its own way
+                    Some(leader) if leader.entity == entity => None,
+                    Some(leader) => {
+                        let offset =
+                            (leader.rotation * formation.slot_offset(slot).extend(0.0)).xy();
+                        let slot_position = leader.boid.position + offset;
+                        let wanted = leader.boid.velocity
+                            + (slot_position - boid.position) * weights.formation
+                            + separation(boid, neighbours, &weights);
+
+                        Some(wanted.clamp_length_max(weights.max_speed))
+                    }
+                    // The leader is gone, break formation and swarm
+                    None => Some(swarm_velocity(boid, neighbours, &weights, forward)),
+                }
+            }
+        };
+
+        let wanted = match (wanted, flocking.avoidance) {
+            (Some(wanted), Some(avoidance)) if wanted != Vec2::ZERO => {
+                let check_transform = Transform::from_translation(transform.translation)
+                    .with_rotation(Quat::from_rotation_z(Vec2::Y.angle_between(wanted)));
+                // Seen as a ship, so that only meteors are obstacles
+                let filter = QueryFilter::new()
+                    .exclude_sensors()
+                    .groups(CollisionGroups::new(
+                        groups::SHIP_GROUPS,
+                        groups::METEOR_GROUP,
+                    ));
+
+                let path = rapier_extension::find_unobstructed_path(
+                    rapier_context,
+                    giz,
+                    &check_transform,
+                    10.0,
+                    avoidance.inner_distance,
+                    avoidance.outer_distance,
+                    avoidance.width,
+                    filter,
+                    None,
+                );
+                // Boxed in, keep going and hope for the best
+                Some(path.map_or(wanted, |path| path * wanted.length()))
+            }
+            (wanted, _) => wanted,
+        };
+
+        flocking.set_velocity(wanted);
     }
-    return (
-        compute_table.get(entity1).unwrap(),
-        compute_table.get(entity2).unwrap(),
-    );
 }
@@ -10,13 +10,13 @@
 use std::collections::HashSet;
 
 use super::config::Flag;
+use crate::settings::keybindings::{Binding, Bindings, Keybindings, Rebindable};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::DebugRenderContext;
 use clap::ValueEnum;
-use leafwing_input_manager::input_map::InputMap;
 use leafwing_input_manager::plugin::InputManagerPlugin;
 use leafwing_input_manager::prelude::ActionState;
-use leafwing_input_manager::user_input::InputKind;
 use leafwing_input_manager::{Actionlike, InputManagerBundle};
 use serde::{Deserialize, Serialize};
 
@@ -58,6 +58,11 @@ impl Plugin for DebugPlugin {
                 "Vision Cone",
                 "Display the vision cone",
                 self.visual_debug.contains(&VisualDebug::VisionCone),
+            ))
+            .insert_resource(Flag::<ShipAiFlagLabel>::new(
+                "Ship AI",
+                "Display the active behaviour of enemy ships",
+                self.visual_debug.contains(&VisualDebug::ShipAi),
             ));
     }
 }
@@ -77,38 +82,31 @@ pub enum VisualDebug {
     Render,
     FPSCounter,
     VisionCone,
+    ShipAi,
 }
 
-pub fn create_input_map() -> InputMap<VisualDebug> {
-    // Create an `InputMap` to add default inputs to
-    let mut input_map: InputMap<VisualDebug> = InputMap::default();
-
-    // Add Debug inputs
-    input_map.insert_multiple(vec![
-        (
-            InputKind::Keyboard(KeyCode::F1),
-            VisualDebug::BackgroundGrid,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::F2),
-            VisualDebug::CameraPosition,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::F3),
-            VisualDebug::CameraSetpoint,
-        ),
-        (InputKind::Keyboard(KeyCode::F4), VisualDebug::Render),
-        (InputKind::Keyboard(KeyCode::F5), VisualDebug::FPSCounter),
-        (InputKind::Keyboard(KeyCode::F6), VisualDebug::VisionCone),
-    ]);
-
-    input_map.build()
+impl Rebindable for VisualDebug {
+    fn default_bindings() -> Bindings<Self> {
+        Bindings::new([
+            (VisualDebug::BackgroundGrid, vec![Binding::Key(KeyCode::F1)]),
+            (VisualDebug::CameraPosition, vec![Binding::Key(KeyCode::F2)]),
+            (VisualDebug::CameraSetpoint, vec![Binding::Key(KeyCode::F3)]),
+            (VisualDebug::Render, vec![Binding::Key(KeyCode::F4)]),
+            (VisualDebug::FPSCounter, vec![Binding::Key(KeyCode::F5)]),
+            (VisualDebug::VisionCone, vec![Binding::Key(KeyCode::F6)]),
+            (VisualDebug::ShipAi, vec![Binding::Key(KeyCode::F7)]),
+        ])
+    }
+
+    fn bindings(keybindings: &Keybindings) -> &Bindings<Self> {
+        &keybindings.debug
+    }
 }
 
 fn create_debug_input(mut commands: Commands) {
     commands.spawn(InputManagerBundle::<VisualDebug> {
         action_state: ActionState::default(),
-        input_map: create_input_map(),
+        input_map: VisualDebug::default_bindings().input_map(),
     });
 }
 
@@ -141,6 +139,11 @@ pub type FPSDebugFlag = Flag<FPSDebugFlagLabel>;
 pub struct VisionConeFlagLabel;
 pub type VisionConeDebugFlag = Flag<VisionConeFlagLabel>;
 
+/// A flag that can be used to enable/disable showing the enemy ship AI.
+#[derive(Debug)]
+pub struct ShipAiFlagLabel;
+pub type ShipAiDebugFlag = Flag<ShipAiFlagLabel>;
+
 ////////////////////////////////////////////////////////////////////////////////
 /// Systems
 ////////////////////////////////////////////////////////////////////////////////
@@ -150,35 +153,59 @@ pub fn flag_is_on<A: Send + Sync + 'static>(flag: Option<Res<Flag<A>>>) -> bool
     flag.map(|flag| flag.is_on()).unwrap_or(false)
 }
 
+/// The flags the debug keys switch.
+#[derive(SystemParam)]
+struct DebugFlags<'w> {
+    background: Option<ResMut<'w, BackgroundGridDebugFlag>>,
+    camera_position: Option<ResMut<'w, CameraPositionDebugFlag>>,
+    camera_setpoint: Option<ResMut<'w, CameraSetpointDebugFlag>>,
+    fps: Option<ResMut<'w, FPSDebugFlag>>,
+    vision_cone: Option<ResMut<'w, VisionConeDebugFlag>>,
+    ship_ai: Option<ResMut<'w, ShipAiDebugFlag>>,
+}
+
 fn debug_keyboard_input(
     debug_action_query: Query<&ActionState<VisualDebug>>,
-    mut background_debug: Option<ResMut<BackgroundGridDebugFlag>>,
-    mut camera_position_debug: Option<ResMut<CameraPositionDebugFlag>>,
-    mut camera_setpoint_debug: Option<ResMut<CameraSetpointDebugFlag>>,
+    mut flags: DebugFlags,
     mut render_debug: Option<ResMut<DebugRenderContext>>,
-    mut fps_debug: Option<ResMut<FPSDebugFlag>>,
-    mut vision_cone_debug: Option<ResMut<VisionConeDebugFlag>>,
 ) {
     if let Ok(input_action) = debug_action_query.get_single() {
         for action in input_action.get_just_pressed() {
             match action {
                 VisualDebug::BackgroundGrid => {
-                    background_debug.as_mut().map(|flag| flag.flip());
+                    if let Some(flag) = flags.background.as_mut() {
+                        flag.flip();
+                    }
                 }
                 VisualDebug::CameraPosition => {
-                    camera_position_debug.as_mut().map(|flag| flag.flip());
+                    if let Some(flag) = flags.camera_position.as_mut() {
+                        flag.flip();
+                    }
                 }
                 VisualDebug::CameraSetpoint => {
-                    camera_setpoint_debug.as_mut().map(|flag| flag.flip());
+                    if let Some(flag) = flags.camera_setpoint.as_mut() {
+                        flag.flip();
+                    }
                 }
                 VisualDebug::Render => {
-                    render_debug.as_mut().map(|ctx| ctx.enabled = !ctx.enabled);
+                    if let Some(ctx) = render_debug.as_mut() {
+                        ctx.enabled = !ctx.enabled;
+                    }
                 }
                 VisualDebug::FPSCounter => {
-                    fps_debug.as_mut().map(|flag| flag.flip());
+                    if let Some(flag) = flags.fps.as_mut() {
+                        flag.flip();
+                    }
                 }
                 VisualDebug::VisionCone => {
-                    vision_cone_debug.as_mut().map(|flag| flag.flip());
+                    if let Some(flag) = flags.vision_cone.as_mut() {
+                        flag.flip();
+                    }
+                }
+                VisualDebug::ShipAi => {
+                    if let Some(flag) = flags.ship_ai.as_mut() {
+                        flag.flip();
+                    }
                 }
             }
         }
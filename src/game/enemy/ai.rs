@@ -1,81 +1,483 @@
+//! # Ship AI
+//!
+//! A small utility AI. Every frame each behaviour a ship knows scores how much
+//! it wants to take over, given what the ship senses, and the one with the
+//! highest score steers the ship. The active behaviour gets a small bonus, so
+//! ships do not flip back and forth between two behaviours that score about
+//! the same.
+
 use bevy::prelude::*;
+use std::fmt;
+
+////////////////////////////////////////////////////////////////////////////////
+// Senses & Steering
+////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Component, Debug, Clone, PartialEq, Eq)]
-pub struct ShipAI {
-    pub state: ShipState,
-    // target_group: Group, //Used to allow turrets to target different groups, player, enemies, etc
+/// What a ship knows when it decides what to do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Senses {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// The direction the ship is facing.
+    pub forward: Vec2,
+    /// From 0 to 1.
+    pub health: f32,
+    pub target: Option<SensedTarget>,
+    /// The center of the other ships of the faction nearby, if there are any.
+    pub allies: Option<Vec2>,
+    /// The velocity that keeps the ship in its squad's formation, if it
+    /// follows a leader.
+    pub formation: Option<Vec2>,
 }
 
-impl Default for ShipAI {
-    fn default() -> Self {
-        Self {
-            state: ShipState::default(),
-            // target_group: Group::default(),
-        }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensedTarget {
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+impl Senses {
+    fn target_offset(&self) -> Option<Vec2> {
+        self.target.map(|target| target.position - self.position)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ShipState {
-    Idle,
-    Targeting { timer: Timer },
-    Fire,
+/// What a behaviour wants the ship to do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Steering {
+    /// The direction to turn the ship to.
+    pub facing: Vec2,
+    /// Impulse per frame, before obstacles are avoided.
+    pub thrust: Vec2,
+    pub fire: bool,
+    /// Where the ship is going, for the debug overlay.
+    pub goal: Option<Vec2>,
 }
 
-impl Default for ShipState {
-    fn default() -> Self {
-        Self::Idle
-    }
+////////////////////////////////////////////////////////////////////////////////
+// Behaviours
+////////////////////////////////////////////////////////////////////////////////
+
+/// The nodes a ship picks from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Behaviour {
+    /// Fly between the waypoints, when there is nothing else to do.
+    Patrol,
+    /// Close in on a target that is out of range.
+    Pursue,
+    /// Circle a target at range while shooting at it.
+    Strafe,
+    /// Get away from the target when badly hurt.
+    Retreat,
+    /// Fly back to the other ships of the faction.
+    Regroup,
+    /// Keep the slot in the squad's formation.
+    Formation,
 }
 
-impl ShipState {
-    pub fn is_firing(&self) -> bool {
+impl Behaviour {
+    pub const ALL: [Behaviour; 6] = [
+        Behaviour::Patrol,
+        Behaviour::Pursue,
+        Behaviour::Strafe,
+        Behaviour::Retreat,
+        Behaviour::Regroup,
+        Behaviour::Formation,
+    ];
+
+    /// How much the behaviour wants to take over, from 0 to 1.
+    pub fn score(&self, senses: &Senses, config: &BrainConfig) -> f32 {
+        let target_distance = senses.target_offset().map(Vec2::length);
+        let in_range = |distance: f32| distance <= config.preferred_range * 1.25;
+
         match self {
-            Self::Fire => true,
-            _ => false,
+            Behaviour::Patrol => 0.1,
+            Behaviour::Pursue => match target_distance {
+                Some(distance) if !in_range(distance) => 0.6,
+                _ => 0.0,
+            },
+            Behaviour::Strafe => match target_distance {
+                Some(distance) if in_range(distance) => 0.7,
+                _ => 0.0,
+            },
+            Behaviour::Retreat => {
+                if senses.target.is_some() && senses.health < config.retreat_health {
+                    0.9
+                } else {
+                    0.0
+                }
+            }
+            Behaviour::Regroup => match senses.allies {
+                // The formation keeps the squad together
+                _ if senses.formation.is_some() => 0.0,
+                Some(allies) if allies.distance(senses.position) > config.regroup_distance => {
+                    // Fighting comes first
+                    if senses.target.is_some() {
+                        0.05
+                    } else {
+                        0.4
+                    }
+                }
+                _ => 0.0,
+            },
+            Behaviour::Formation => {
+                if senses.formation.is_some() {
+                    0.3
+                } else {
+                    0.0
+                }
+            }
         }
     }
 
-    pub fn is_targeting(&self) -> bool {
-        match self {
-            Self::Targeting { .. } => true,
-            _ => false,
+    pub fn steer(&self, senses: &Senses, config: &BrainConfig, patrol: &mut Patrol) -> Steering {
+        let towards = |point: Vec2| (point - senses.position).normalize_or_zero();
+        // Face the direction the ship is going, or keep facing forward
+        let steering = |direction: Vec2, thrust: f32, goal: Vec2| Steering {
+            facing: if direction == Vec2::ZERO {
+                senses.forward
+            } else {
+                direction
+            },
+            thrust: direction * thrust,
+            fire: false,
+            goal: Some(goal),
+        };
+
+        match (self, senses.target) {
+            (Behaviour::Pursue, Some(target)) => {
+                // Lead the target a little
+                let goal = target.position + target.velocity * 0.5;
+                let direction = towards(goal);
+                Steering {
+                    fire: is_aimed(senses.forward, direction),
+                    ..steering(direction, config.thrust, goal)
+                }
+            }
+            (Behaviour::Strafe, Some(target)) => {
+                let offset = target.position - senses.position;
+                let direction = offset.normalize_or_zero();
+                // Keep circling the way the ship is already going
+                let mut tangent = direction.perp();
+                if tangent.dot(senses.velocity) < 0.0 {
+                    tangent = -tangent;
+                }
+                let correction =
+                    (offset.length() - config.preferred_range) / config.preferred_range;
+                let thrust = (tangent + direction * correction).normalize_or_zero();
+
+                Steering {
+                    facing: direction,
+                    thrust: thrust * config.thrust * 0.8,
+                    fire: is_aimed(senses.forward, direction),
+                    goal: Some(target.position),
+                }
+            }
+            (Behaviour::Retreat, Some(target)) => {
+                let direction = -towards(target.position);
+                steering(direction, config.thrust, target.position)
+            }
+            (Behaviour::Formation, _) => match senses.formation {
+                Some(wanted) => {
+                    // Face where the squad is going, once it gets going
+                    let facing = if wanted.length() > FORMATION_CRUISE_SPEED {
+                        wanted.normalize()
+                    } else {
+                        senses.forward
+                    };
+                    Steering {
+                        facing,
+                        thrust: ((wanted - senses.velocity) * FORMATION_GAIN)
+                            .clamp_length_max(config.thrust),
+                        fire: false,
+                        goal: Some(senses.position + wanted),
+                    }
+                }
+                None => patrol.steer(senses, config),
+            },
+            (Behaviour::Regroup, _) => match senses.allies {
+                Some(allies) => steering(towards(allies), config.thrust * 0.8, allies),
+                None => patrol.steer(senses, config),
+            },
+            _ => patrol.steer(senses, config),
         }
     }
-    pub fn is_idle(&self) -> bool {
+}
+
+impl fmt::Display for Behaviour {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Idle => true,
-            _ => false,
+            Behaviour::Patrol => write!(f, "Patrol"),
+            Behaviour::Pursue => write!(f, "Pursue"),
+            Behaviour::Strafe => write!(f, "Strafe"),
+            Behaviour::Retreat => write!(f, "Retreat"),
+            Behaviour::Regroup => write!(f, "Regroup"),
+            Behaviour::Formation => write!(f, "Formation"),
         }
     }
+}
 
-    pub fn update(&mut self, time: &Time, has_target: bool) {
-        match self {
-            Self::Idle => {
-                if has_target {
-                    *self = Self::Targeting {
-                        timer: Timer::from_seconds(1.0, TimerMode::Once),
-                    };
-                }
+/// Thrust per unit of velocity the ship is off from its formation.
+const FORMATION_GAIN: f32 = 0.02;
+/// Slower than this the squad is holding still.
+const FORMATION_CRUISE_SPEED: f32 = 20.0;
+
+/// Only shoot when pointing at the target.
+fn is_aimed(forward: Vec2, direction: Vec2) -> bool {
+    forward.dot(direction) > 0.95
+}
+
+/// Waypoints flown in order, over and over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Patrol {
+    pub waypoints: Vec<Vec2>,
+    next: usize,
+}
+
+/// Close enough to a waypoint to go on to the next one.
+const WAYPOINT_RADIUS: f32 = 60.0;
+
+impl Patrol {
+    pub fn new(waypoints: Vec<Vec2>) -> Self {
+        Self { waypoints, next: 0 }
+    }
+
+    /// A square with sides of twice `radius` around `center`.
+    pub fn around(center: Vec2, radius: f32, rotation: f32) -> Self {
+        let corners = [
+            Vec2::new(1.0, 1.0),
+            Vec2::new(-1.0, 1.0),
+            Vec2::new(-1.0, -1.0),
+            Vec2::new(1.0, -1.0),
+        ];
+        let rotation = Vec2::from_angle(rotation);
+
+        Self::new(
+            corners
+                .iter()
+                .map(|corner| center + rotation.rotate(*corner * radius))
+                .collect(),
+        )
+    }
+
+    pub fn next_waypoint(&self) -> Option<Vec2> {
+        self.waypoints.get(self.next).copied()
+    }
+
+    fn steer(&mut self, senses: &Senses, config: &BrainConfig) -> Steering {
+        if let Some(waypoint) = self.next_waypoint() {
+            if waypoint.distance(senses.position) < WAYPOINT_RADIUS {
+                self.next = (self.next + 1) % self.waypoints.len();
             }
-            Self::Targeting { timer } => {
-                if has_target {
-                    if timer.tick(time.delta()).just_finished() {
-                        *self = Self::Fire;
-                    }
-                } else {
-                    *self = Self::Idle;
+        }
+
+        match self.next_waypoint() {
+            Some(waypoint) => {
+                let direction = (waypoint - senses.position).normalize_or_zero();
+                Steering {
+                    facing: direction,
+                    thrust: direction * config.thrust * 0.5,
+                    fire: false,
+                    goal: Some(waypoint),
                 }
             }
-            Self::Fire => {
-                if has_target {
-                    *self = Self::Targeting {
-                        timer: Timer::from_seconds(1.0, TimerMode::Once),
-                    };
-                } else {
-                    *self = Self::Idle;
-                }
+            // Drift on
+            None => Steering {
+                facing: senses.forward,
+                thrust: Vec2::ZERO,
+                fire: false,
+                goal: None,
+            },
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Brain
+////////////////////////////////////////////////////////////////////////////////
+
+/// The knobs of a ship's behaviours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrainConfig {
+    /// The distance to keep from the target while strafing.
+    pub preferred_range: f32,
+    /// Retreat below this fraction of health.
+    pub retreat_health: f32,
+    /// Regroup when further than this from the allies.
+    pub regroup_distance: f32,
+    /// Impulse per frame at full thrust.
+    pub thrust: f32,
+    /// Seconds a target is followed after it was last seen.
+    pub memory: f32,
+}
+
+impl Default for BrainConfig {
+    fn default() -> Self {
+        Self {
+            preferred_range: 300.0,
+            retreat_health: 0.4,
+            regroup_distance: 600.0,
+            thrust: 1.0,
+            memory: 3.0,
+        }
+    }
+}
+
+/// Picks a behaviour for the ship and remembers what it has seen.
+#[derive(Component, Debug, Clone)]
+pub struct ShipBrain {
+    pub behaviours: Vec<Behaviour>,
+    pub config: BrainConfig,
+    pub patrol: Patrol,
+    active: Behaviour,
+    /// The hostile being followed, and how long until it is forgotten.
+    target: Option<(Entity, Timer)>,
+    /// Pushes away from what is in the way, set by the vision cone.
+    pub avoidance: Vec2,
+    /// What the active behaviour wants, from the last frame.
+    pub steering: Option<Steering>,
+}
+
+/// The active behaviour is kept unless another one beats it by this much.
+const ACTIVE_BONUS: f32 = 0.05;
+
+impl ShipBrain {
+    pub fn new(behaviours: Vec<Behaviour>, config: BrainConfig, patrol: Patrol) -> Self {
+        Self {
+            behaviours,
+            config,
+            patrol,
+            active: Behaviour::Patrol,
+            target: None,
+            avoidance: Vec2::ZERO,
+            steering: None,
+        }
+    }
+
+    pub fn active(&self) -> Behaviour {
+        self.active
+    }
+
+    pub fn target(&self) -> Option<Entity> {
+        self.target.as_ref().map(|(entity, _)| *entity)
+    }
+
+    /// The target was seen, remember it for a while.
+    pub fn see(&mut self, target: Entity) {
+        self.target = Some((
+            target,
+            Timer::from_seconds(self.config.memory, TimerMode::Once),
+        ));
+    }
+
+    /// Forget the target once it has not been seen for a while.
+    pub fn tick_memory(&mut self, time: &Time) {
+        if let Some((_, timer)) = &mut self.target {
+            if timer.tick(time.delta()).finished() {
+                self.target = None;
             }
         }
     }
+
+    pub fn forget(&mut self) {
+        self.target = None;
+    }
+
+    /// Pick the behaviour with the highest score and let it steer.
+    pub fn think(&mut self, senses: &Senses) -> Steering {
+        let config = self.config;
+        let active = self.active;
+
+        self.active = self
+            .behaviours
+            .iter()
+            .map(|behaviour| {
+                let bonus = if *behaviour == active {
+                    ACTIVE_BONUS
+                } else {
+                    0.0
+                };
+                (*behaviour, behaviour.score(senses, &config) + bonus)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(Behaviour::Patrol, |(behaviour, _)| behaviour);
+
+        let steering = self.active.steer(senses, &config, &mut self.patrol);
+        self.steering = Some(steering);
+        steering
+    }
+}
+
+impl Default for ShipBrain {
+    fn default() -> Self {
+        Self::new(
+            Behaviour::ALL.to_vec(),
+            BrainConfig::default(),
+            Patrol::new(Vec::new()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn senses(health: f32, target: Option<Vec2>) -> Senses {
+        Senses {
+            position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            forward: Vec2::Y,
+            health,
+            target: target.map(|position| SensedTarget {
+                position,
+                velocity: Vec2::ZERO,
+            }),
+            allies: None,
+            formation: None,
+        }
+    }
+
+    #[test]
+    fn test_brain_picks_behaviour_for_the_situation() {
+        let mut brain = ShipBrain::default();
+
+        brain.think(&senses(1.0, None));
+        assert_eq!(brain.active(), Behaviour::Patrol);
+
+        brain.think(&senses(1.0, Some(Vec2::Y * 1000.0)));
+        assert_eq!(brain.active(), Behaviour::Pursue);
+
+        let steering = brain.think(&senses(1.0, Some(Vec2::Y * 250.0)));
+        assert_eq!(brain.active(), Behaviour::Strafe);
+        assert!(steering.fire);
+
+        let steering = brain.think(&senses(0.1, Some(Vec2::Y * 250.0)));
+        assert_eq!(brain.active(), Behaviour::Retreat);
+        assert!(steering.thrust.y < 0.0);
+
+        // Followers keep formation until there is something to fight
+        let mut follower = senses(1.0, None);
+        follower.formation = Some(Vec2::X * 100.0);
+        let steering = brain.think(&follower);
+        assert_eq!(brain.active(), Behaviour::Formation);
+        assert!(steering.thrust.x > 0.0);
+
+        follower.target = senses(1.0, Some(Vec2::Y * 1000.0)).target;
+        brain.think(&follower);
+        assert_eq!(brain.active(), Behaviour::Pursue);
+    }
+
+    #[test]
+    fn test_patrol_goes_round_the_waypoints() {
+        let mut patrol = Patrol::around(Vec2::ZERO, 100.0, 0.0);
+        let config = BrainConfig::default();
+        let first = patrol.next_waypoint().unwrap();
+
+        let mut at_waypoint = senses(1.0, None);
+        at_waypoint.position = first;
+        let steering = patrol.steer(&at_waypoint, &config);
+
+        assert_ne!(patrol.next_waypoint(), Some(first));
+        assert_eq!(steering.goal, patrol.next_waypoint());
+    }
 }
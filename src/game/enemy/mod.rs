@@ -2,33 +2,47 @@
 //!
 //! ## Behavior
 //!
-//! The movement works with attraction and repulsion forces. The enemy ship is
-//! attracted to the player ship and repulsed non-player entities. The enemy sees in a cone
-//! in front of it. If the player is in the cone, the enemy will move towards the player.
+//! Enemy ships are driven by a small utility AI, see [`ai`]. The ship sees in a
+//! cone in front of it. Every frame each of its behaviours scores how much it
+//! wants to take over, and the best one turns the ship with its
+//! `DirectionControl` and pushes it with its `ExternalImpulse`.
 //!
-//! **Rules:**
-//! - Moves forward
-//! - If there are more then one enemy entity, move towards the first one that was seen (the influence is constant).
-//! - If an entity is in the view cone (includes enemies), move away from it (the influence: -1 / distance^2).
-//! - If there is an enemy entity in the view cone, shoot.
+//! **Behaviours:**
+//! - Patrol: fly between waypoints around where the ship spawned.
+//! - Pursue: close in on the closest hostile seen, shooting when aimed.
+//! - Strafe: circle the target at range while shooting at it.
+//! - Retreat: get away from the target when health is low.
+//! - Regroup: fly back to the other ships of the faction.
+//! - Formation: keep the slot in the squad, when following a leader, see
+//!   `boids::Flocking`.
 //!
+//! Whatever else is in the vision cone (meteors, allies) pushes the ship away
+//! (the influence: 1 / distance).
 //!
+//! Hit `F7` to show the active behaviour of every ship.
 
 mod ai;
 
+pub use ai::{Behaviour, BrainConfig, Patrol, ShipBrain};
+
 use super::assets;
-use super::assets::groups;
+use super::boids::{self, FlockWeights, Flocking, FlockingSystem, Formation};
 use super::control_system::DirectionControl;
-use super::debug::VisionConeDebugFlag;
+use super::debug::{self, ShipAiFlagLabel, VisionConeFlagLabel};
+use super::faction::{Faction, FactionBody, Hostility};
 use super::game_entity::Enemy;
 use super::game_entity::GameEntityType;
+use super::particles::{self, EmitterSource, ParticlePreset};
 use super::player::components::Player;
+use super::rng::{GameRng, RngStream};
 use super::vitality::Health;
 use super::weapon::Weapon;
 use crate::misc::rapier_extension;
-use bevy::prelude::*;
-use bevy_rapier2d::geometry::*;
+use crate::parent_child_no_rotation::{NoRotationChild, NoRotationParent};
+use ai::{SensedTarget, Senses};
+use bevy::{math::Vec3Swizzles, prelude::*};
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
 use rand_distr;
 use rand_distr::Distribution;
 use rand_distr::Poisson;
@@ -42,7 +56,25 @@ pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_enemy);
+        app.add_systems(
+            Update,
+            (
+                (
+                    perceive.run_if(not(debug::flag_is_on::<VisionConeFlagLabel>)),
+                    perceive_with_vision_cone.run_if(debug::flag_is_on::<VisionConeFlagLabel>),
+                ),
+                think.after(FlockingSystem),
+                fire_weapon,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                update_ai_labels,
+                draw_ai_goals.run_if(debug::flag_is_on::<ShipAiFlagLabel>),
+            ),
+        );
     }
 }
 
@@ -57,30 +89,26 @@ struct ShootTimer {
 
 const POISSON_LAMBDA: f32 = 1.0;
 
-impl Default for ShootTimer {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl ShootTimer {
-    pub fn new() -> Self {
-        let seconds = Poisson::new(POISSON_LAMBDA)
-            .unwrap()
-            .sample(&mut rand::thread_rng());
+    pub fn new<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let seconds = Poisson::new(POISSON_LAMBDA).unwrap().sample(rng);
 
         Self {
             timer: Timer::from_seconds(seconds, TimerMode::Once),
         }
     }
 
-    pub fn update(&mut self, time: &Time) -> bool {
+    pub fn update<R>(&mut self, time: &Time, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
         let completed = self.timer.tick(time.delta()).just_finished();
 
         if completed {
-            let seconds = Poisson::new(POISSON_LAMBDA)
-                .unwrap()
-                .sample(&mut rand::thread_rng());
+            let seconds = Poisson::new(POISSON_LAMBDA).unwrap().sample(rng);
             self.timer = Timer::from_seconds(seconds, TimerMode::Once);
         }
 
@@ -88,61 +116,6 @@ impl ShootTimer {
     }
 }
 
-#[derive(Component, Debug)]
-struct ShipNavigationSystem {
-    /// If present the ship will be incentivized to be close to the guard point
-    ///
-    /// The first element is the point, and the second is the influence. A higher
-    /// influence means the ship will be more incentivized to be close to the point,
-    /// and a lower influence means the ship will be less incentivized to be close to it.
-    ///
-    guard_point: Option<(Vec2, f32)>,
-
-    /// For each frame we calculate a rolling average of the influence vectors
-    /// This is to smooth out the influence vector, so the ship does not jitter
-    /// to much.
-    average_influence: Vec2,
-}
-
-impl Default for ShipNavigationSystem {
-    fn default() -> Self {
-        Self {
-            guard_point: None,
-            average_influence: Vec2::ZERO,
-        }
-    }
-}
-
-impl ShipNavigationSystem {
-    /// Returns the influence vector for the ship
-    fn influence_vector(&self) -> Vec2 {
-        self.average_influence
-    }
-
-    /// Sets the guard point for the ship
-    fn set_guard_point(&mut self, point: Vec2, influence: f32) {
-        self.guard_point = Some((point, influence));
-    }
-
-    /// Clears the guard point for the ship
-    fn clear_guard_point(&mut self) {
-        self.guard_point = None;
-    }
-
-    /// Update and return the influence vector for the ship
-    fn update(&mut self, transform: &Transform, influence: &Vec2) -> Vec2 {
-        let position = transform.translation.truncate();
-        let forward_bias = (transform.rotation * Vec3::Y).truncate();
-        self.average_influence = (self.average_influence + *influence + forward_bias) / 2.0;
-
-        if let Some((guard_point, influence)) = self.guard_point {
-            return position.distance(guard_point) * influence + self.average_influence;
-        } else {
-            return self.average_influence;
-        }
-    }
-}
-
 #[derive(Component, Debug)]
 struct Tracking {
     entities: Vec<Entity>,
@@ -163,95 +136,266 @@ struct VisionDonutSegment {
 // Systems
 ////////////////////////////////////////////////////////////////////////////////
 
-// fn update_enemy_shooting(
-//     time: Res<Time>,
-//     asset_db: Res<AssetDB>,
-//     asset_server: Res<AssetServer>,
-//     mut commands: Commands,
-//     mut query: Query<(&mut ShootTimer, &Transform, &mut Weapon)>,
-// ) {
-//     for (mut shoot_timer, transform, mut weapon) in query.iter_mut() {
-//         if shoot_timer.update(&time) {
-//             let position = transform.translation.truncate();
-//             let direction = (transform.rotation * Vec3::Y).truncate();
-
-//             weapon.fire(&mut commands, &asset_db, &asset_server, *transform);
-//         }
-//     }
-// }
-
-fn update_enemy(
-    vision_cone_debug: Res<VisionConeDebugFlag>,
-    gizmos: Gizmos, // TODO: expensive to pass this around?
-    mut ship_query: Query<
-        (
-            &mut ExternalImpulse,
-            &Velocity,
-            &Transform,
-            &VisionDonutSegment,
-            &mut DirectionControl,
-            &mut ShipNavigationSystem,
-        ),
-        (With<EnemyShipLabel>, Without<Player>),
-    >,
-    player_query: Query<&Player>,
+type PerceptionQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut ShipBrain,
+        &'static Transform,
+        &'static Velocity,
+        &'static VisionDonutSegment,
+        &'static Faction,
+    ),
+    (With<EnemyShipLabel>, Without<Player>),
+>;
+
+fn perceive(
+    mut ship_query: PerceptionQuery,
+    hostility: Hostility,
     rapier_context: Res<RapierContext>,
+    time: Res<Time>,
 ) {
-    let mut giz = if vision_cone_debug.is_on() {
-        Some(gizmos)
-    } else {
-        None
-    };
+    look_around(
+        &mut None,
+        &mut ship_query,
+        &hostility,
+        &rapier_context,
+        &time,
+    );
+}
 
-    for (
-        mut enemy_impulse,
-        velocity,
-        enemy_transform,
-        vision_donut_segment,
-        mut direction_control,
-        mut ship_navigation_system,
-    ) in ship_query.iter_mut()
-    {
-        // Bias the influence vector towards the direction the enemy is facing
-        // let (_, _, current_angle) = enemy_transform.rotation.to_euler(EulerRot::XYZ);
-        let mut influence_vector = Vec2::ZERO;
+/// Same as `perceive` but also draws the vision cone of every ship.
+fn perceive_with_vision_cone(
+    gizmos: Gizmos,
+    mut ship_query: PerceptionQuery,
+    hostility: Hostility,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    look_around(
+        &mut Some(gizmos),
+        &mut ship_query,
+        &hostility,
+        &rapier_context,
+        &time,
+    );
+}
+
+/// Remember the closest hostile in the vision cone, and steer clear of
+/// everything else in it.
+fn look_around(
+    giz: &mut Option<Gizmos>,
+    ship_query: &mut PerceptionQuery,
+    hostility: &Hostility,
+    rapier_context: &RapierContext,
+    time: &Time,
+) {
+    for (mut brain, transform, velocity, vision_donut_segment, faction) in ship_query.iter_mut() {
+        brain.tick_memory(time);
 
-        // Find every entity in the vision cone
-        let angel = vision_donut_segment.angle * (velocity.linvel.length() / 200.0).clamp(0.0, 1.0);
+        // The cone narrows when slow, but never closes
+        let speed_factor = (velocity.linvel.length() / 200.0).clamp(0.25, 1.0);
 
         let visible_entities = rapier_extension::cast_vision_cone(
-            &rapier_context,
-            &mut giz,
-            &enemy_transform,
+            rapier_context,
+            giz,
+            transform,
             vision_donut_segment.ray_angel_density,
             vision_donut_segment.inner_distance,
             vision_donut_segment.outer_distance,
-            angel,
+            vision_donut_segment.angle * speed_factor,
         );
 
+        let ship_position = transform.translation.xy();
+        let mut avoidance = Vec2::ZERO;
+        let mut closest_hostile: Option<(Entity, f32)> = None;
+
         for (visible_entity, visible_position) in visible_entities {
-            // If the entity is the player, move towards it
-            let ship_position = enemy_transform.translation.truncate();
             let distance = visible_position.distance(ship_position);
-            let direction = (ship_position - visible_position).normalize();
-            let influence = 100.0 / distance;
-
-            let vel = direction * influence;
 
-            if player_query.contains(visible_entity) {
-                influence_vector -= vel;
-            } else {
-                influence_vector += vel;
+            if hostility.is_hostile(*faction, visible_entity) {
+                if closest_hostile.map_or(true, |(_, closest)| distance < closest) {
+                    closest_hostile = Some((visible_entity, distance));
+                }
+            } else if let Some(away) = (ship_position - visible_position).try_normalize() {
+                avoidance += away * 100.0 / distance;
             }
         }
 
-        let final_influence = ship_navigation_system.update(&enemy_transform, &influence_vector);
+        brain.avoidance = avoidance;
+        if let Some((hostile, _)) = closest_hostile {
+            brain.see(hostile);
+        }
+    }
+}
+
+type ThinkingQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut ShipBrain,
+        &'static Transform,
+        &'static Velocity,
+        &'static Health,
+        &'static Faction,
+        &'static mut DirectionControl,
+        &'static mut ExternalImpulse,
+        Option<&'static Flocking>,
+    ),
+    (With<EnemyShipLabel>, Without<Player>),
+>;
+
+/// Other ships of the faction further away than this are not regrouped with.
+const ALLY_RANGE: f32 = 1500.0;
+
+/// Let the best behaviour of every ship turn and push it.
+fn think(
+    mut ship_query: ThinkingQuery,
+    ally_query: Query<(Entity, &Transform, &Faction), With<EnemyShipLabel>>,
+    target_query: Query<(&Transform, Option<&Velocity>)>,
+) {
+    for (
+        entity,
+        mut brain,
+        transform,
+        velocity,
+        health,
+        faction,
+        mut direction_control,
+        mut impulse,
+        flocking,
+    ) in ship_query.iter_mut()
+    {
+        let position = transform.translation.xy();
+
+        let target = brain.target().and_then(|target| {
+            let (target_transform, target_velocity) = target_query.get(target).ok()?;
+            Some(SensedTarget {
+                position: target_transform.translation.xy(),
+                velocity: target_velocity.map_or(Vec2::ZERO, |velocity| velocity.linvel),
+            })
+        });
+        // The target is gone
+        if target.is_none() {
+            brain.forget();
+        }
+
+        let (ally_sum, ally_count) = ally_query
+            .iter()
+            .filter(|(ally, _, ally_faction)| *ally != entity && *ally_faction == faction)
+            .map(|(_, ally_transform, _)| ally_transform.translation.xy())
+            .filter(|ally_position| ally_position.distance(position) < ALLY_RANGE)
+            .fold((Vec2::ZERO, 0), |(sum, count), ally_position| {
+                (sum + ally_position, count + 1)
+            });
+
+        let senses = Senses {
+            position,
+            velocity: velocity.linvel,
+            forward: (transform.rotation * Vec3::Y).xy(),
+            health: health.current() as f32 / health.max().max(1) as f32,
+            target,
+            allies: (ally_count > 0).then(|| ally_sum / ally_count as f32),
+            formation: flocking.and_then(Flocking::velocity),
+        };
+
+        let steering = brain.think(&senses);
+
+        if steering.facing != Vec2::ZERO {
+            direction_control.set_setpoint(Vec2::Y.angle_between(steering.facing));
+        }
+        impulse.impulse = steering.thrust + brain.avoidance;
+    }
+}
+
+/// Shoot now and then, when the active behaviour wants to.
+fn fire_weapon(
+    mut ship_query: Query<(&ShipBrain, &mut ShootTimer, &Transform, &mut Weapon)>,
+    mut game_rng: ResMut<GameRng>,
+    time: Res<Time>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let rng = game_rng.stream(RngStream::EnemyAI);
+
+    for (brain, mut shoot_timer, transform, mut weapon) in ship_query.iter_mut() {
+        if !shoot_timer.update(&time, rng) {
+            continue;
+        }
+
+        let wants_to_fire = brain.steering.is_some_and(|steering| steering.fire);
+        if wants_to_fire && weapon.can_fire() {
+            weapon.fire(&mut commands, &asset_server, *transform);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Debug Overlay
+////////////////////////////////////////////////////////////////////////////////
+
+/// Shows the active behaviour above the ship.
+#[derive(Component, Debug)]
+struct ShipAiLabel;
+
+const LABEL_OFFSET: Vec3 = Vec3::new(0.0, 50.0, 1.0);
+
+fn update_ai_labels(
+    flag: Option<Res<debug::ShipAiDebugFlag>>,
+    ship_query: Query<(&ShipBrain, &Transform), Without<ShipAiLabel>>,
+    mut label_query: Query<
+        (&Parent, &mut Text, &mut Visibility, &mut Transform),
+        With<ShipAiLabel>,
+    >,
+) {
+    let show = debug::flag_is_on(flag);
+
+    for (parent, mut text, mut visibility, mut transform) in label_query.iter_mut() {
+        *visibility = if show {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        let Ok((brain, ship_transform)) = ship_query.get(parent.get()) else {
+            continue;
+        };
+        if !show {
+            continue;
+        }
+
+        // Keep the label above the ship however it is turned
+        transform.translation = ship_transform.rotation.inverse() * LABEL_OFFSET;
+
+        let name = brain.active().to_string();
+        if text.sections[0].value != name {
+            text.sections[0].value = name;
+        }
+    }
+}
 
-        // turn the influence vector into an angle
-        let new_angle = Vec2::Y.angle_between(final_influence);
-        direction_control.set_setpoint(new_angle);
+/// Circle every ship in the color of its behaviour, with a line to where it
+/// is going.
+fn draw_ai_goals(mut gizmos: Gizmos, ship_query: Query<(&ShipBrain, &Transform)>) {
+    for (brain, transform) in ship_query.iter() {
+        let position = transform.translation.xy();
+        let color = behaviour_color(brain.active());
 
-        enemy_impulse.impulse = final_influence;
+        gizmos.circle_2d(position, 40.0, color);
+        if let Some(goal) = brain.steering.and_then(|steering| steering.goal) {
+            gizmos.line_2d(position, goal, color);
+        }
+    }
+}
+
+fn behaviour_color(behaviour: Behaviour) -> Color {
+    match behaviour {
+        Behaviour::Patrol => Color::GRAY,
+        Behaviour::Pursue => Color::ORANGE,
+        Behaviour::Strafe => Color::RED,
+        Behaviour::Retreat => Color::YELLOW,
+        Behaviour::Regroup => Color::CYAN,
+        Behaviour::Formation => Color::GREEN,
     }
 }
 
@@ -265,12 +409,22 @@ pub fn despawn(mut commands: Commands, query: Query<Entity, With<Enemy>>) {
     }
 }
 
-pub fn spawn(
+/// Make `ships` a squad flying in `formation`, led by the first one.
+pub fn form_squad(commands: &mut Commands, ships: &[Entity], formation: Formation) {
+    boids::form_squad(commands, ships, formation, FlockWeights::ships());
+}
+
+pub fn spawn<R>(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
+    rng: &mut R,
     spawn_location: Vec2,
     rotation: f32,
-) -> Entity {
+    faction: Faction,
+) -> Entity
+where
+    R: Rng + ?Sized,
+{
     let asset = assets::ENEMY_SHIP_1;
 
     let mut spawn_transform = Transform::from_translation(spawn_location.extend(0.0));
@@ -289,7 +443,7 @@ pub fn spawn(
             transform: spawn_transform,
             ..Default::default()
         })
-        .insert(ShootTimer::default())
+        .insert(ShootTimer::new(rng))
         .insert(GameEntityType::Enemy)
         .insert(Enemy)
         .insert(EnemyShipLabel)
@@ -299,7 +453,12 @@ pub fn spawn(
             outer_distance: 300.0,
             angle: PI / 2.0,
         })
-        .insert(ShipNavigationSystem::default())
+        .insert(ShipBrain::new(
+            Behaviour::ALL.to_vec(),
+            BrainConfig::default(),
+            Patrol::around(spawn_location, 300.0, rotation),
+        ))
+        .insert(NoRotationParent)
         .insert(DirectionControl {
             torque_impulse_magnitude: 0.005,
             ..Default::default()
@@ -308,21 +467,13 @@ pub fn spawn(
         .insert(Weapon::laser(
             10,
             750.0,
-            Timer::from_seconds(1.0, TimerMode::Once),
+            Timer::from_seconds(3.0, TimerMode::Once),
             None,
-            groups::ENEMY_PROJECTILE_GROUP,
-            groups::ENEMY_PROJECTILE_FILTER_MASK,
+            faction,
         ))
         .insert(RigidBody::Dynamic)
         .insert(asset.collider())
-        .insert(CollisionGroups::new(
-            groups::ENEMY_GROUP.into(),
-            groups::ENEMY_FILTER_MASK.into(),
-        ))
-        .insert(SolverGroups::new(
-            groups::ENEMY_GROUP.into(),
-            groups::ENEMY_FILTER_MASK.into(),
-        ))
+        .insert((faction, FactionBody::Ship))
         .insert(Damping {
             linear_damping: 0.5,
             angular_damping: 1.0,
@@ -330,7 +481,32 @@ pub fn spawn(
         .insert(Velocity::default())
         .insert(ExternalForce::default())
         .insert(ExternalImpulse::default())
+        .with_children(|parent| {
+            parent
+                .spawn(Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 16.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_translation(LABEL_OFFSET),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                })
+                .insert((ShipAiLabel, NoRotationChild));
+        })
         .id();
 
+    particles::spawn_emitter(
+        commands,
+        entity,
+        ParticlePreset::EngineExhaust,
+        EmitterSource::Thrust,
+        Vec2::new(0.0, -40.0),
+    );
+
     return entity;
 }
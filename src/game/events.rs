@@ -6,10 +6,37 @@ pub struct GameOverEvent {
     pub score: u64,
 }
 
-// Game Over event
+// High Score event, sent when a game over score places on the board
 #[derive(Event, Debug, Clone, Eq, PartialEq, Hash, Default)]
 pub struct HighScoreEvent {
     pub player_name: String,
     pub placement: usize,
     pub score: u64,
 }
+
+// High Score Submitted event, sent when the player has entered a name
+#[derive(Event, Debug, Clone, Eq, PartialEq, Hash, Default)]
+pub struct HighScoreSubmitted {
+    pub player_name: String,
+    pub score: u64,
+}
+
+// Wave Started event
+#[derive(Event, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct WaveStarted {
+    pub wave: u32,
+    /// The number of enemies that will spawn during the wave
+    pub enemies: u32,
+}
+
+// Wave Cleared event
+#[derive(Event, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct WaveCleared {
+    pub wave: u32,
+}
+
+// Player Hit event, sent when the player takes damage from crashing into something
+#[derive(Event, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct PlayerHit {
+    pub damage: u32,
+}
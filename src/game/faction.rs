@@ -0,0 +1,337 @@
+//! # Factions
+//!
+//! Every ship, turret and drone belongs to a faction, and so do the
+//! projectiles they fire and the sensors they look for targets with. How the
+//! factions get along is set in the `[factions]` section of a level:
+//!
+//! - A faction is always friendly to itself.
+//! - Factions listed as `hostile` attack each other.
+//! - All others are neutral. They do not go after each other, but are still
+//!   hit by each other's projectiles, so neutral ships can be caught in the
+//!   crossfire.
+//!
+//! The collision groups of everything with a `Faction` and a `FactionBody` are
+//! derived from the relations, see `FactionRelations::collision_groups`.
+
+use super::assets::groups;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct FactionPlugin;
+
+impl Plugin for FactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FactionRelations>()
+            // Before new colliders are handed to the physics
+            .add_systems(
+                PostUpdate,
+                assign_collision_groups.before(PhysicsSet::SyncBackend),
+            );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component, Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Faction {
+    Player,
+    Enemy1,
+    Enemy2,
+    Enemy3,
+    Neutral,
+}
+
+impl Faction {
+    pub const ALL: [Faction; 5] = [
+        Faction::Player,
+        Faction::Enemy1,
+        Faction::Enemy2,
+        Faction::Enemy3,
+        Faction::Neutral,
+    ];
+
+    /// The collision group of the ships of the faction.
+    pub fn ship_group(&self) -> Group {
+        match self {
+            Faction::Player => groups::PLAYER_GROUP,
+            Faction::Enemy1 => groups::ENEMY_GROUP,
+            Faction::Enemy2 => groups::ENEMY2_GROUP,
+            Faction::Enemy3 => groups::ENEMY3_GROUP,
+            Faction::Neutral => groups::NEUTRAL_GROUP,
+        }
+    }
+
+    /// The collision group of the projectiles fired by the faction.
+    pub fn projectile_group(&self) -> Group {
+        match self {
+            Faction::Player => groups::PLAYER_PROJECTILE_GROUP,
+            Faction::Enemy1 => groups::ENEMY_PROJECTILE_GROUP,
+            Faction::Enemy2 => groups::ENEMY2_PROJECTILE_GROUP,
+            Faction::Enemy3 => groups::ENEMY3_PROJECTILE_GROUP,
+            Faction::Neutral => groups::NEUTRAL_PROJECTILE_GROUP,
+        }
+    }
+}
+
+/// What a collider of a faction is, which decides what it collides with.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FactionBody {
+    /// Bumps into ships and meteors, and is hit by projectiles.
+    Ship,
+    /// Flies through ships and meteors, but is hit by projectiles.
+    Drone,
+    Projectile,
+    /// Finds the ships of hostile factions.
+    Sensor,
+}
+
+impl FactionBody {
+    /// Whether the physics pushes it away from what it collides with.
+    pub fn is_solid(&self) -> bool {
+        !matches!(self, FactionBody::Sensor)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Relations
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Friendly,
+    Neutral,
+    Hostile,
+}
+
+/// How the factions get along. Factions are friendly to themselves and
+/// neutral to those not listed.
+#[derive(Resource, Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FactionRelations {
+    /// Pairs of factions that attack each other. The order does not matter.
+    pub hostile: Vec<(Faction, Faction)>,
+}
+
+/// Everyone fights the player and each other, the neutrals are left alone.
+impl Default for FactionRelations {
+    fn default() -> Self {
+        use Faction::*;
+
+        Self {
+            hostile: vec![
+                (Player, Enemy1),
+                (Player, Enemy2),
+                (Player, Enemy3),
+                (Enemy1, Enemy2),
+                (Enemy1, Enemy3),
+                (Enemy2, Enemy3),
+            ],
+        }
+    }
+}
+
+impl FactionRelations {
+    pub fn relation(&self, a: Faction, b: Faction) -> Relation {
+        if a == b {
+            Relation::Friendly
+        } else if self
+            .hostile
+            .iter()
+            .any(|&pair| pair == (a, b) || pair == (b, a))
+        {
+            Relation::Hostile
+        } else {
+            Relation::Neutral
+        }
+    }
+
+    pub fn is_hostile(&self, a: Faction, b: Faction) -> bool {
+        self.relation(a, b) == Relation::Hostile
+    }
+
+    /// Projectiles hit everyone that is not friendly.
+    pub fn is_friendly(&self, a: Faction, b: Faction) -> bool {
+        self.relation(a, b) == Relation::Friendly
+    }
+
+    /// The ship groups of the factions that `faction` attacks.
+    pub fn hostile_ships(&self, faction: Faction) -> Group {
+        self.groups_where(faction, Faction::ship_group, |relation| {
+            relation == Relation::Hostile
+        })
+    }
+
+    /// The ship groups of the factions its projectiles hit.
+    pub fn unfriendly_ships(&self, faction: Faction) -> Group {
+        self.groups_where(faction, Faction::ship_group, |relation| {
+            relation != Relation::Friendly
+        })
+    }
+
+    /// The projectile groups of the factions whose projectiles hit it.
+    pub fn unfriendly_projectiles(&self, faction: Faction) -> Group {
+        self.groups_where(faction, Faction::projectile_group, |relation| {
+            relation != Relation::Friendly
+        })
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use space_game::game::assets::groups;
+    /// use space_game::game::faction::{Faction, FactionBody, FactionRelations};
+    ///
+    /// let relations = FactionRelations::default();
+    /// let sensor = relations.collision_groups(Faction::Enemy1, FactionBody::Sensor);
+    ///
+    /// assert_eq!(sensor.memberships, groups::SENSOR_GROUP);
+    /// assert!(sensor.filters.contains(groups::PLAYER_GROUP));
+    /// assert!(!sensor.filters.contains(groups::NEUTRAL_GROUP));
+    /// ```
+    pub fn collision_groups(&self, faction: Faction, body: FactionBody) -> CollisionGroups {
+        let (memberships, filters) = match body {
+            FactionBody::Ship => {
+                let mut filters = groups::SHIP_GROUPS
+                    | groups::METEOR_GROUP
+                    | groups::SENSOR_GROUP
+                    | self.unfriendly_projectiles(faction);
+                // Only the player is kept in by the arena and picks things up
+                if faction == Faction::Player {
+                    filters |= groups::ARENA_GROUP | groups::PICKUP_GROUP;
+                }
+                (faction.ship_group(), filters)
+            }
+            FactionBody::Drone => (
+                faction.ship_group() | groups::KAMIKAZE_DRONE_GROUP,
+                groups::SENSOR_GROUP | self.unfriendly_projectiles(faction),
+            ),
+            FactionBody::Projectile => (
+                faction.projectile_group(),
+                groups::METEOR_GROUP | self.unfriendly_ships(faction),
+            ),
+            FactionBody::Sensor => (groups::SENSOR_GROUP, self.hostile_ships(faction)),
+        };
+
+        CollisionGroups::new(memberships, filters)
+    }
+
+    fn groups_where(
+        &self,
+        faction: Faction,
+        group: fn(&Faction) -> Group,
+        keep: impl Fn(Relation) -> bool,
+    ) -> Group {
+        Faction::ALL
+            .iter()
+            .filter(|other| keep(self.relation(faction, **other)))
+            .fold(Group::NONE, |groups, other| groups | group(other))
+    }
+}
+
+/// Look up how an entity relates to a faction, for AI picking targets.
+#[derive(SystemParam)]
+pub struct Hostility<'w, 's> {
+    pub relations: Res<'w, FactionRelations>,
+    factions: Query<'w, 's, &'static Faction>,
+}
+
+impl Hostility<'_, '_> {
+    /// The faction of an entity, if it has one.
+    pub fn faction(&self, entity: Entity) -> Option<Faction> {
+        self.factions.get(entity).ok().copied()
+    }
+
+    /// Whether `faction` attacks `entity`.
+    pub fn is_hostile(&self, faction: Faction, entity: Entity) -> bool {
+        self.faction(entity)
+            .is_some_and(|other| self.relations.is_hostile(faction, other))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+/// Give new bodies their collision groups, and everything new ones when the
+/// relations change.
+fn assign_collision_groups(
+    mut commands: Commands,
+    relations: Res<FactionRelations>,
+    query: Query<(Entity, Ref<Faction>, &FactionBody)>,
+) {
+    for (entity, faction, body) in query.iter() {
+        if !relations.is_changed() && !faction.is_changed() {
+            continue;
+        }
+
+        let collision_groups = relations.collision_groups(*faction, *body);
+        let mut entity = commands.entity(entity);
+        entity.insert(collision_groups);
+        if body.is_solid() {
+            entity.insert(SolverGroups::new(
+                collision_groups.memberships,
+                collision_groups.filters,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Both have to be in the filter of the other to collide.
+    fn collides(a: CollisionGroups, b: CollisionGroups) -> bool {
+        a.filters.intersects(b.memberships) && b.filters.intersects(a.memberships)
+    }
+
+    #[test]
+    fn test_projectiles_hit_everyone_but_friends() {
+        let relations = FactionRelations {
+            hostile: vec![(Faction::Player, Faction::Enemy1)],
+        };
+        let groups = |faction, body| relations.collision_groups(faction, body);
+
+        let player_shot = groups(Faction::Player, FactionBody::Projectile);
+        assert!(collides(
+            player_shot,
+            groups(Faction::Enemy1, FactionBody::Ship)
+        ));
+        assert!(collides(
+            player_shot,
+            groups(Faction::Enemy2, FactionBody::Drone)
+        ));
+        // Caught in the crossfire
+        assert!(collides(
+            player_shot,
+            groups(Faction::Neutral, FactionBody::Ship)
+        ));
+        assert!(!collides(
+            player_shot,
+            groups(Faction::Player, FactionBody::Ship)
+        ));
+
+        // Only hostile ships are seen
+        let sensor = groups(Faction::Enemy1, FactionBody::Sensor);
+        assert!(collides(sensor, groups(Faction::Player, FactionBody::Ship)));
+        assert!(!collides(
+            sensor,
+            groups(Faction::Enemy2, FactionBody::Ship)
+        ));
+        assert!(!collides(sensor, player_shot));
+
+        // Drones fly through ships
+        assert!(!collides(
+            groups(Faction::Enemy1, FactionBody::Drone),
+            groups(Faction::Player, FactionBody::Ship)
+        ));
+    }
+}
@@ -1,20 +1,5 @@
-use bevy::prelude::{Component, Entity};
-
-use std::collections::HashSet;
+use bevy::prelude::Component;
 
 /// Means an entity is a KamikazeDrone
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
 pub struct KamikazeDroneLabel;
-
-/// Means an entity is a KamikazeDroneSensorLabel
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
-pub struct KamikazeDroneSensorLabel;
-
-#[derive(Debug, PartialEq, Eq, Component)]
-pub struct BoidTargets(pub HashSet<Entity>);
-
-impl Default for BoidTargets {
-    fn default() -> Self {
-        Self(HashSet::new())
-    }
-}
@@ -4,17 +4,18 @@ mod systems;
 use bevy::prelude::*;
 use bevy_rapier2d::{
     dynamics::Damping,
-    prelude::{
-        ActiveEvents, Collider, ColliderMassProperties, CollisionGroups, ExternalForce,
-        ExternalImpulse, RigidBody, Sensor, SolverGroups, Velocity,
-    },
+    prelude::{ActiveEvents, ExternalForce, ExternalImpulse, RigidBody, Velocity},
 };
 
 use crate::misc::transform::from_location_angle;
 
-use self::components::{BoidTargets, KamikazeDroneLabel, KamikazeDroneSensorLabel};
+use self::components::KamikazeDroneLabel;
 
-use super::assets::{self, groups};
+use super::assets;
+use super::boids::{FlockMode, FlockWeights, Flocking, FlockingSystem, ObstacleAvoidance};
+use super::faction::{Faction, FactionBody};
+use super::game_entity::{Enemy, GameEntityType};
+use super::vitality::Health;
 
 ////////////////////////////////////////////////////////////////////////////////
 // Plugin
@@ -24,13 +25,7 @@ pub struct KamikazeDronesPlugin;
 
 impl Plugin for KamikazeDronesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                systems::update_kamikaze_drone,
-                systems::update_kamikaze_drone_targets,
-            ),
-        );
+        app.add_systems(Update, systems::update_kamikaze_drone.after(FlockingSystem));
     }
 }
 
@@ -46,22 +41,10 @@ pub fn spawn(
     asset_server: &Res<AssetServer>,
     location: Vec2,
     rotation: f32,
+    faction: Faction,
 ) -> Entity {
     let spawn_transform = from_location_angle(location, rotation);
     let asset = assets::KAMIKAZE_DRONE;
-    let sensor_range = 200.0;
-
-    let drone_sensor = commands
-        .spawn(KamikazeDroneSensorLabel)
-        .insert(Collider::ball(sensor_range))
-        .insert(ColliderMassProperties::Density(0.0))
-        .insert(Sensor)
-        .insert(ActiveEvents::COLLISION_EVENTS)
-        .insert(CollisionGroups::new(
-            groups::SENSOR_GROUP.into(),
-            groups::KAMIKAZE_DRONE_GROUP.into(),
-        ))
-        .id();
 
     let drone_entity = commands
         .spawn(SpriteBundle {
@@ -70,6 +53,9 @@ pub fn spawn(
             ..Default::default()
         })
         .insert(KamikazeDroneLabel)
+        .insert(Enemy)
+        .insert(GameEntityType::Enemy)
+        .insert(Health::at_max(10))
         .insert(asset.collider())
         .insert(Damping {
             linear_damping: 0.0,
@@ -80,21 +66,36 @@ pub fn spawn(
         .insert(Velocity::default())
         .insert(ExternalImpulse::default())
         .insert(ExternalForce::default())
-        .insert(BoidTargets::default())
-        .insert(CollisionGroups::new(
-            groups::KAMIKAZE_DRONE_GROUP.into(),
-            groups::KAMIKAZE_DRONE_FILTER_MASK.into(),
-        ))
-        .insert(SolverGroups::new(
-            groups::KAMIKAZE_DRONE_GROUP.into(),
-            groups::KAMIKAZE_DRONE_FILTER_MASK.into(),
-        ))
-        .push_children(&[drone_sensor])
+        .insert((faction, FactionBody::Drone))
         .id();
 
+    // A swarm of one, until it joins others
+    commands.entity(drone_entity).insert(flocking(drone_entity));
+
     return drone_entity;
 }
 
+/// Make `drones` swarm together.
+pub fn form_swarm(commands: &mut Commands, drones: &[Entity]) {
+    let Some(&leader) = drones.first() else {
+        return;
+    };
+
+    for drone in drones {
+        commands.entity(*drone).insert(flocking(leader));
+    }
+}
+
+fn flocking(flock: Entity) -> Flocking {
+    Flocking::new(flock, FlockMode::Swarm, FlockWeights::drones()).with_avoidance(
+        ObstacleAvoidance {
+            inner_distance: 25.0,
+            outer_distance: 200.0,
+            width: 20.0,
+        },
+    )
+}
+
 pub fn despawn(mut commands: Commands, query: Query<Entity, With<KamikazeDroneLabel>>) {
     for entity in query.iter() {
         commands.entity(entity).despawn_recursive();
@@ -0,0 +1,548 @@
+//! # Level
+//!
+//! A level describes a game in the main scene: the size of the arena, how many
+//! meteors there are, what the player starts with, how strong the enemies are,
+//! the waves they come in, what they drop and which factions fight each
+//! other. Levels are TOML files in the `levels/` folder.
+//!
+//! Levels are validated when they are loaded, errors name the file, the field
+//! and what is wrong with it.
+
+use super::arena::{self, MIN_ARENA_RADIUS};
+use super::faction::FactionRelations;
+use super::meteors::MeteorSizeWeights;
+use super::pickup::{PickupConfig, PickupKind};
+use super::player::PlayerLoadout;
+use super::turret::TurretConfig;
+use super::wave::{SpawnPattern, WaveSchedule};
+use crate::file_save::{self, FileSave};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// The folder levels are loaded from.
+pub const LEVEL_DIR: &str = "levels";
+
+/// The level played when no other is chosen. The game has a built in copy, see
+/// `Level::default`, in case the file is missing.
+pub const DEFAULT_LEVEL: &str = "default";
+
+////////////////////////////////////////////////////////////////////////////////
+// Level
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Resource, Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    /// The name the level was loaded with, not part of the file.
+    #[serde(skip)]
+    pub name: String,
+    pub arena: ArenaConfig,
+    pub meteors: MeteorConfig,
+    pub player: PlayerLoadout,
+    pub enemies: EnemyStats,
+    pub waves: WaveSchedule,
+    #[serde(default)]
+    pub pickups: PickupConfig,
+    #[serde(default)]
+    pub factions: FactionRelations,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            name: String::from(DEFAULT_LEVEL),
+            arena: ArenaConfig::default(),
+            meteors: MeteorConfig::default(),
+            player: PlayerLoadout::default(),
+            enemies: EnemyStats::default(),
+            waves: WaveSchedule::default(),
+            pickups: PickupConfig::default(),
+            factions: FactionRelations::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ArenaConfig {
+    /// The radius of the playable area.
+    pub radius: f32,
+    /// The width of the asteroid belt around the arena.
+    pub width: f32,
+}
+
+impl Default for ArenaConfig {
+    fn default() -> Self {
+        Self {
+            radius: 2000.0,
+            width: 400.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MeteorConfig {
+    /// Meteors per million square pixels of arena.
+    pub density: f32,
+    pub sizes: MeteorSizeWeights,
+}
+
+impl Default for MeteorConfig {
+    fn default() -> Self {
+        Self {
+            density: 7.96,
+            sizes: MeteorSizeWeights::default(),
+        }
+    }
+}
+
+impl MeteorConfig {
+    /// The number of meteors in an arena with the given radius.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use space_game::game::level::MeteorConfig;
+    ///
+    /// let meteors = MeteorConfig::default();
+    /// assert_eq!(meteors.count(2000.0), 100);
+    /// ```
+    pub fn count(&self, arena_radius: f32) -> usize {
+        (self.density * arena::circle_area(arena_radius) / 1_000_000.0).round() as usize
+    }
+}
+
+/// Stats of the enemies spawned by the waves.
+#[derive(Resource, Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyStats {
+    pub turret: TurretConfig,
+}
+
+impl Level {
+    /// The path of a level. A name ending in `.toml` is used as a path as is,
+    /// otherwise the level is looked up in the `levels/` folder.
+    pub fn path(name: &str) -> String {
+        if name.ends_with(".toml") {
+            name.to_string()
+        } else {
+            format!("{}/{}.toml", LEVEL_DIR, name)
+        }
+    }
+
+    /// Load a level by name, see `Level::path`.
+    pub fn load(name: &str) -> Result<Level, Box<dyn Error>> {
+        let mut level = Level::load_from_file(&Level::path(name))?;
+        level.name = name.to_string();
+        Ok(level)
+    }
+
+    /// Check that every field has a sensible value.
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, field: String, reason: &str| {
+            if !ok {
+                errors.push(FieldError {
+                    field,
+                    reason: reason.to_string(),
+                });
+            }
+        };
+
+        // Arena
+        check(
+            self.arena.radius > MIN_ARENA_RADIUS,
+            "arena.radius".into(),
+            &format!("must be greater than {}", MIN_ARENA_RADIUS),
+        );
+        check(
+            self.arena.width > 0.0,
+            "arena.width".into(),
+            "must be greater than 0",
+        );
+
+        // Meteors
+        check(
+            self.meteors.density >= 0.0,
+            "meteors.density".into(),
+            "must not be negative",
+        );
+        let sizes = &self.meteors.sizes;
+        for (name, weight) in [
+            ("tiny", sizes.tiny),
+            ("small", sizes.small),
+            ("medium", sizes.medium),
+            ("big", sizes.big),
+        ] {
+            check(
+                weight >= 0.0,
+                format!("meteors.sizes.{}", name),
+                "must not be negative",
+            );
+        }
+        check(
+            sizes.tiny + sizes.small + sizes.medium + sizes.big > 0.0,
+            "meteors.sizes".into(),
+            "at least one size must have a weight greater than 0",
+        );
+
+        // Player
+        check(
+            self.player.max_health > 0,
+            "player.max_health".into(),
+            "must be greater than 0",
+        );
+        check(
+            self.player.weapon_cooldown > 0.0,
+            "player.weapon_cooldown".into(),
+            "must be greater than 0",
+        );
+        if let Some(heat) = &self.player.heat {
+            check(
+                heat.per_shot > 0.0,
+                "player.heat.per_shot".into(),
+                "must be greater than 0",
+            );
+            check(
+                heat.dissipation >= 0.0,
+                "player.heat.dissipation".into(),
+                "must not be negative",
+            );
+            check(
+                heat.lockout >= 0.0,
+                "player.heat.lockout".into(),
+                "must not be negative",
+            );
+        }
+        if let Some(shield) = &self.player.shield {
+            check(
+                shield.capacity > 0,
+                "player.shield.capacity".into(),
+                "must be greater than 0",
+            );
+            check(
+                shield.recharge_delay >= 0.0,
+                "player.shield.recharge_delay".into(),
+                "must not be negative",
+            );
+            check(
+                shield.recharge_rate >= 0.0,
+                "player.shield.recharge_rate".into(),
+                "must not be negative",
+            );
+        }
+        if let Some(ammo) = &self.player.ammo {
+            check(
+                ammo.capacity > 0,
+                "player.ammo.capacity".into(),
+                "must be greater than 0",
+            );
+            check(
+                ammo.reload >= 0.0,
+                "player.ammo.reload".into(),
+                "must not be negative",
+            );
+        }
+
+        // Enemies
+        check(
+            self.enemies.turret.max_health > 0,
+            "enemies.turret.max_health".into(),
+            "must be greater than 0",
+        );
+        check(
+            self.enemies.turret.sensor_range > 0.0,
+            "enemies.turret.sensor_range".into(),
+            "must be greater than 0",
+        );
+        check(
+            self.enemies.turret.gun_turn_rate >= 0.0,
+            "enemies.turret.gun_turn_rate".into(),
+            "must not be negative",
+        );
+
+        // Waves
+        check(
+            self.waves.initial_delay >= 0.0,
+            "waves.initial_delay".into(),
+            "must not be negative",
+        );
+        check(
+            !self.waves.waves.is_empty(),
+            "waves.wave".into(),
+            "there must be at least one wave",
+        );
+        for (i, wave) in self.waves.waves.iter().enumerate() {
+            let field = format!("waves.wave[{}]", i);
+            check(
+                wave.rest >= 0.0,
+                format!("{}.rest", field),
+                "must not be negative",
+            );
+            check(
+                wave.enemy_count() > 0,
+                format!("{}.groups", field),
+                "a wave must have at least one enemy",
+            );
+
+            for (j, group) in wave.groups.iter().enumerate() {
+                let field = format!("{}.groups[{}]", field, j);
+                check(
+                    group.count > 0,
+                    format!("{}.count", field),
+                    "must be greater than 0",
+                );
+                check(
+                    group.delay >= 0.0,
+                    format!("{}.delay", field),
+                    "must not be negative",
+                );
+                match group.pattern {
+                    SpawnPattern::Scattered => {}
+                    SpawnPattern::Cluster { radius } => check(
+                        radius > 0.0,
+                        format!("{}.pattern.Cluster.radius", field),
+                        "must be greater than 0",
+                    ),
+                    SpawnPattern::Stream { interval } => check(
+                        interval > 0.0,
+                        format!("{}.pattern.Stream.interval", field),
+                        "must be greater than 0",
+                    ),
+                }
+            }
+        }
+
+        // Pickups
+        check(
+            self.pickups.time_to_live > 0.0,
+            "pickups.time_to_live".into(),
+            "must be greater than 0",
+        );
+        check(
+            self.pickups.attraction_radius >= 0.0,
+            "pickups.attraction_radius".into(),
+            "must not be negative",
+        );
+        check(
+            self.pickups.attraction_speed >= 0.0,
+            "pickups.attraction_speed".into(),
+            "must not be negative",
+        );
+        let drop_table = &self.pickups.drop_table;
+        check(
+            (0.0..=1.0).contains(&drop_table.chance),
+            "pickups.drop_table.chance".into(),
+            "must be between 0 and 1",
+        );
+        for (i, entry) in drop_table.drops.iter().enumerate() {
+            let field = format!("pickups.drop_table.drop[{}]", i);
+            check(
+                entry.weight >= 0.0,
+                format!("{}.weight", field),
+                "must not be negative",
+            );
+            let amount_is_positive = match entry.pickup {
+                PickupKind::Health { amount } | PickupKind::Shield { amount } => amount > 0,
+                PickupKind::BoostFuel { amount } => amount > 0.0,
+                PickupKind::ScoreMultiplier { amount } => amount > 0,
+                PickupKind::Weapon { .. } => true,
+            };
+            check(
+                amount_is_positive,
+                format!("{}.pickup.amount", field),
+                "must be greater than 0",
+            );
+        }
+
+        // Factions
+        for (i, (a, b)) in self.factions.hostile.iter().enumerate() {
+            check(
+                a != b,
+                format!("factions.hostile[{}]", i),
+                "a faction can not be hostile to itself",
+            );
+        }
+
+        errors
+    }
+}
+
+impl FileSave for Level {
+    type Item = Level;
+    fn load_from_file(path: &str) -> Result<Level, Box<dyn Error>> {
+        let contents = file_save::load_from_file(path).map_err(|err| LevelError {
+            file: path.to_string(),
+            errors: vec![FieldError::file(&err.to_string())],
+        })?;
+
+        let mut level: Level = toml::from_str(&contents).map_err(|err| {
+            let reason = match err.span() {
+                Some(span) => {
+                    let line = contents[..span.start].matches('\n').count() + 1;
+                    format!("line {}: {}", line, err.message())
+                }
+                None => err.message().to_string(),
+            };
+            LevelError {
+                file: path.to_string(),
+                errors: vec![FieldError::file(&reason)],
+            }
+        })?;
+
+        let errors = level.validate();
+        if !errors.is_empty() {
+            return Err(LevelError {
+                file: path.to_string(),
+                errors,
+            }
+            .into());
+        }
+
+        level.name = Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        Ok(level)
+    }
+
+    fn save_to_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let contents = toml::to_string(&self)?;
+        file_save::save_to_file(path, &contents)?;
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Errors
+////////////////////////////////////////////////////////////////////////////////
+
+/// Something wrong with a field of a level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// The path to the field, e.g. `waves.wave[0].rest`. Empty if the problem
+    /// is with the file as a whole.
+    pub field: String,
+    pub reason: String,
+}
+
+impl FieldError {
+    fn file(reason: &str) -> Self {
+        Self {
+            field: String::new(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// A level that could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelError {
+    pub file: String,
+    pub errors: Vec<FieldError>,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            if error.field.is_empty() {
+                write!(f, "{}: {}", self.file, error.reason.trim())?;
+            } else {
+                write!(f, "{}: {}: {}", self.file, error.field, error.reason)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for LevelError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::wave::{EnemyKind, SpawnGroup, Wave};
+
+    #[test]
+    fn test_default_level_is_valid() {
+        assert_eq!(Level::default().validate(), vec![]);
+    }
+
+    #[test]
+    fn test_default_level_file_matches_default() {
+        let level = Level::load("default").unwrap();
+
+        assert_eq!(
+            level,
+            Level {
+                name: String::from("default"),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_names_the_field() {
+        let mut level = Level::default();
+        level.arena.width = 0.0;
+        level.waves.waves[1].groups[0] = SpawnGroup::new(
+            EnemyKind::KamikazeDrone,
+            3,
+            SpawnPattern::Stream { interval: -1.0 },
+        );
+        level.waves.waves.push(Wave::new(vec![], 5.0));
+
+        let fields: Vec<String> = level
+            .validate()
+            .into_iter()
+            .map(|error| error.field)
+            .collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                "arena.width",
+                "waves.wave[1].groups[0].pattern.Stream.interval",
+                "waves.wave[4].groups",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_names_file_field_and_reason() {
+        let error = LevelError {
+            file: String::from("levels/test.toml"),
+            errors: vec![FieldError {
+                field: String::from("arena.width"),
+                reason: String::from("must be greater than 0"),
+            }],
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "levels/test.toml: arena.width: must be greater than 0"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_names_the_line() {
+        let path = std::env::temp_dir()
+            .join(format!("space-game-level-{}.toml", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        // The unknown key starts the third line
+        std::fs::write(&path, "[arena]\nradius = 2000.0\nwidht = 400.0\n").unwrap();
+
+        let error = Level::load_from_file(&path).unwrap_err().to_string();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(error.contains("line 3:"), "{}", error);
+    }
+}
@@ -4,6 +4,9 @@ use super::assets::Asset;
 use bevy::prelude::*;
 use bevy_rapier2d::geometry::*;
 use bevy_rapier2d::prelude::*;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
 ////////////////////////////////////////////////////////////////////////////////
 // Components
@@ -12,6 +15,7 @@ use bevy_rapier2d::prelude::*;
 #[derive(Component)]
 pub struct Meteor;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MeteorSize {
     Tiny,
     Small,
@@ -19,6 +23,45 @@ pub enum MeteorSize {
     Big,
 }
 
+/// How likely each meteor size is, relative to the others.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MeteorSizeWeights {
+    pub tiny: f32,
+    pub small: f32,
+    pub medium: f32,
+    pub big: f32,
+}
+
+impl Default for MeteorSizeWeights {
+    fn default() -> Self {
+        Self {
+            tiny: 2.0,
+            small: 3.0,
+            medium: 3.0,
+            big: 2.0,
+        }
+    }
+}
+
+impl MeteorSizeWeights {
+    /// Pick a random size. Returns `None` if the weights are invalid, e.g. all zero.
+    pub fn sample<R>(&self, rng: &mut R) -> Option<MeteorSize>
+    where
+        R: Rng + ?Sized,
+    {
+        let sizes = [
+            MeteorSize::Tiny,
+            MeteorSize::Small,
+            MeteorSize::Medium,
+            MeteorSize::Big,
+        ];
+        let weights = [self.tiny, self.small, self.medium, self.big];
+        let index = WeightedIndex::new(weights).ok()?.sample(rng);
+        Some(sizes[index])
+    }
+}
+
 pub enum MeteorColor {
     Brown,
     Grey,
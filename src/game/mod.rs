@@ -1,3 +1,4 @@
+pub mod accessibility;
 pub mod arena;
 pub mod assets;
 pub mod average_velocity;
@@ -8,35 +9,40 @@ pub mod control_system;
 pub mod debug;
 pub mod enemy;
 pub mod events;
+pub mod faction;
 pub mod game_entity;
 pub mod kamikaze_drone;
+pub mod level;
 pub mod meteors;
 pub mod movement;
+pub mod particles;
+pub mod pickup;
 pub mod player;
 pub mod player_camera;
 pub mod projectile;
+pub mod rng;
 pub mod score;
 pub mod screen_bounds;
 pub mod sensor;
-mod systems;
 pub mod time_to_live;
 pub mod trauma;
 pub mod turret;
 pub mod vitality;
+pub mod wave;
 pub mod weapon;
 
 use std::collections::HashSet;
 
 use bevy::prelude::*;
+use bevy::render::RenderPlugin;
 use bevy_rapier2d::prelude::*;
 use player::PlayerPlugin;
-use systems::*;
 use weapon::WeaponPlugin;
 
-use arena::ArenaPlugin;
 use projectile::ProjectilePlugin;
 
 use self::{
+    accessibility::AccessibilityPlugin,
     average_velocity::AverageVelocityPlugin,
     background::BackgroundPlugin,
     boids::BoidsPlugin,
@@ -44,8 +50,13 @@ use self::{
     debug::{DebugPlugin, VisualDebug},
     enemy::EnemyPlugin,
     events::GameOverEvent,
+    faction::FactionPlugin,
     kamikaze_drone::KamikazeDronesPlugin,
+    level::Level,
     movement::MovementPlugin,
+    particles::ParticlePlugin,
+    pickup::PickupPlugin,
+    rng::GameRng,
     score::ScorePlugin,
     screen_bounds::ScreenBoundsPlugin,
     sensor::SensorPlugin,
@@ -53,14 +64,25 @@ use self::{
     trauma::TraumaPlugin,
     turret::TurretPlugin,
     vitality::VitalityPlugin,
+    wave::WavePlugin,
 };
 
 pub struct GamePlugin {
     pub visual_debug: HashSet<VisualDebug>,
+    /// Seed for all gameplay randomness. A random seed is used if not set.
+    pub seed: Option<u64>,
+    /// The level the main game is played on.
+    pub level: Level,
 }
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
+        let game_rng = self.seed.map(GameRng::new).unwrap_or_default();
+        info!("Game seed: {}", game_rng.seed());
+        app.insert_resource(game_rng);
+        app.insert_resource(self.level.clone());
+        app.insert_resource(self.level.factions.clone());
+
         app.insert_resource(RapierConfiguration {
             gravity: Vec2::ZERO,
             ..Default::default()
@@ -68,8 +90,6 @@ impl Plugin for GamePlugin {
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
         // Events
         .add_event::<GameOverEvent>()
-        // States
-        .add_state::<SimulationState>()
         // Systems
         .add_plugins((
             SensorPlugin,
@@ -85,7 +105,7 @@ impl Plugin for GamePlugin {
                 visual_debug: self.visual_debug.clone(),
             },
             BackgroundPlugin,
-            ArenaPlugin,
+            WavePlugin,
             PlayerPlugin,
             TurretPlugin,
             ProjectilePlugin,
@@ -95,23 +115,16 @@ impl Plugin for GamePlugin {
             TimeToLivePlugin,
             VitalityPlugin,
             ScorePlugin,
-        ))
-        .add_systems(
-            Update,
-            (pause_simulation, toggle_simulation, resume_simulation),
-        );
+            PickupPlugin,
+            AccessibilityPlugin,
+            FactionPlugin,
+        ));
 
-        let rapier_debug_plugin = RapierDebugRenderPlugin::default().disabled();
+        // There is nothing to draw on when running headless
+        if app.is_plugin_added::<RenderPlugin>() {
+            let rapier_debug_plugin = RapierDebugRenderPlugin::default().disabled();
 
-        app.add_plugins(rapier_debug_plugin);
+            app.add_plugins((rapier_debug_plugin, ParticlePlugin));
+        }
     }
 }
-
-// Simulation State
-
-#[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
-pub enum SimulationState {
-    #[default]
-    Running,
-    Paused,
-}
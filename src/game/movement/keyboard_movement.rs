@@ -1,6 +1,7 @@
+use crate::settings::keybindings::{Binding, Bindings, Keybindings, Rebindable};
 use bevy::prelude::*;
 use leafwing_input_manager::action_state::ActionState;
-use leafwing_input_manager::{prelude::*, user_input::InputKind};
+use leafwing_input_manager::prelude::*;
 
 ////////////////////////////////////////////////////////////////////////////////
 /// Plugin
@@ -31,7 +32,7 @@ impl Default for KeyboardMovementBundle {
             keyboard_movement: KeyboardMovement::default(),
             input: InputManagerBundle {
                 action_state: ActionState::default(),
-                input_map: create_input_map(),
+                input_map: KeyboardMovementAction::default_bindings().input_map(),
             },
         }
     }
@@ -46,46 +47,31 @@ impl KeyboardMovementBundle {
     }
 }
 
-fn create_input_map() -> InputMap<KeyboardMovementAction> {
-    let mut input_map: InputMap<KeyboardMovementAction> = InputMap::default();
-
-    // Add Camera inputs
-    input_map.insert_multiple(vec![
-        (
-            InputKind::Keyboard(KeyCode::Up),
-            KeyboardMovementAction::MoveUp,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::Down),
-            KeyboardMovementAction::MoveDown,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::Left),
-            KeyboardMovementAction::MoveLeft,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::Right),
-            KeyboardMovementAction::MoveRight,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::W),
-            KeyboardMovementAction::MoveUp,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::S),
-            KeyboardMovementAction::MoveDown,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::A),
-            KeyboardMovementAction::MoveLeft,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::D),
-            KeyboardMovementAction::MoveRight,
-        ),
-    ]);
-
-    input_map
+impl Rebindable for KeyboardMovementAction {
+    fn default_bindings() -> Bindings<Self> {
+        Bindings::new([
+            (
+                KeyboardMovementAction::MoveUp,
+                vec![Binding::Key(KeyCode::Up), Binding::Key(KeyCode::W)],
+            ),
+            (
+                KeyboardMovementAction::MoveDown,
+                vec![Binding::Key(KeyCode::Down), Binding::Key(KeyCode::S)],
+            ),
+            (
+                KeyboardMovementAction::MoveLeft,
+                vec![Binding::Key(KeyCode::Left), Binding::Key(KeyCode::A)],
+            ),
+            (
+                KeyboardMovementAction::MoveRight,
+                vec![Binding::Key(KeyCode::Right), Binding::Key(KeyCode::D)],
+            ),
+        ])
+    }
+
+    fn bindings(keybindings: &Keybindings) -> &Bindings<Self> {
+        &keybindings.camera
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -35,7 +35,7 @@ mod shaky_movement;
 use bevy::prelude::*;
 
 pub use follow_entity_movement::{FollowEntityMovement, FollowEntityMovementBundle};
-pub use keyboard_movement::{KeyboardMovement, KeyboardMovementBundle};
+pub use keyboard_movement::{KeyboardMovement, KeyboardMovementAction, KeyboardMovementBundle};
 pub use shaky_movement::ShakyMovement;
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use noise::{Fbm, NoiseFn, Perlin, Seedable};
 
-use crate::game::trauma::Trauma;
+use crate::game::trauma::{ShakeIntensity, Trauma};
 
 ////////////////////////////////////////////////////////////////////////////////
 /// Plugin
@@ -60,6 +60,7 @@ impl ShakyMovement {
 
 fn update(
     time: Res<Time>,
+    shake_intensity: Res<ShakeIntensity>,
     mut query: Query<(&mut Transform, &ShakyMovement)>,
     mut trasform_query: Query<&Transform, Without<ShakyMovement>>,
     trauma_query: Query<&Trauma, Without<ShakyMovement>>,
@@ -67,7 +68,7 @@ fn update(
     for (mut movement_transform, movement) in query.iter_mut() {
         if let Ok(target_transform) = trasform_query.get_mut(movement.transform_entity) {
             if let Ok(target_trauma) = trauma_query.get(movement.trauma_entity) {
-                let shake = target_trauma.get_trauma().powi(2);
+                let shake = target_trauma.get_trauma().powi(2) * shake_intensity.0;
 
                 // The sample point must be relative to time such that
                 // slowmotion slows down the shaking. (NOT TESTED)
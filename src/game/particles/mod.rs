@@ -0,0 +1,551 @@
+//! # Particles
+//!
+//! Small sprites that move on their own for a short while, for explosions,
+//! meteor debris, impacts, engine exhaust and boost trails. They are not part
+//! of the physics, and use their own randomness so replays are not affected.
+//!
+//! Bursts are sent as `ParticleBurst` events. Continuous effects come from a
+//! `ParticleEmitter` attached to a ship, see `spawn_emitter`. A particle that
+//! is gone is hidden and reused, and there are never more than
+//! `MAX_PARTICLES`.
+//!
+//! Each preset can be turned off in the `[particles]` section of the
+//! settings.
+
+mod presets;
+
+use super::game_entity::GameEntityType;
+use super::meteors::Meteor;
+use super::player::components::Boost;
+use super::projectile::{ProjectileImpact, ProjectileType};
+use super::vitality::DeathEvent;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+pub use presets::{Curve, Gradient, ParticlePreset, PresetConfig};
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParticleSettings>()
+            .init_resource::<ParticlePool>()
+            .add_event::<ParticleBurst>()
+            .add_systems(
+                Update,
+                (
+                    burst_on_deaths,
+                    burst_on_impacts,
+                    emit_particles,
+                    spawn_bursts,
+                    update_particles,
+                )
+                    .chain(),
+            )
+            // The impulses are cleared once they are handed to the physics
+            .add_systems(PostUpdate, update_emitters.before(PhysicsSet::SyncBackend));
+    }
+}
+
+/// The most particles alive at once. Nothing new is spawned past this.
+pub const MAX_PARTICLES: usize = 2000;
+
+/// The thrust impulse at which engines give off the most exhaust.
+const FULL_THRUST_IMPULSE: f32 = 1.0;
+
+////////////////////////////////////////////////////////////////////////////////
+// Settings
+////////////////////////////////////////////////////////////////////////////////
+
+/// Which particle effects are shown. Turning them off helps on slow machines.
+#[derive(Resource, Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ParticleSettings {
+    pub explosions: bool,
+    pub debris: bool,
+    pub impacts: bool,
+    pub exhaust: bool,
+    pub boost_trails: bool,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            explosions: true,
+            debris: true,
+            impacts: true,
+            exhaust: true,
+            boost_trails: true,
+        }
+    }
+}
+
+impl ParticleSettings {
+    pub fn is_enabled(&self, preset: ParticlePreset) -> bool {
+        match preset {
+            ParticlePreset::ShipExplosion => self.explosions,
+            ParticlePreset::MeteorDebris => self.debris,
+            ParticlePreset::LaserImpact => self.impacts,
+            ParticlePreset::EngineExhaust => self.exhaust,
+            ParticlePreset::BoostTrail => self.boost_trails,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+/// Spawn the burst of a preset at once.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ParticleBurst {
+    pub preset: ParticlePreset,
+    pub position: Vec2,
+    /// The particles spread around it. They go every way if it is zero.
+    pub direction: Vec2,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+/// Keeps spawning particles while its intensity is above zero.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct ParticleEmitter {
+    pub preset: ParticlePreset,
+    pub source: EmitterSource,
+    /// From 0 to 1, scales the rate of the preset.
+    pub intensity: f32,
+    /// Where the particles are sent, in world space.
+    pub direction: Vec2,
+    /// Particles owed from earlier frames, to emit at low rates.
+    accumulator: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(preset: ParticlePreset, source: EmitterSource) -> Self {
+        Self {
+            preset,
+            source,
+            intensity: 0.0,
+            direction: Vec2::ZERO,
+            accumulator: 0.0,
+        }
+    }
+
+    /// The number of particles to spawn this frame.
+    pub fn tick(&mut self, rate: f32, delta_seconds: f32) -> u32 {
+        self.accumulator += rate * self.intensity * delta_seconds;
+        let count = self.accumulator.floor();
+        self.accumulator -= count;
+        count as u32
+    }
+}
+
+/// What drives the intensity of an emitter, read from its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmitterSource {
+    /// Always at full intensity, sent out the back of the parent.
+    Constant,
+    /// The `ExternalImpulse` of the parent. Particles go the opposite way.
+    Thrust,
+    /// Full intensity while the `Boost` of the parent is active.
+    Boost,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+struct Particle {
+    preset: ParticlePreset,
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+    alive: bool,
+}
+
+/// Particles that are gone and can be reused.
+#[derive(Resource, Debug, Default)]
+struct ParticlePool {
+    free: Vec<Entity>,
+    /// Particles alive or free. Counted up when spawning, the new particles
+    /// are only in the world once the commands are applied.
+    count: usize,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Helpers
+////////////////////////////////////////////////////////////////////////////////
+
+/// Attach an emitter to `parent`, at `offset` from its center.
+pub fn spawn_emitter(
+    commands: &mut Commands,
+    parent: Entity,
+    preset: ParticlePreset,
+    source: EmitterSource,
+    offset: Vec2,
+) -> Entity {
+    let emitter = commands
+        .spawn((
+            ParticleEmitter::new(preset, source),
+            TransformBundle::from_transform(Transform::from_translation(offset.extend(0.0))),
+        ))
+        .id();
+    commands.entity(parent).add_child(emitter);
+    emitter
+}
+
+#[derive(SystemParam)]
+struct Particles<'w, 's> {
+    commands: Commands<'w, 's>,
+    pool: ResMut<'w, ParticlePool>,
+    query: Query<
+        'w,
+        's,
+        (
+            &'static mut Particle,
+            &'static mut Transform,
+            &'static mut Visibility,
+        ),
+    >,
+}
+
+impl Particles<'_, '_> {
+    /// Spawn a particle of `preset` going along `direction`, reusing one that
+    /// is gone if there is any.
+    fn spawn(&mut self, preset: ParticlePreset, position: Vec2, direction: Vec2) {
+        let config = preset.config();
+        let mut rng = rand::thread_rng();
+
+        let (base_angle, spread) = if direction == Vec2::ZERO {
+            (0.0, TAU)
+        } else {
+            (direction.y.atan2(direction.x), config.spread)
+        };
+        let angle = base_angle + rng.gen_range(-0.5..=0.5) * spread;
+        let speed = rng.gen_range(config.speed.0..=config.speed.1);
+        let particle = Particle {
+            preset,
+            velocity: Vec2::from_angle(angle) * speed,
+            age: 0.0,
+            lifetime: rng.gen_range(config.lifetime.0..=config.lifetime.1),
+            alive: true,
+        };
+        let translation = position.extend(config.z);
+
+        // Particles can be despawned by something else, e.g. a scene change
+        while let Some(entity) = self.pool.free.pop() {
+            if let Ok((mut reused, mut transform, mut visibility)) = self.query.get_mut(entity) {
+                *reused = particle;
+                transform.translation = translation;
+                *visibility = Visibility::Visible;
+                return;
+            }
+        }
+
+        if self.pool.count >= MAX_PARTICLES {
+            return;
+        }
+        self.pool.count += 1;
+        self.commands.spawn((
+            particle,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: config.color.sample(0.0),
+                    custom_size: Some(Vec2::splat(config.size.sample(0.0))),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+        ));
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn burst_on_deaths(
+    mut death_events: EventReader<DeathEvent>,
+    mut bursts: EventWriter<ParticleBurst>,
+) {
+    for death in death_events.read() {
+        if matches!(
+            death._type(),
+            GameEntityType::Player | GameEntityType::Enemy
+        ) {
+            bursts.send(ParticleBurst {
+                preset: ParticlePreset::ShipExplosion,
+                position: death.location(),
+                direction: Vec2::ZERO,
+            });
+        }
+    }
+}
+
+fn burst_on_impacts(
+    mut impact_events: EventReader<ProjectileImpact>,
+    mut bursts: EventWriter<ParticleBurst>,
+    meteor_query: Query<(), With<Meteor>>,
+) {
+    for impact in impact_events.read() {
+        let position = impact.position;
+        // Sprays back towards where the projectile came from
+        let direction = -impact.direction;
+
+        if impact.projectile_type == ProjectileType::Mine {
+            bursts.send(ParticleBurst {
+                preset: ParticlePreset::ShipExplosion,
+                position,
+                direction: Vec2::ZERO,
+            });
+        } else {
+            bursts.send(ParticleBurst {
+                preset: ParticlePreset::LaserImpact,
+                position,
+                direction,
+            });
+        }
+
+        if impact
+            .target
+            .is_some_and(|target| meteor_query.contains(target))
+        {
+            bursts.send(ParticleBurst {
+                preset: ParticlePreset::MeteorDebris,
+                position,
+                direction,
+            });
+        }
+    }
+}
+
+fn spawn_bursts(
+    settings: Res<ParticleSettings>,
+    mut bursts: EventReader<ParticleBurst>,
+    mut particles: Particles,
+) {
+    for burst in bursts.read() {
+        if !settings.is_enabled(burst.preset) {
+            continue;
+        }
+        for _ in 0..burst.preset.config().burst {
+            particles.spawn(burst.preset, burst.position, burst.direction);
+        }
+    }
+}
+
+fn emit_particles(
+    time: Res<Time>,
+    settings: Res<ParticleSettings>,
+    mut emitter_query: Query<(&mut ParticleEmitter, &GlobalTransform)>,
+    mut particles: Particles,
+) {
+    let dt = time.delta_seconds();
+
+    for (mut emitter, global_transform) in emitter_query.iter_mut() {
+        if !settings.is_enabled(emitter.preset) {
+            continue;
+        }
+
+        let rate = emitter.preset.config().rate;
+        let count = emitter.tick(rate, dt);
+        let position = global_transform.translation().truncate();
+        for _ in 0..count {
+            particles.spawn(emitter.preset, position, emitter.direction);
+        }
+    }
+}
+
+fn update_emitters(
+    mut emitter_query: Query<(&mut ParticleEmitter, &Parent)>,
+    parent_query: Query<(&Transform, Option<&ExternalImpulse>, Option<&Boost>)>,
+) {
+    for (mut emitter, parent) in emitter_query.iter_mut() {
+        let Ok((transform, impulse, boost)) = parent_query.get(parent.get()) else {
+            continue;
+        };
+        let backwards = transform.rotation.mul_vec3(Vec3::NEG_Y).truncate();
+
+        let (intensity, direction) = match emitter.source {
+            EmitterSource::Constant => (1.0, backwards),
+            EmitterSource::Thrust => {
+                let impulse = impulse.map_or(Vec2::ZERO, |impulse| impulse.impulse);
+                (
+                    (impulse.length() / FULL_THRUST_IMPULSE).min(1.0),
+                    -impulse.normalize_or_zero(),
+                )
+            }
+            EmitterSource::Boost => {
+                let active = boost.is_some_and(|boost| boost.is_active());
+                (if active { 1.0 } else { 0.0 }, backwards)
+            }
+        };
+
+        emitter.intensity = intensity;
+        emitter.direction = direction;
+    }
+}
+
+fn update_particles(
+    time: Res<Time>,
+    mut pool: ResMut<ParticlePool>,
+    mut query: Query<(
+        Entity,
+        &mut Particle,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+    mut removed: RemovedComponents<Particle>,
+) {
+    let dt = time.delta_seconds();
+
+    // Particles can be despawned by something else, e.g. a scene change
+    pool.count = pool.count.saturating_sub(removed.read().count());
+
+    for (entity, mut particle, mut transform, mut sprite, mut visibility) in query.iter_mut() {
+        if !particle.alive {
+            continue;
+        }
+
+        particle.age += dt;
+        if particle.age >= particle.lifetime {
+            particle.alive = false;
+            *visibility = Visibility::Hidden;
+            pool.free.push(entity);
+            continue;
+        }
+
+        let config = particle.preset.config();
+        let t = particle.age / particle.lifetime;
+        particle.velocity *= (1.0 - config.drag * dt).max(0.0);
+        transform.translation += (particle.velocity * dt).extend(0.0);
+        sprite.color = config.color.sample(t);
+        sprite.custom_size = Some(Vec2::splat(config.size.sample(t)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                0.1,
+            )))
+            .add_event::<DeathEvent>()
+            .add_event::<ProjectileImpact>()
+            .add_plugins(ParticlePlugin);
+        app.update();
+        app
+    }
+
+    fn count_particles(app: &mut App) -> (usize, usize) {
+        let mut query = app.world.query::<&Particle>();
+        let particles: Vec<_> = query.iter(&app.world).collect();
+        let alive = particles.iter().filter(|particle| particle.alive).count();
+        (alive, particles.len())
+    }
+
+    #[test]
+    fn test_particles_are_reused() {
+        let mut app = app();
+        let burst = ParticleBurst {
+            preset: ParticlePreset::ShipExplosion,
+            position: Vec2::ZERO,
+            direction: Vec2::ZERO,
+        };
+        let size = ParticlePreset::ShipExplosion.config().burst as usize;
+
+        app.world.send_event(burst);
+        app.update();
+        assert_eq!(count_particles(&mut app), (size, size));
+
+        // Longer than any explosion particle lives
+        for _ in 0..12 {
+            app.update();
+        }
+        assert_eq!(count_particles(&mut app), (0, size));
+
+        app.world.send_event(burst);
+        app.update();
+        assert_eq!(count_particles(&mut app), (size, size));
+    }
+
+    #[test]
+    fn test_never_more_than_max_particles() {
+        let mut app = app();
+        let burst = ParticleBurst {
+            preset: ParticlePreset::ShipExplosion,
+            position: Vec2::ZERO,
+            direction: Vec2::ZERO,
+        };
+        let bursts = MAX_PARTICLES / ParticlePreset::ShipExplosion.config().burst as usize + 1;
+
+        // Twice as many as allowed, over two frames
+        for _ in 0..2 {
+            for _ in 0..bursts {
+                app.world.send_event(burst);
+            }
+            app.update();
+        }
+        assert_eq!(count_particles(&mut app).1, MAX_PARTICLES);
+
+        // Despawned particles make room for new ones
+        let particles: Vec<Entity> = app
+            .world
+            .query_filtered::<Entity, With<Particle>>()
+            .iter(&app.world)
+            .collect();
+        for entity in particles {
+            app.world.despawn(entity);
+        }
+        app.update();
+        app.world.send_event(burst);
+        app.update();
+        assert_eq!(
+            count_particles(&mut app).1,
+            ParticlePreset::ShipExplosion.config().burst as usize
+        );
+    }
+
+    #[test]
+    fn test_disabled_presets_are_not_spawned() {
+        let mut app = app();
+        app.world.resource_mut::<ParticleSettings>().explosions = false;
+
+        app.world.send_event(DeathEvent::new(
+            Entity::PLACEHOLDER,
+            GameEntityType::Enemy,
+            Vec2::ZERO,
+        ));
+        app.update();
+        assert_eq!(count_particles(&mut app), (0, 0));
+    }
+
+    #[test]
+    fn test_emitter_carries_over_fractions() {
+        let mut emitter =
+            ParticleEmitter::new(ParticlePreset::EngineExhaust, EmitterSource::Thrust);
+        emitter.intensity = 0.5;
+
+        // 10 per second at half intensity is a particle every 0.2 seconds
+        let spawned: u32 = (0..10).map(|_| emitter.tick(10.0, 0.1)).sum();
+        assert_eq!(spawned, 5);
+
+        emitter.intensity = 0.0;
+        assert_eq!(emitter.tick(10.0, 1.0), 0);
+    }
+}
@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+/// The kinds of particle effects. Each can be turned off in the settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParticlePreset {
+    ShipExplosion,
+    MeteorDebris,
+    LaserImpact,
+    EngineExhaust,
+    BoostTrail,
+}
+
+/// How the particles of a preset are spawned and change over their life.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PresetConfig {
+    /// Particles spawned at once by a burst.
+    pub burst: u32,
+    /// Particles per second of an emitter at full intensity.
+    pub rate: f32,
+    /// Seconds, picked at random between the two.
+    pub lifetime: (f32, f32),
+    /// Pixels per second, picked at random between the two.
+    pub speed: (f32, f32),
+    /// Radians around the direction the particles are sent in. Without a
+    /// direction they go every way.
+    pub spread: f32,
+    /// The fraction of speed lost per second.
+    pub drag: f32,
+    /// Size in pixels over the life of a particle.
+    pub size: Curve,
+    pub color: Gradient,
+    pub z: f32,
+}
+
+const EXPLOSION_COLORS: &[(f32, Color)] = &[
+    (0.0, Color::rgb(1.0, 1.0, 0.8)),
+    (0.3, Color::rgb(1.0, 0.6, 0.1)),
+    (1.0, Color::rgba(0.5, 0.05, 0.0, 0.0)),
+];
+
+const DEBRIS_COLORS: &[(f32, Color)] = &[
+    (0.0, Color::rgb(0.55, 0.4, 0.3)),
+    (1.0, Color::rgba(0.35, 0.25, 0.2, 0.0)),
+];
+
+const IMPACT_COLORS: &[(f32, Color)] = &[
+    (0.0, Color::WHITE),
+    (0.5, Color::rgb(0.5, 0.9, 1.0)),
+    (1.0, Color::rgba(0.2, 0.5, 1.0, 0.0)),
+];
+
+const EXHAUST_COLORS: &[(f32, Color)] = &[
+    (0.0, Color::rgb(0.8, 0.9, 1.0)),
+    (1.0, Color::rgba(0.2, 0.4, 1.0, 0.0)),
+];
+
+const BOOST_TRAIL_COLORS: &[(f32, Color)] = &[
+    (0.0, Color::rgb(1.0, 0.85, 0.3)),
+    (1.0, Color::rgba(1.0, 0.3, 0.1, 0.0)),
+];
+
+impl ParticlePreset {
+    pub fn config(&self) -> PresetConfig {
+        match self {
+            ParticlePreset::ShipExplosion => PresetConfig {
+                burst: 40,
+                rate: 0.0,
+                lifetime: (0.4, 1.0),
+                speed: (50.0, 250.0),
+                spread: TAU,
+                drag: 2.0,
+                size: Curve(&[(0.0, 6.0), (1.0, 1.0)]),
+                color: Gradient(EXPLOSION_COLORS),
+                z: 1.0,
+            },
+            ParticlePreset::MeteorDebris => PresetConfig {
+                burst: 8,
+                rate: 0.0,
+                lifetime: (0.5, 1.2),
+                speed: (30.0, 120.0),
+                spread: PI / 2.0,
+                drag: 1.0,
+                size: Curve(&[(0.0, 4.0), (1.0, 2.0)]),
+                color: Gradient(DEBRIS_COLORS),
+                z: 1.0,
+            },
+            ParticlePreset::LaserImpact => PresetConfig {
+                burst: 8,
+                rate: 0.0,
+                lifetime: (0.1, 0.3),
+                speed: (80.0, 200.0),
+                spread: PI * 0.8,
+                drag: 4.0,
+                size: Curve(&[(0.0, 3.0), (1.0, 0.5)]),
+                color: Gradient(IMPACT_COLORS),
+                z: 1.0,
+            },
+            ParticlePreset::EngineExhaust => PresetConfig {
+                burst: 0,
+                rate: 60.0,
+                lifetime: (0.2, 0.4),
+                speed: (40.0, 80.0),
+                spread: 0.3,
+                drag: 1.0,
+                size: Curve(&[(0.0, 4.0), (1.0, 1.0)]),
+                color: Gradient(EXHAUST_COLORS),
+                // Behind the ship
+                z: -0.5,
+            },
+            ParticlePreset::BoostTrail => PresetConfig {
+                burst: 0,
+                rate: 80.0,
+                lifetime: (0.4, 0.7),
+                speed: (10.0, 30.0),
+                spread: 0.5,
+                drag: 0.5,
+                size: Curve(&[(0.0, 6.0), (1.0, 2.0)]),
+                color: Gradient(BOOST_TRAIL_COLORS),
+                z: -0.5,
+            },
+        }
+    }
+}
+
+/// Values over the life of a particle, from 0 (spawned) to 1 (gone). Points
+/// are in order, the value in between is interpolated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve(pub &'static [(f32, f32)]);
+
+impl Curve {
+    /// # Examples
+    ///
+    /// ```
+    /// use space_game::game::particles::Curve;
+    ///
+    /// let curve = Curve(&[(0.0, 10.0), (0.5, 20.0), (1.0, 0.0)]);
+    ///
+    /// assert_eq!(curve.sample(0.25), 15.0);
+    /// assert_eq!(curve.sample(0.75), 10.0);
+    /// assert_eq!(curve.sample(2.0), 0.0);
+    /// ```
+    pub fn sample(&self, t: f32) -> f32 {
+        sample(self.0, t, |a, b, f| a + (b - a) * f).unwrap_or_default()
+    }
+}
+
+/// Colors over the life of a particle, like `Curve`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gradient(pub &'static [(f32, Color)]);
+
+impl Gradient {
+    pub fn sample(&self, t: f32) -> Color {
+        sample(self.0, t, |a, b, f| {
+            let a = Vec4::from(a.as_rgba_f32());
+            let b = Vec4::from(b.as_rgba_f32());
+            Color::from(a.lerp(b, f))
+        })
+        .unwrap_or(Color::WHITE)
+    }
+}
+
+fn sample<T: Copy>(points: &[(f32, T)], t: f32, lerp: impl Fn(T, T, f32) -> T) -> Option<T> {
+    let (first, last) = (points.first()?, points.last()?);
+    if t <= first.0 {
+        return Some(first.1);
+    }
+
+    points
+        .windows(2)
+        .find(|pair| t <= pair[1].0)
+        .map(|pair| {
+            let ((t0, a), (t1, b)) = (pair[0], pair[1]);
+            lerp(a, b, (t - t0) / (t1 - t0))
+        })
+        .or(Some(last.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gradient_fades_out() {
+        let gradient = ParticlePreset::ShipExplosion.config().color;
+
+        assert_eq!(gradient.sample(0.0), Color::rgb(1.0, 1.0, 0.8));
+        assert_eq!(gradient.sample(1.0).a(), 0.0);
+        assert!(gradient.sample(0.65).a() > 0.0);
+    }
+}
@@ -0,0 +1,113 @@
+use super::PickupKind;
+use crate::game::weapon::WeaponArchetype;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What destroyed enemies drop.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DropTable {
+    /// The chance, from 0 to 1, that a destroyed enemy drops anything.
+    pub chance: f32,
+    #[serde(rename = "drop")]
+    pub drops: Vec<DropEntry>,
+}
+
+/// A pickup in a drop table and how likely it is, relative to the others.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DropEntry {
+    pub pickup: PickupKind,
+    pub weight: f32,
+}
+
+impl Default for DropTable {
+    fn default() -> Self {
+        Self {
+            chance: 0.3,
+            drops: vec![
+                DropEntry {
+                    pickup: PickupKind::Health { amount: 20 },
+                    weight: 3.0,
+                },
+                DropEntry {
+                    pickup: PickupKind::Shield { amount: 15 },
+                    weight: 3.0,
+                },
+                DropEntry {
+                    pickup: PickupKind::BoostFuel { amount: 50.0 },
+                    weight: 2.0,
+                },
+                DropEntry {
+                    pickup: PickupKind::ScoreMultiplier { amount: 2 },
+                    weight: 1.0,
+                },
+                DropEntry {
+                    pickup: PickupKind::Weapon {
+                        weapon: WeaponArchetype::Spread,
+                    },
+                    weight: 0.5,
+                },
+            ],
+        }
+    }
+}
+
+impl DropTable {
+    /// Roll for a drop. Returns `None` if nothing drops or if the weights are
+    /// invalid, e.g. all zero.
+    pub fn roll<R>(&self, rng: &mut R) -> Option<PickupKind>
+    where
+        R: Rng + ?Sized,
+    {
+        if !rng.gen_bool(self.chance.clamp(0.0, 1.0) as f64) {
+            return None;
+        }
+
+        let weights = self.drops.iter().map(|entry| entry.weight);
+        let index = WeightedIndex::new(weights).ok()?.sample(rng);
+        Some(self.drops[index].pickup)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_roll_respects_chance_and_weights() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let health = PickupKind::Health { amount: 10 };
+        let table = DropTable {
+            chance: 1.0,
+            drops: vec![
+                DropEntry {
+                    pickup: health,
+                    weight: 1.0,
+                },
+                DropEntry {
+                    pickup: PickupKind::Shield { amount: 10 },
+                    weight: 0.0,
+                },
+            ],
+        };
+
+        for _ in 0..100 {
+            assert_eq!(table.roll(&mut rng), Some(health));
+        }
+
+        let never = DropTable {
+            chance: 0.0,
+            ..table.clone()
+        };
+        assert_eq!(never.roll(&mut rng), None);
+
+        let empty = DropTable {
+            drops: vec![],
+            ..table
+        };
+        assert_eq!(empty.roll(&mut rng), None);
+    }
+}
@@ -0,0 +1,325 @@
+//! # Pickups
+//!
+//! Collectibles that drop from destroyed enemies. A pickup floats where the
+//! enemy died until it times out, and drifts towards the player when the
+//! player comes close. Flying through it sends a `PickupCollected` event and
+//! applies its effect to whoever collected it.
+
+mod drop_table;
+
+use super::assets::groups;
+use super::game_entity::GameEntityType;
+use super::level::Level;
+use super::player::components::BoostFuel;
+use super::player::{Player, PlayerLoadout};
+use super::rng::{GameRng, RngStream};
+use super::score::GameScore;
+use super::time_to_live::TimeToLive;
+use super::vitality::{DeathEvent, Health, Shield, VitalitySystem};
+use super::weapon::{Weapon, WeaponArchetype};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use drop_table::{DropEntry, DropTable};
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PickupCollected>().add_systems(
+            Update,
+            (
+                drop_pickups.after(VitalitySystem::DeathCheck),
+                attract_pickups,
+                collect_pickups,
+                apply_pickups.after(collect_pickups),
+            ),
+        );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Config
+////////////////////////////////////////////////////////////////////////////////
+
+/// How pickups behave and what drops them. Only inserted while a game with
+/// pickups is running.
+#[derive(Resource, Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PickupConfig {
+    /// Seconds before an uncollected pickup disappears.
+    pub time_to_live: f32,
+    /// Pickups closer to the player than this drift towards the player.
+    pub attraction_radius: f32,
+    /// How fast attracted pickups move, in pixels per second.
+    pub attraction_speed: f32,
+    pub drop_table: DropTable,
+}
+
+impl Default for PickupConfig {
+    fn default() -> Self {
+        Self {
+            time_to_live: 12.0,
+            attraction_radius: 250.0,
+            attraction_speed: 500.0,
+            drop_table: DropTable::default(),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+/// What a pickup does when it is collected.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum PickupKind {
+    /// Heal, up to the max health.
+    Health { amount: u32 },
+    /// Charge the shield, up to its capacity.
+    Shield { amount: u32 },
+    /// Refuel the boost, up to the capacity of the tank.
+    BoostFuel { amount: f32 },
+    /// Swap the weapon for another archetype, with the damage, heat and ammo
+    /// of the level's player loadout.
+    Weapon { weapon: WeaponArchetype },
+    /// Raise the score multiplier.
+    ScoreMultiplier { amount: u64 },
+}
+
+impl PickupKind {
+    fn color(&self) -> Color {
+        match self {
+            PickupKind::Health { .. } => Color::rgb(0.2, 0.9, 0.3),
+            PickupKind::Shield { .. } => Color::rgb(0.3, 0.7, 1.0),
+            PickupKind::BoostFuel { .. } => Color::rgb(1.0, 0.6, 0.1),
+            PickupKind::Weapon { .. } => Color::rgb(0.9, 0.2, 0.9),
+            PickupKind::ScoreMultiplier { .. } => Color::rgb(1.0, 0.9, 0.2),
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Pickup {
+    kind: PickupKind,
+}
+
+impl Pickup {
+    pub fn kind(&self) -> PickupKind {
+        self.kind
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+/// Sent when a pickup is collected, its effect is applied to the collector.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct PickupCollected {
+    pub collector: Entity,
+    pub kind: PickupKind,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Spawning
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn spawn(mut commands: Commands, level: Option<Res<Level>>) {
+    let config = level.map(|level| level.pickups.clone()).unwrap_or_default();
+    commands.insert_resource(config);
+}
+
+pub fn despawn(mut commands: Commands, query: Query<Entity, With<Pickup>>) {
+    commands.remove_resource::<PickupConfig>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+const PICKUP_RADIUS: f32 = 12.0;
+
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    kind: PickupKind,
+    location: Vec2,
+    time_to_live: f32,
+) -> Entity {
+    let shape = shapes::RegularPolygon {
+        sides: 4,
+        center: Vec2::ZERO,
+        feature: RegularPolygonFeature::Radius(PICKUP_RADIUS),
+    };
+    let color = kind.color();
+
+    commands
+        .spawn((
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shape),
+                spatial: SpatialBundle::from_transform(Transform::from_translation(
+                    location.extend(0.0),
+                )),
+                ..default()
+            },
+            Fill::color(color.with_a(0.4)),
+            Stroke::new(color, 2.0),
+        ))
+        .insert(Pickup { kind })
+        .insert(RigidBody::KinematicVelocityBased)
+        .insert(Collider::ball(PICKUP_RADIUS))
+        .insert(Sensor)
+        .insert(CollisionGroups::new(
+            groups::PICKUP_GROUP,
+            groups::PICKUP_FILTER_MASK,
+        ))
+        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert(Velocity {
+            linvel: Vec2::ZERO,
+            angvel: 1.0,
+        })
+        .insert(TimeToLive::from_seconds(time_to_live))
+        .id()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn drop_pickups(
+    mut commands: Commands,
+    config: Option<Res<PickupConfig>>,
+    mut rng: ResMut<GameRng>,
+    mut death_events: EventReader<DeathEvent>,
+) {
+    let Some(config) = config else {
+        death_events.clear();
+        return;
+    };
+
+    for death in death_events.read() {
+        if death._type() != GameEntityType::Enemy {
+            continue;
+        }
+
+        if let Some(kind) = config.drop_table.roll(rng.stream(RngStream::Drops)) {
+            spawn_pickup(&mut commands, kind, death.location(), config.time_to_live);
+        }
+    }
+}
+
+fn attract_pickups(
+    config: Option<Res<PickupConfig>>,
+    player_query: Query<&Transform, With<Player>>,
+    mut pickup_query: Query<(&Transform, &mut Velocity), With<Pickup>>,
+) {
+    let Some(config) = config else {
+        return;
+    };
+    let player_location = player_query
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+
+    for (transform, mut velocity) in pickup_query.iter_mut() {
+        let to_player = player_location
+            .map(|player| player - transform.translation.truncate())
+            .filter(|to_player| to_player.length() < config.attraction_radius);
+
+        velocity.linvel = match to_player {
+            Some(to_player) => to_player.normalize_or_zero() * config.attraction_speed,
+            None => Vec2::ZERO,
+        };
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    pickup_query: Query<&Pickup>,
+    player_query: Query<(), With<Player>>,
+    mut pickup_collected_events: EventWriter<PickupCollected>,
+) {
+    // A pickup can touch more than one collider in the same frame
+    let mut collected = Vec::new();
+
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(entity1, entity2, _) = collision_event else {
+            continue;
+        };
+
+        for (pickup_entity, collector) in [(*entity1, *entity2), (*entity2, *entity1)] {
+            let Ok(pickup) = pickup_query.get(pickup_entity) else {
+                continue;
+            };
+            if !player_query.contains(collector) || collected.contains(&pickup_entity) {
+                continue;
+            }
+
+            collected.push(pickup_entity);
+            commands.entity(pickup_entity).despawn_recursive();
+            pickup_collected_events.send(PickupCollected {
+                collector,
+                kind: pickup.kind(),
+            });
+        }
+    }
+}
+
+type CollectorQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static mut Health>,
+        Option<&'static mut Shield>,
+        Option<&'static mut BoostFuel>,
+        Option<&'static mut Weapon>,
+    ),
+>;
+
+fn apply_pickups(
+    mut pickup_collected_events: EventReader<PickupCollected>,
+    level: Option<Res<Level>>,
+    mut game_score: ResMut<GameScore>,
+    mut collector_query: CollectorQuery,
+) {
+    for PickupCollected { collector, kind } in pickup_collected_events.read() {
+        let Ok((health, shield, fuel, weapon)) = collector_query.get_mut(*collector) else {
+            continue;
+        };
+
+        match *kind {
+            PickupKind::Health { amount } => {
+                if let Some(mut health) = health {
+                    health.heal(amount);
+                }
+            }
+            PickupKind::Shield { amount } => {
+                if let Some(mut shield) = shield {
+                    shield.restore(amount);
+                }
+            }
+            PickupKind::BoostFuel { amount } => {
+                if let Some(mut fuel) = fuel {
+                    fuel.refill(amount);
+                }
+            }
+            PickupKind::Weapon { weapon: archetype } => {
+                if let Some(mut weapon) = weapon {
+                    let loadout = level.as_ref().map(|level| level.player).unwrap_or_default();
+                    *weapon = PlayerLoadout {
+                        weapon: archetype,
+                        ..loadout
+                    }
+                    .build_weapon();
+                }
+            }
+            PickupKind::ScoreMultiplier { amount } => game_score.add_multiplier(amount),
+        }
+    }
+}
@@ -1,13 +1,13 @@
+use crate::settings::keybindings::{Binding, Bindings, Keybindings, Rebindable};
 use bevy::{
     input::{gamepad::GamepadButtonType, keyboard::KeyCode},
     reflect::Reflect,
 };
-use leafwing_input_manager::{
-    axislike::DualAxis, input_map::InputMap, user_input::InputKind, Actionlike,
-};
+use leafwing_input_manager::Actionlike;
+use serde::{Deserialize, Serialize};
 
 /// Actions that can be performed by the player
-#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Debug, Reflect, Serialize, Deserialize)]
 pub enum PlayerShipAction {
     ThrottleForward,
     ThrottleBackwards,
@@ -15,52 +15,54 @@ pub enum PlayerShipAction {
     RotateShipLeft,
     RotateShipRight,
     FireWeapon,
+    Boost,
 }
 
-/// Map inputs (keyboard/mouse/gamepad) to actions
-pub fn create_input_map() -> InputMap<PlayerShipAction> {
-    // Create an `InputMap` to add default inputs to
-    let mut input_map: InputMap<PlayerShipAction> = InputMap::default();
-
-    // Add PlayerShip inputs
-    input_map.insert_multiple(vec![
-        (
-            InputKind::Keyboard(KeyCode::W),
-            PlayerShipAction::ThrottleForward,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::S),
-            PlayerShipAction::ThrottleBackwards,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::A),
-            PlayerShipAction::RotateShipLeft,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::D),
-            PlayerShipAction::RotateShipRight,
-        ),
-        (
-            InputKind::Keyboard(KeyCode::L),
-            PlayerShipAction::FireWeapon,
-        ),
-        (
-            InputKind::GamepadButton(GamepadButtonType::RightTrigger2),
-            PlayerShipAction::ThrottleForward,
-        ),
-        (
-            InputKind::GamepadButton(GamepadButtonType::LeftTrigger2),
-            PlayerShipAction::ThrottleBackwards,
-        ),
-        (
-            InputKind::GamepadButton(GamepadButtonType::South),
-            PlayerShipAction::FireWeapon,
-        ),
-        (
-            InputKind::DualAxis(DualAxis::left_stick()),
-            PlayerShipAction::RotateShip,
-        ),
-    ]);
+impl Rebindable for PlayerShipAction {
+    /// Map inputs (keyboard/mouse/gamepad) to actions
+    fn default_bindings() -> Bindings<Self> {
+        Bindings::new([
+            (
+                PlayerShipAction::ThrottleForward,
+                vec![
+                    Binding::Key(KeyCode::W),
+                    Binding::GamepadButton(GamepadButtonType::RightTrigger2),
+                ],
+            ),
+            (
+                PlayerShipAction::ThrottleBackwards,
+                vec![
+                    Binding::Key(KeyCode::S),
+                    Binding::GamepadButton(GamepadButtonType::LeftTrigger2),
+                ],
+            ),
+            (PlayerShipAction::RotateShip, vec![Binding::LeftStick]),
+            (
+                PlayerShipAction::RotateShipLeft,
+                vec![Binding::Key(KeyCode::A)],
+            ),
+            (
+                PlayerShipAction::RotateShipRight,
+                vec![Binding::Key(KeyCode::D)],
+            ),
+            (
+                PlayerShipAction::FireWeapon,
+                vec![
+                    Binding::Key(KeyCode::L),
+                    Binding::GamepadButton(GamepadButtonType::South),
+                ],
+            ),
+            (
+                PlayerShipAction::Boost,
+                vec![
+                    Binding::Key(KeyCode::ShiftLeft),
+                    Binding::GamepadButton(GamepadButtonType::LeftTrigger),
+                ],
+            ),
+        ])
+    }
 
-    input_map.build()
+    fn bindings(keybindings: &Keybindings) -> &Bindings<Self> {
+        &keybindings.game
+    }
 }
@@ -27,3 +27,168 @@ impl ContactForceInvulnerability {
 
 #[derive(Component)]
 pub struct Player;
+
+/// Fuel for the boost. Drains while boosting and regenerates when not.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct BoostFuel {
+    current: f32,
+    capacity: f32,
+    /// Fuel used per second of boosting.
+    drain_rate: f32,
+    /// Fuel regained per second when not boosting.
+    regen_rate: f32,
+}
+
+impl BoostFuel {
+    pub fn new(capacity: f32, drain_rate: f32, regen_rate: f32) -> Self {
+        Self {
+            current: capacity,
+            capacity,
+            drain_rate,
+            regen_rate,
+        }
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    /// How full the tank is, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.capacity <= 0.0 {
+            return 0.0;
+        }
+        self.current / self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    pub fn drain(&mut self, delta: Duration) {
+        self.current = (self.current - self.drain_rate * delta.as_secs_f32()).max(0.0);
+    }
+
+    pub fn regenerate(&mut self, delta: Duration) {
+        self.current = (self.current + self.regen_rate * delta.as_secs_f32()).min(self.capacity);
+    }
+
+    /// Add fuel, up to the capacity of the tank.
+    pub fn refill(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.capacity);
+    }
+}
+
+/// Boosting multiplies the forward thrust and raises the top speed.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Boost {
+    pub thrust_multiplier: f32,
+    /// Top speed when not boosting. Going faster, e.g. after a boost, slowly
+    /// brings the ship back down to it.
+    pub max_speed: f32,
+    pub boost_max_speed: f32,
+    /// Fuel needed to start a boost, so that an empty tank does not stutter.
+    pub min_fuel_to_start: f32,
+    /// Trauma added when the boost kicks in.
+    pub trauma: f32,
+    active: bool,
+}
+
+impl Default for Boost {
+    fn default() -> Self {
+        Self {
+            thrust_multiplier: 2.5,
+            max_speed: 700.0,
+            boost_max_speed: 1200.0,
+            min_fuel_to_start: 10.0,
+            trauma: 0.3,
+            active: false,
+        }
+    }
+}
+
+impl Boost {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// The speed the ship is allowed to go right now.
+    pub fn current_max_speed(&self) -> f32 {
+        if self.active {
+            self.boost_max_speed
+        } else {
+            self.max_speed
+        }
+    }
+
+    /// Start or stop boosting. Returns true if the boost just started.
+    pub fn update(&mut self, wants_boost: bool, fuel: &BoostFuel) -> bool {
+        let was_active = self.active;
+
+        self.active = wants_boost
+            && !fuel.is_empty()
+            && (was_active || fuel.current() >= self.min_fuel_to_start);
+
+        self.active && !was_active
+    }
+}
+
+/// Whether the weapon keeps firing in `FireMode::Toggle`, and whether the
+/// fire input was held last frame, to find when it is pressed again.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FireToggle {
+    pub firing: bool,
+    pub was_pressed: bool,
+}
+
+impl FireToggle {
+    /// Update with whether the fire input is held. Returns whether the
+    /// weapon should be firing.
+    pub fn update(&mut self, pressed: bool) -> bool {
+        if pressed && !self.was_pressed {
+            self.firing = !self.firing;
+        }
+        self.was_pressed = pressed;
+        self.firing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boost_needs_fuel_to_start() {
+        let mut boost = Boost::default();
+        let mut fuel = BoostFuel::new(20.0, 10.0, 5.0);
+
+        assert!(boost.update(true, &fuel));
+        // Already boosting, so it did not just start
+        assert!(!boost.update(true, &fuel));
+
+        fuel.drain(Duration::from_secs_f32(1.5));
+        assert!(!boost.update(true, &fuel));
+        assert!(boost.is_active());
+
+        fuel.drain(Duration::from_secs_f32(1.0));
+        boost.update(true, &fuel);
+        assert!(!boost.is_active());
+
+        // Not enough fuel to start again
+        fuel.regenerate(Duration::from_secs_f32(1.0));
+        assert!(!boost.update(true, &fuel));
+    }
+
+    #[test]
+    fn test_fire_toggle_flips_when_pressed_again() {
+        let mut toggle = FireToggle::default();
+
+        assert!(toggle.update(true));
+        // Holding the button does not flip it back
+        assert!(toggle.update(true));
+        assert!(toggle.update(false));
+
+        assert!(!toggle.update(true));
+        assert!(!toggle.update(false));
+    }
+}
@@ -4,21 +4,29 @@ mod systems;
 
 use crate::game::average_velocity::AverageVelocity;
 use crate::game::control_system::DirectionControl;
+use crate::game::events::PlayerHit;
+use crate::game::faction::{Faction, FactionBody};
 use crate::game::game_entity::GameEntityType;
+use crate::game::particles::{self, EmitterSource, ParticlePreset};
 use crate::game::trauma::Trauma;
-use crate::game::vitality::Health;
-use crate::game::{assets, assets::groups, weapon::Weapon};
+use crate::game::vitality::{self, Health, Shield, ShieldConfig};
+use crate::game::{
+    assets,
+    weapon::{AmmoConfig, HeatConfig, Weapon, WeaponArchetype},
+};
+use crate::settings::keybindings::Rebindable;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::action_state::ActionState;
-use leafwing_input_manager::plugin::InputManagerPlugin;
+use leafwing_input_manager::plugin::{InputManagerPlugin, InputManagerSystem};
 use leafwing_input_manager::InputManagerBundle;
+use serde::{Deserialize, Serialize};
 use systems::*;
 
 pub use actions::PlayerShipAction;
 pub use components::Player;
 
-use self::components::ContactForceInvulnerability;
+use self::components::{Boost, BoostFuel, ContactForceInvulnerability, FireToggle};
 
 ////////////////////////////////////////////////////////////////////////////////
 // Plugin
@@ -29,10 +37,24 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(InputManagerPlugin::<PlayerShipAction>::default())
+            .add_event::<PlayerHit>()
+            .init_resource::<FireMode>()
+            .configure_sets(
+                PreUpdate,
+                AdjustPlayerActions.after(InputManagerSystem::Update),
+            )
+            .add_systems(
+                PreUpdate,
+                toggle_fire
+                    .run_if(resource_equals(FireMode::Toggle))
+                    .in_set(AdjustPlayerActions),
+            )
             .add_systems(
                 Update,
                 (
+                    update_boost.before(control_ship),
                     control_ship,
+                    limit_speed.after(control_ship),
                     fire_weapon,
                     player_collision,
                     update_contact_force_invulnerability,
@@ -41,6 +63,89 @@ impl Plugin for PlayerPlugin {
     }
 }
 
+/// Changes the player actions after they are read from the input, and before
+/// they are recorded for a replay.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AdjustPlayerActions;
+
+/// How the fire button works.
+#[derive(Resource, Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FireMode {
+    /// Fire while the button is held.
+    #[default]
+    Hold,
+    /// Press once to start firing and again to stop.
+    Toggle,
+}
+
+/// Where the engine is, from the center of the ship.
+const PLAYER_ENGINE_OFFSET: Vec2 = Vec2::new(0.0, -38.0);
+
+/// The radius of the shield bubble around the ship.
+const PLAYER_SHIELD_RADIUS: f32 = 60.0;
+
+/// A full tank lasts 2.5 seconds of boosting and takes 8 seconds to refill.
+const PLAYER_BOOST_FUEL: f32 = 100.0;
+const PLAYER_BOOST_DRAIN_RATE: f32 = 40.0;
+const PLAYER_BOOST_REGEN_RATE: f32 = 12.5;
+
+////////////////////////////////////////////////////////////////////////////////
+// Loadout
+////////////////////////////////////////////////////////////////////////////////
+
+/// What the player starts out with.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PlayerLoadout {
+    pub max_health: u32,
+    pub weapon_damage: u32,
+    /// Seconds between two shots.
+    pub weapon_cooldown: f32,
+    #[serde(default)]
+    pub weapon: WeaponArchetype,
+    /// The weapon never overheats if not set.
+    #[serde(default)]
+    pub heat: Option<HeatConfig>,
+    /// The ammo is unlimited if not set.
+    #[serde(default)]
+    pub ammo: Option<AmmoConfig>,
+    /// No shield if not set.
+    #[serde(default)]
+    pub shield: Option<ShieldConfig>,
+}
+
+impl Default for PlayerLoadout {
+    fn default() -> Self {
+        Self {
+            max_health: 100,
+            weapon_damage: 10,
+            weapon_cooldown: 0.1,
+            weapon: WeaponArchetype::Laser,
+            heat: Some(HeatConfig::default()),
+            ammo: None,
+            shield: Some(ShieldConfig::default()),
+        }
+    }
+}
+
+impl PlayerLoadout {
+    pub fn build_weapon(&self) -> Weapon {
+        let mut weapon = Weapon::from_archetype(
+            self.weapon,
+            self.weapon_damage,
+            self.weapon_cooldown,
+            Faction::Player,
+        );
+        if let Some(heat) = self.heat {
+            weapon = weapon.with_heat(heat);
+        }
+        if let Some(ammo) = self.ammo {
+            weapon = weapon.with_ammo(ammo);
+        }
+        weapon
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Spawning
 ////////////////////////////////////////////////////////////////////////////////
@@ -60,6 +165,22 @@ pub fn spawn_player(
     asset_server: &Res<AssetServer>,
     location: Vec2,
     rotation: f32,
+) -> Entity {
+    spawn_player_with_loadout(
+        commands,
+        asset_server,
+        location,
+        rotation,
+        &PlayerLoadout::default(),
+    )
+}
+
+pub fn spawn_player_with_loadout(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    location: Vec2,
+    rotation: f32,
+    loadout: &PlayerLoadout,
 ) -> Entity {
     // Spawn transform
     let spawn_transform = Transform::from_xyz(location.x, location.y, 0.0)
@@ -67,7 +188,7 @@ pub fn spawn_player(
 
     // Add the player entity
 
-    commands
+    let player = commands
         .spawn(SpriteBundle {
             transform: spawn_transform,
             texture: asset_server.load(assets::PLAYER_SHIP.sprite_path),
@@ -81,7 +202,7 @@ pub fn spawn_player(
         })
         .insert(InputManagerBundle::<PlayerShipAction> {
             action_state: ActionState::default(),
-            input_map: actions::create_input_map(),
+            input_map: PlayerShipAction::default_bindings().input_map(),
         })
         .insert(RigidBody::Dynamic)
         .insert(assets::PLAYER_SHIP.collider())
@@ -91,15 +212,8 @@ pub fn spawn_player(
         .insert(ContactForceInvulnerability::new(0.1))
         .insert(ReadMassProperties::default())
         .insert(ContactForceEventThreshold(0.0)) // TODO: increase this to some reasonable value
-        .insert(Health::at_max(100))
-        .insert(CollisionGroups::new(
-            groups::PLAYER_GROUP.into(),
-            groups::PLAYER_FILTER_MASK.into(),
-        ))
-        .insert(SolverGroups::new(
-            groups::PLAYER_GROUP.into(),
-            groups::PLAYER_FILTER_MASK.into(),
-        ))
+        .insert(Health::at_max(loadout.max_health))
+        .insert((Faction::Player, FactionBody::Ship))
         .insert(Damping {
             linear_damping: 0.5,
             angular_damping: 1.0,
@@ -117,15 +231,37 @@ pub fn spawn_player(
             angvel: 0.0,
         })
         .insert(AverageVelocity::new(0.5))
-        .insert(Weapon::laser(
-            10,
-            1000.0,
-            Timer::from_seconds(1.0, TimerMode::Once),
-            Some(Timer::from_seconds(0.1, TimerMode::Repeating)),
-            groups::PLAYER_PROJECTILE_GROUP,
-            groups::PLAYER_PROJECTILE_FILTER_MASK,
+        .insert(FireToggle::default())
+        .insert(loadout.build_weapon())
+        .insert(Boost::default())
+        .insert(BoostFuel::new(
+            PLAYER_BOOST_FUEL,
+            PLAYER_BOOST_DRAIN_RATE,
+            PLAYER_BOOST_REGEN_RATE,
         ))
-        .id()
+        .id();
+
+    particles::spawn_emitter(
+        commands,
+        player,
+        ParticlePreset::EngineExhaust,
+        EmitterSource::Thrust,
+        PLAYER_ENGINE_OFFSET,
+    );
+    particles::spawn_emitter(
+        commands,
+        player,
+        ParticlePreset::BoostTrail,
+        EmitterSource::Boost,
+        PLAYER_ENGINE_OFFSET,
+    );
+
+    if let Some(shield) = loadout.shield {
+        commands.entity(player).insert(Shield::new(shield));
+        vitality::spawn_bubble(commands, player, PLAYER_SHIELD_RADIUS);
+    }
+
+    player
 }
 
 pub fn despawn(mut commands: Commands, player_query: Query<Entity, With<Player>>) {
@@ -134,6 +270,6 @@ pub fn despawn(mut commands: Commands, player_query: Query<Entity, With<Player>>
 
 pub fn despawn_all(commands: &mut Commands, player_query: &Query<Entity, With<Player>>) {
     if let Ok(player_entity) = player_query.get_single() {
-        commands.entity(player_entity).despawn();
+        commands.entity(player_entity).despawn_recursive();
     }
 }
@@ -1,27 +1,35 @@
-use super::components::{ContactForceInvulnerability, Player};
+use super::components::{Boost, BoostFuel, ContactForceInvulnerability, FireToggle, Player};
 use super::PlayerShipAction;
 use crate::game::control_system::DirectionControl;
+use crate::game::events::PlayerHit;
 use crate::game::trauma::Trauma;
-use crate::game::vitality::Health;
+use crate::game::vitality::{take_damage, Health, Shield};
 use crate::game::weapon::Weapon;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
 
-pub fn control_ship(
-    mut query: Query<
-        (
-            &mut ExternalImpulse,
-            &Transform,
-            &mut DirectionControl,
-            &ActionState<PlayerShipAction>,
-        ),
-        With<Player>,
-    >,
-) {
-    if let Ok((mut player_impulse, player_transform, mut direction_control, input_action)) =
+type ControlShipQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut ExternalImpulse,
+        &'static Transform,
+        &'static mut DirectionControl,
+        &'static ActionState<PlayerShipAction>,
+        Option<&'static Boost>,
+    ),
+    With<Player>,
+>;
+
+pub fn control_ship(mut query: ControlShipQuery) {
+    if let Ok((mut player_impulse, player_transform, mut direction_control, input_action, boost)) =
         query.get_single_mut()
     {
+        let thrust_multiplier = boost
+            .filter(|boost| boost.is_active())
+            .map_or(1.0, |boost| boost.thrust_multiplier);
+
         if input_action.pressed(PlayerShipAction::ThrottleForward) {
             // Note that some gamepad buttons are also tied to axes, so even though we used a
             // GamepadbuttonType::RightTrigger2 binding to trigger the throttle action, we can get a
@@ -29,7 +37,8 @@ pub fn control_ship(
             // we expect a value between 0.0 and 1.0
             let value: f32 = input_action
                 .value(PlayerShipAction::ThrottleForward)
-                .clamp(0.0, 1.0);
+                .clamp(0.0, 1.0)
+                * thrust_multiplier;
 
             let impulse = player_transform
                 .rotation
@@ -79,6 +88,64 @@ pub fn control_ship(
     }
 }
 
+pub fn update_boost(
+    time: Res<Time>,
+    mut query: Query<
+        (
+            &mut Boost,
+            &mut BoostFuel,
+            &mut Trauma,
+            &ActionState<PlayerShipAction>,
+        ),
+        With<Player>,
+    >,
+) {
+    for (mut boost, mut fuel, mut trauma, action) in query.iter_mut() {
+        if boost.update(action.pressed(PlayerShipAction::Boost), &fuel) {
+            trauma.add_trauma(boost.trauma);
+        }
+
+        if boost.is_active() {
+            fuel.drain(time.delta());
+        } else {
+            fuel.regenerate(time.delta());
+        }
+    }
+}
+
+/// How fast the ship slows down to its top speed after a boost, in pixels per
+/// second squared.
+const OVER_SPEED_DECELERATION: f32 = 400.0;
+
+pub fn limit_speed(time: Res<Time>, mut query: Query<(&Boost, &mut Velocity), With<Player>>) {
+    for (boost, mut velocity) in query.iter_mut() {
+        let speed = velocity.linvel.length();
+        let max_speed = boost.current_max_speed();
+
+        if speed > max_speed {
+            let slowed = speed - OVER_SPEED_DECELERATION * time.delta_seconds();
+            velocity.linvel = velocity.linvel.clamp_length_max(slowed.max(max_speed));
+        }
+    }
+}
+
+/// In `FireMode::Toggle` the fire action stays pressed until the fire input
+/// is pressed again.
+pub fn toggle_fire(
+    mut query: Query<(&mut FireToggle, &mut ActionState<PlayerShipAction>), With<Player>>,
+) {
+    for (mut toggle, mut action) in query.iter_mut() {
+        let pressed = action.pressed(PlayerShipAction::FireWeapon);
+
+        if toggle.update(pressed) {
+            action.press(PlayerShipAction::FireWeapon);
+            action.action_data_mut(PlayerShipAction::FireWeapon).value = 1.0;
+        } else if pressed {
+            action.release(PlayerShipAction::FireWeapon);
+        }
+    }
+}
+
 pub fn fire_weapon(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
@@ -91,20 +158,29 @@ pub fn fire_weapon(
                 weapon.fire(&mut commands, &asset_server, player_transform.clone());
             }
         }
+        if action.just_released(PlayerShipAction::FireWeapon) {
+            weapon.release(&mut commands, &asset_server, *player_transform);
+        }
     }
 }
 
+type PlayerCollisionQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Trauma,
+        &'static mut Health,
+        Option<&'static mut Shield>,
+        &'static ReadMassProperties,
+        &'static mut ContactForceInvulnerability,
+    ),
+    With<Player>,
+>;
+
 pub fn player_collision(
     mut contact_force_events: EventReader<ContactForceEvent>,
-    mut player_query: Query<
-        (
-            &mut Trauma,
-            &mut Health,
-            &ReadMassProperties,
-            &mut ContactForceInvulnerability,
-        ),
-        With<Player>,
-    >,
+    mut player_hit_events: EventWriter<PlayerHit>,
+    mut player_query: PlayerCollisionQuery,
 ) {
     for contact_force_event in contact_force_events.read() {
         if player_query.contains(contact_force_event.collider1)
@@ -113,6 +189,7 @@ pub fn player_collision(
             if let Ok((
                 mut player_trauma,
                 mut player_health,
+                mut player_shield,
                 mass_properties,
                 mut contact_force_invulnerability,
             )) = player_query.get_single_mut()
@@ -123,7 +200,11 @@ pub fn player_collision(
                         contact_force_event.total_force_magnitude / mass_properties.mass;
                     let effect = (adjusted_force / 400.0).min(1.0);
                     // Take damage
-                    player_health.take_damage_u32((effect * 10.0) as u32);
+                    let damage = (effect * 10.0) as u32;
+                    take_damage(&mut player_health, player_shield.as_deref_mut(), damage);
+                    if damage > 0 {
+                        player_hit_events.send(PlayerHit { damage });
+                    }
                     // Trauma
                     player_trauma.add_trauma(effect);
                 }
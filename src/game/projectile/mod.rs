@@ -1,8 +1,11 @@
+use super::accessibility::Palette;
 use super::assets;
+use super::faction::{Faction, FactionBody, FactionRelations};
 use super::time_to_live::TimeToLive;
 use super::vitality::*;
 use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
 use bevy_rapier2d::prelude::*;
 use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
 
@@ -14,10 +17,34 @@ pub struct ProjectilePlugin;
 
 impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_projectiles_on_collision);
+        app.add_event::<ProjectileImpact>().add_systems(
+            Update,
+            (
+                update_projectiles_on_collision,
+                update_homing_projectiles,
+                update_proximity_fuses,
+            ),
+        );
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+/// A projectile hit something, or a mine went off.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ProjectileImpact {
+    pub projectile_type: ProjectileType,
+    pub position: Vec2,
+    /// Where the projectile was going, zero if it was not moving.
+    pub direction: Vec2,
+    /// What was hit. Not set when a mine goes off.
+    pub target: Option<Entity>,
+    /// Whose weapon fired the projectile, if known.
+    pub shooter: Option<Entity>,
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Components
 ////////////////////////////////////////////////////////////////////////////////
@@ -29,12 +56,70 @@ impl Projectile {
     pub fn new(projectile_type: ProjectileType) -> Self {
         Self(projectile_type)
     }
+
+    pub fn projectile_type(&self) -> ProjectileType {
+        self.0
+    }
 }
 
+/// The entity whose weapon fired the projectile.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shooter(pub Entity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProjectileType {
     Laser,
+    Beam,
+    Missile,
+    Mine,
+}
+
+/// What every projectile fired by a weapon has in common.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProjectileStats {
+    pub damage: u32,
+    pub speed: f32,
+    /// Seconds before the projectile is despawned.
+    pub time_to_live: f32,
+    /// The faction that fired it, which decides what it hits.
+    pub faction: Faction,
+    /// The entity that fired it. Set when the weapon is added to an entity.
+    pub shooter: Option<Entity>,
+}
+
+/// Steers the projectile towards the closest ship of a hostile faction within
+/// `range`.
+#[derive(Component)]
+pub struct Homing {
+    /// Radians per second.
+    pub turn_rate: f32,
+    pub range: f32,
+    target: Option<Entity>,
 }
 
+impl Homing {
+    pub fn new(turn_rate: f32, range: f32) -> Self {
+        Self {
+            turn_rate,
+            range,
+            target: None,
+        }
+    }
+}
+
+/// Detonates the projectile when a ship of a hostile faction comes within
+/// `radius`. Everything that is not friendly within `blast_radius` takes its
+/// damage.
+#[derive(Component)]
+pub struct ProximityFuse {
+    pub radius: f32,
+    pub blast_radius: f32,
+}
+
+/// The number of things the projectile passes through before it is despawned.
+#[derive(Component)]
+pub struct Piercing(pub u32);
+
 ////////////////////////////////////////////////////////////////////////////////
 // Helpers
 ////////////////////////////////////////////////////////////////////////////////
@@ -45,40 +130,163 @@ pub fn despawn_projectiles(commands: &mut Commands, query: Query<Entity, With<Pr
     }
 }
 
-pub fn spawn_laser_projectile(
+/// Spawn a projectile flying in the direction the transform is facing.
+/// Returns the entity so that behaviours can be added to it.
+pub fn spawn_projectile(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     spawn_transform: Transform,
-    collision_membership: &Group,
-    collision_filter: &Group,
-    damage: u32,
-) {
+    stats: &ProjectileStats,
+    projectile_type: ProjectileType,
+) -> Entity {
     let laser_projectile = assets::PROJECTILE_LASER;
 
-    commands
+    // Recolored for the chosen palette, see `accessibility`
+    let color = Palette::default().colors().projectile(projectile_type);
+
+    let entity = commands
         .spawn(SpriteBundle {
             transform: spawn_transform,
             texture: asset_server.load(laser_projectile.sprite_path),
+            sprite: Sprite { color, ..default() },
             ..default()
         })
         .insert(RigidBody::Dynamic)
         .insert(laser_projectile.collider())
-        .insert(CollisionGroups::new(
-            (*collision_membership).into(),
-            (*collision_filter).into(),
-        ))
+        .insert((stats.faction, FactionBody::Projectile))
         .insert(ActiveEvents::COLLISION_EVENTS)
-        .insert(SolverGroups::new(
-            (*collision_membership).into(),
-            (*collision_filter).into(),
-        ))
         .insert(Velocity {
-            linvel: spawn_transform.rotation.mul_vec3(Vec3::Y).xy().normalize() * 1000.0,
+            linvel: spawn_transform.rotation.mul_vec3(Vec3::Y).xy().normalize() * stats.speed,
             angvel: 0.0,
         })
-        .insert(Projectile::new(ProjectileType::Laser))
-        .insert(Damage(damage))
-        .insert(TimeToLive::from_seconds(3.0));
+        .insert(Projectile::new(projectile_type))
+        .insert(Damage(stats.damage))
+        .insert(TimeToLive::from_seconds(stats.time_to_live))
+        .id();
+
+    insert_shooter(commands, entity, stats);
+    entity
+}
+
+fn insert_shooter(commands: &mut Commands, entity: Entity, stats: &ProjectileStats) {
+    if let Some(shooter) = stats.shooter {
+        commands.entity(entity).insert(Shooter(shooter));
+    }
+}
+
+pub fn spawn_laser_projectile(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    spawn_transform: Transform,
+    stats: &ProjectileStats,
+) -> Entity {
+    spawn_projectile(
+        commands,
+        asset_server,
+        spawn_transform,
+        stats,
+        ProjectileType::Laser,
+    )
+}
+
+/// A long projectile that passes through `pierce` things before it is gone.
+pub fn spawn_beam_projectile(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    mut spawn_transform: Transform,
+    stats: &ProjectileStats,
+    length: f32,
+    pierce: u32,
+) -> Entity {
+    spawn_transform.scale = Vec3::new(1.0, length, 1.0);
+
+    let entity = spawn_projectile(
+        commands,
+        asset_server,
+        spawn_transform,
+        stats,
+        ProjectileType::Beam,
+    );
+
+    // A sensor is not pushed around by what it hits, so it can pass through
+    commands.entity(entity).insert((Sensor, Piercing(pierce)));
+
+    entity
+}
+
+pub fn spawn_missile_projectile(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    spawn_transform: Transform,
+    stats: &ProjectileStats,
+    turn_rate: f32,
+    range: f32,
+) -> Entity {
+    let entity = spawn_projectile(
+        commands,
+        asset_server,
+        spawn_transform,
+        stats,
+        ProjectileType::Missile,
+    );
+
+    commands
+        .entity(entity)
+        .insert(Homing::new(turn_rate, range));
+
+    entity
+}
+
+/// A mine stays where it is dropped until something comes close.
+pub fn spawn_mine_projectile(
+    commands: &mut Commands,
+    spawn_transform: Transform,
+    stats: &ProjectileStats,
+    fuse_radius: f32,
+    blast_radius: f32,
+) -> Entity {
+    let colors = Palette::default().colors();
+    let mine_radius = 8.0;
+    let shape = shapes::Circle {
+        radius: mine_radius,
+        center: Vec2::ZERO,
+    };
+
+    let entity = commands
+        .spawn((
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shape),
+                spatial: SpatialBundle::from_transform(spawn_transform),
+                ..default()
+            },
+            Fill::color(mine_fill(colors.mine)),
+            Stroke::new(colors.mine, 2.0),
+        ))
+        .insert(RigidBody::Dynamic)
+        .insert(Collider::ball(mine_radius))
+        .insert((stats.faction, FactionBody::Projectile))
+        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert(Velocity::zero())
+        .insert(Damping {
+            linear_damping: 2.0,
+            angular_damping: 2.0,
+        })
+        .insert(Projectile::new(ProjectileType::Mine))
+        .insert(ProximityFuse {
+            radius: fuse_radius,
+            blast_radius,
+        })
+        .insert(Damage(stats.damage))
+        .insert(TimeToLive::from_seconds(stats.time_to_live))
+        .id();
+
+    insert_shooter(commands, entity, stats);
+    entity
+}
+
+/// The inside of a mine is a darker shade of its outline.
+pub fn mine_fill(color: Color) -> Color {
+    color * 0.4
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -88,8 +296,9 @@ pub fn spawn_laser_projectile(
 fn update_projectiles_on_collision(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
-    projectile_query: Query<(&Projectile, Option<&Damage>), Without<Health>>,
-    mut health_query: Query<&mut Health, Without<Projectile>>,
+    mut impact_events: EventWriter<ProjectileImpact>,
+    mut projectile_query: ProjectileQuery,
+    mut health_query: HealthQuery,
 ) {
     for collision_event in collision_events.read() {
         match collision_event {
@@ -101,7 +310,8 @@ fn update_projectiles_on_collision(
 
                 let did_resolve = resolve_projectile_collision(
                     &mut commands,
-                    &projectile_query,
+                    &mut impact_events,
+                    &mut projectile_query,
                     &mut health_query,
                     entity1,
                     entity2,
@@ -110,7 +320,8 @@ fn update_projectiles_on_collision(
                 if !did_resolve {
                     resolve_projectile_collision(
                         &mut commands,
-                        &projectile_query,
+                        &mut impact_events,
+                        &mut projectile_query,
                         &mut health_query,
                         entity2,
                         entity1,
@@ -122,21 +333,202 @@ fn update_projectiles_on_collision(
     }
 }
 
+type HealthQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Health,
+        Option<&'static mut Shield>,
+        Option<&'static Armor>,
+    ),
+    Without<Projectile>,
+>;
+
+type ProjectileQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Projectile,
+        &'static Transform,
+        Option<&'static Velocity>,
+        Option<&'static Damage>,
+        Option<&'static mut Piercing>,
+        Option<&'static Shooter>,
+    ),
+    Without<Health>,
+>;
+
 fn resolve_projectile_collision(
     commands: &mut Commands,
-    projectile_query: &Query<(&Projectile, Option<&Damage>), Without<Health>>,
-    health_query: &mut Query<&mut Health, Without<Projectile>>,
+    impact_events: &mut EventWriter<ProjectileImpact>,
+    projectile_query: &mut ProjectileQuery,
+    health_query: &mut HealthQuery,
     entity1: &Entity,
     entity2: &Entity,
 ) -> bool {
-    if let Ok((_, damge_opt)) = projectile_query.get(*entity1) {
-        commands.entity(*entity1).despawn_recursive();
+    if let Ok((projectile, transform, velocity, damge_opt, piercing_opt, shooter)) =
+        projectile_query.get_mut(*entity1)
+    {
+        impact_events.send(ProjectileImpact {
+            projectile_type: projectile.projectile_type(),
+            position: transform.translation.xy(),
+            direction: velocity.map_or(Vec2::ZERO, |v| v.linvel.normalize_or_zero()),
+            target: Some(*entity2),
+            shooter: shooter.map(|shooter| shooter.0),
+        });
+        match piercing_opt {
+            Some(mut piercing) if piercing.0 > 0 => piercing.0 -= 1,
+            _ => commands.entity(*entity1).despawn_recursive(),
+        }
         if let Some(damage) = damge_opt {
-            if let Ok(mut health) = health_query.get_mut(*entity2) {
-                health.take_damage(damage);
+            if let Ok((mut health, mut shield, armor)) = health_query.get_mut(*entity2) {
+                let damage = armor.map_or(damage.0, |armor| armor.reduce(damage.0));
+                take_damage(&mut health, shield.as_deref_mut(), damage);
             }
         }
         return true;
     }
     return false;
 }
+
+type HomingTargetQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static Transform, &'static Faction),
+    (With<Health>, Without<Projectile>),
+>;
+
+type HomingQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Homing,
+        &'static Faction,
+        &'static mut Velocity,
+        &'static mut Transform,
+    ),
+    With<Projectile>,
+>;
+
+fn update_homing_projectiles(
+    time: Res<Time>,
+    relations: Res<FactionRelations>,
+    mut projectile_query: HomingQuery,
+    target_query: HomingTargetQuery,
+) {
+    let dt = time.delta_seconds();
+
+    for (mut homing, faction, mut velocity, mut transform) in projectile_query.iter_mut() {
+        let position = transform.translation.xy();
+
+        // Keep the current target while it is alive and in range
+        let target_position = homing
+            .target
+            .and_then(|target| target_query.get(target).ok())
+            .map(|(_, target_transform, _)| target_transform.translation.xy())
+            .filter(|target| target.distance(position) <= homing.range);
+
+        let target_position = match target_position {
+            Some(target_position) => Some(target_position),
+            None => {
+                let closest = target_query
+                    .iter()
+                    .filter(|(_, _, target)| relations.is_hostile(*faction, **target))
+                    .map(|(entity, target_transform, _)| {
+                        (entity, target_transform.translation.xy())
+                    })
+                    .filter(|(_, target)| target.distance(position) <= homing.range)
+                    .min_by(|(_, a), (_, b)| {
+                        a.distance_squared(position)
+                            .total_cmp(&b.distance_squared(position))
+                    });
+
+                homing.target = closest.map(|(entity, _)| entity);
+                closest.map(|(_, target)| target)
+            }
+        };
+
+        let Some(target_position) = target_position else {
+            continue;
+        };
+
+        // Turn towards the target, no faster than the turn rate
+        let desired_angle = velocity.linvel.angle_between(target_position - position);
+        if desired_angle.is_nan() {
+            continue;
+        }
+        let max_turn = homing.turn_rate * dt;
+        let turn = desired_angle.clamp(-max_turn, max_turn);
+
+        velocity.linvel = Vec2::from_angle(turn).rotate(velocity.linvel);
+        transform.rotation = Quat::from_rotation_z(Vec2::Y.angle_between(velocity.linvel));
+    }
+}
+
+type MineQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static ProximityFuse,
+        &'static Faction,
+        &'static Damage,
+        Option<&'static Shooter>,
+    ),
+    With<Projectile>,
+>;
+
+type FuseTargetQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static Faction,
+        &'static mut Health,
+        Option<&'static mut Shield>,
+        Option<&'static Armor>,
+    ),
+    Without<Projectile>,
+>;
+
+fn update_proximity_fuses(
+    mut commands: Commands,
+    mut impact_events: EventWriter<ProjectileImpact>,
+    relations: Res<FactionRelations>,
+    mine_query: MineQuery,
+    mut target_query: FuseTargetQuery,
+) {
+    for (entity, transform, fuse, mine_faction, damage, shooter) in mine_query.iter() {
+        let position = transform.translation.xy();
+
+        let triggered = target_query
+            .iter()
+            .any(|(target_transform, faction, _, _, _)| {
+                relations.is_hostile(*mine_faction, *faction)
+                    && target_transform.translation.xy().distance(position) <= fuse.radius
+            });
+
+        if !triggered {
+            continue;
+        }
+
+        for (target_transform, faction, mut health, mut shield, armor) in target_query.iter_mut() {
+            if !relations.is_friendly(*mine_faction, *faction)
+                && target_transform.translation.xy().distance(position) <= fuse.blast_radius
+            {
+                let damage = armor.map_or(damage.0, |armor| armor.reduce(damage.0));
+                take_damage(&mut health, shield.as_deref_mut(), damage);
+            }
+        }
+
+        impact_events.send(ProjectileImpact {
+            projectile_type: ProjectileType::Mine,
+            position,
+            direction: Vec2::ZERO,
+            target: None,
+            shooter: shooter.map(|shooter| shooter.0),
+        });
+        commands.entity(entity).despawn_recursive();
+    }
+}
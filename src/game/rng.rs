@@ -0,0 +1,165 @@
+//! # Game RNG
+//!
+//! All gameplay randomness is drawn from the `GameRng` resource. Given the same
+//! seed the game will spawn the same arena, the same enemies and make the same
+//! AI decisions, which makes bug reports, replays and balancing runs reproducible.
+//!
+//! Each subsystem draws from its own stream. The streams are forked from the seed
+//! independently of each other, so adding a random call in one subsystem does not
+//! change what another subsystem sees.
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+
+////////////////////////////////////////////////////////////////////////////////
+// Streams
+////////////////////////////////////////////////////////////////////////////////
+
+/// The subsystems that draw random numbers.
+///
+/// NOTE: Only add new streams at the end, the position of a stream is used to
+/// derive its seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    /// Layout of the arena, e.g. where asteroids are placed.
+    Arena,
+    /// Where and when enemies spawn.
+    EnemySpawn,
+    /// Decisions made by the enemy AI.
+    EnemyAI,
+    /// Setup of the debug scenes.
+    Scene,
+    /// What destroyed enemies drop.
+    Drops,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Resource
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Resource, Debug)]
+pub struct GameRng {
+    seed: u64,
+    streams: HashMap<RngStream, StdRng>,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Create a new `GameRng` with a random seed.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+
+    /// The seed all streams are forked from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Get the random number generator for a subsystem.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::Rng;
+    /// use space_game::game::rng::{GameRng, RngStream};
+    ///
+    /// let mut a = GameRng::new(42);
+    /// let mut b = GameRng::new(42);
+    ///
+    /// // Drawing from one stream does not affect the others
+    /// let _: u32 = a.stream(RngStream::Arena).gen();
+    ///
+    /// let x: u32 = a.stream(RngStream::EnemyAI).gen();
+    /// let y: u32 = b.stream(RngStream::EnemyAI).gen();
+    /// assert_eq!(x, y);
+    /// ```
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        let seed = self.seed;
+        self.streams
+            .entry(stream)
+            .or_insert_with(|| StdRng::seed_from_u64(stream_seed(seed, stream)))
+    }
+
+    /// Restart every stream from the beginning.
+    pub fn reset(&mut self) {
+        self.streams.clear();
+    }
+
+    /// Start over with a new seed.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.reset();
+    }
+}
+
+/// Derive the seed of a stream from the game seed.
+///
+/// Uses the SplitMix64 finalizer so that nearby seeds and streams give
+/// unrelated sequences.
+fn stream_seed(seed: u64, stream: RngStream) -> u64 {
+    let mut z = seed ^ (stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(rng: &mut GameRng, stream: RngStream) -> Vec<u32> {
+        (0..8).map(|_| rng.stream(stream).gen()).collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = GameRng::new(1234);
+        let mut b = GameRng::new(1234);
+
+        assert_eq!(
+            draw(&mut a, RngStream::Arena),
+            draw(&mut b, RngStream::Arena)
+        );
+    }
+
+    #[test]
+    fn test_streams_are_independent() {
+        let mut a = GameRng::new(1234);
+        let mut b = GameRng::new(1234);
+
+        draw(&mut a, RngStream::Arena);
+
+        assert_eq!(
+            draw(&mut a, RngStream::EnemySpawn),
+            draw(&mut b, RngStream::EnemySpawn)
+        );
+        assert_ne!(
+            draw(&mut b, RngStream::Arena),
+            draw(&mut b, RngStream::EnemySpawn)
+        );
+    }
+
+    #[test]
+    fn test_reset_restarts_streams() {
+        let mut rng = GameRng::new(99);
+        let first = draw(&mut rng, RngStream::Scene);
+
+        rng.reset();
+
+        assert_eq!(draw(&mut rng, RngStream::Scene), first);
+    }
+}
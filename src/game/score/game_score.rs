@@ -46,6 +46,11 @@ impl GameScore {
         self.current_multiplier_score += score;
     }
 
+    /// Add score that is locked in right away, unaffected by the multiplier.
+    pub fn add_bonus(&mut self, bonus: u64) {
+        self.locked_in_score += bonus;
+    }
+
     pub fn tick(&mut self, time: &Time) {
         if let Some(timer) = &mut self.multiplier_timer {
             if timer.tick(time.delta()).just_finished() {
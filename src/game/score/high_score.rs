@@ -1,13 +1,16 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{cmp::Ordering, error::Error};
-use toml::from_str;
+use toml::Table;
 
-use crate::file_save::{self, FileSave};
+use crate::file_save::{self, FileSave, Versioned};
 
 const MAX_NUMBER_OF_HIGH_SCORES: usize = 10;
 
+/// The best scores, highest first.
 #[derive(Deserialize, Serialize, Resource, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
 pub struct HighScores {
     scores: Vec<HighScore>,
 }
@@ -17,63 +20,147 @@ impl HighScores {
         Self { scores: Vec::new() }
     }
 
+    /// Where a score would be placed on the board, or None if it is not good
+    /// enough. A score placed at the same score as an existing one goes below it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use space_game::game::score::HighScores;
+    ///
+    /// let mut high_scores = HighScores::new();
+    /// high_scores.add_name_score(String::from("A"), 100);
+    ///
+    /// assert_eq!(high_scores.placement(200), Some(0));
+    /// assert_eq!(high_scores.placement(100), Some(1));
+    /// ```
+    pub fn placement(&self, score: u64) -> Option<usize> {
+        match self.scores.iter().position(|s| s.score < score) {
+            Some(index) => Some(index),
+            None if self.len() < MAX_NUMBER_OF_HIGH_SCORES => Some(self.len()),
+            None => None,
+        }
+    }
+
     /// Returns the index of the score if it was added, or None if it was not added.
     pub fn add_score(&mut self, score: HighScore) -> Option<usize> {
-        let index = self.scores.iter().position(|s| s < &score);
-        if let Some(index) = index {
-            self.scores.insert(index, score);
-            self.scores.truncate(MAX_NUMBER_OF_HIGH_SCORES);
-            Some(index)
-        } else if self.len() < MAX_NUMBER_OF_HIGH_SCORES {
-            self.scores.push(score);
-            Some(self.len() - 1)
-        } else {
-            None
-        }
+        let index = self.placement(score.score)?;
+        self.scores.insert(index, score);
+        self.scores.truncate(MAX_NUMBER_OF_HIGH_SCORES);
+        Some(index)
     }
 
     pub fn add_name_score(&mut self, name: String, score: u64) -> Option<usize> {
         self.add_score(HighScore {
             player_name: name,
             score,
+            ..Default::default()
         })
     }
 
+    /// Sort the scores, highest first.
     pub fn sort(&mut self) {
-        self.scores.sort();
+        self.scores.sort_by(|a, b| b.cmp(a));
     }
 
     pub fn len(&self) -> usize {
         self.scores.len()
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HighScore> {
+        self.scores.iter()
+    }
 }
 
 impl FileSave for HighScores {
     type Item = HighScores;
     fn load_from_file(path: &str) -> Result<HighScores, Box<dyn Error>> {
-        let contents = file_save::load_from_file(path)?;
-        let mut config: HighScores = from_str(&contents)?;
+        let mut config: HighScores = file_save::load_versioned(path)?;
         config.sort();
         Ok(config)
     }
 
     fn save_to_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
-        let contents = toml::to_string(&self)?;
+        let contents = file_save::to_versioned_string(self)?;
         file_save::save_to_file(path, &contents)?;
         Ok(())
     }
 }
 
+impl Versioned for HighScores {
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, _document: &mut Table) -> Result<(), Box<dyn Error>> {
+        match version {
+            // Version 1 only added the `version` field. The date, level and
+            // seed of a score are optional, so older scores load as they are.
+            0 => Ok(()),
+            _ => Err(format!("no migration from version {}", version).into()),
+        }
+    }
+}
+
 impl Default for HighScores {
     fn default() -> Self {
         Self { scores: Vec::new() }
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// A score on the board. The date, level and seed are missing from scores
+/// saved by older versions of the game.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Default)]
 pub struct HighScore {
     pub player_name: String,
     pub score: u64,
+    /// When the score was set, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub date: u64,
+    /// The name of the level that was played.
+    #[serde(default)]
+    pub level: String,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl HighScore {
+    /// A score set right now.
+    pub fn now(player_name: String, score: u64, level: String, seed: u64) -> Self {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            player_name,
+            score,
+            date,
+            level,
+            seed: Some(seed),
+        }
+    }
+
+    /// The date the score was set as `YYYY-MM-DD`, in UTC. Empty if unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use space_game::game::score::HighScore;
+    ///
+    /// let score = HighScore {
+    ///     date: 1_700_000_000,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(score.date_string(), "2023-11-14");
+    /// ```
+    pub fn date_string(&self) -> String {
+        if self.date == 0 {
+            return String::new();
+        }
+
+        let (year, month, day) = civil_from_days((self.date / 86_400) as i64);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
 }
 
 impl Ord for HighScore {
@@ -87,3 +174,70 @@ impl PartialOrd for HighScore {
         Some(self.cmp(other))
     }
 }
+
+/// The year, month and day of a number of days since 1970-01-01.
+///
+/// From Howard Hinnant's `civil_from_days`:
+/// https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board_keeps_the_best_scores_highest_first() {
+        let mut high_scores = HighScores::new();
+        for score in 0..15 {
+            high_scores.add_name_score(format!("{}", score), score * 10);
+        }
+
+        let scores: Vec<u64> = high_scores.iter().map(|s| s.score).collect();
+        assert_eq!(scores, vec![140, 130, 120, 110, 100, 90, 80, 70, 60, 50]);
+        assert_eq!(high_scores.placement(10), None);
+        assert_eq!(high_scores.placement(55), Some(9));
+    }
+
+    #[test]
+    fn test_old_scores_load_without_date_level_and_seed() {
+        let high_scores: HighScores = toml::from_str(
+            r#"
+            [[scores]]
+            player_name = "Old"
+            score = 5
+
+            [[scores]]
+            player_name = "Older"
+            score = 50
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(high_scores.len(), 2);
+        assert_eq!(high_scores.iter().next().unwrap().date_string(), "");
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    }
+}
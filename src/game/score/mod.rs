@@ -4,6 +4,7 @@ pub mod high_score;
 use bevy::prelude::*;
 
 use crate::file_save::FileSave;
+use crate::paths::Paths;
 
 // re-export
 pub use self::game_score::*;
@@ -11,6 +12,10 @@ pub use self::high_score::*;
 
 use super::events::GameOverEvent;
 use super::events::HighScoreEvent;
+use super::events::HighScoreSubmitted;
+use super::events::WaveCleared;
+use super::level::Level;
+use super::rng::GameRng;
 use super::{game_entity::GameEntityType, vitality::DeathEvent};
 
 pub struct ScorePlugin;
@@ -18,19 +23,27 @@ pub struct ScorePlugin;
 impl Plugin for ScorePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(GameScore::default())
-            .insert_resource(HighScores::default())
+            .init_resource::<HighScores>()
             .add_event::<HighScoreEvent>()
+            .add_event::<HighScoreSubmitted>()
             .add_systems(
                 Update,
                 (
                     update_score_on_deaths,
+                    update_score_on_wave_cleared,
                     update_score_timer,
-                    update_high_scores_on_game_over,
+                    check_high_score_on_game_over,
+                    save_submitted_high_scores,
                 ),
             );
     }
 }
 
+/// Start a new game from zero.
+pub fn reset(mut game_score: ResMut<GameScore>) {
+    game_score.reset();
+}
+
 fn update_score_timer(time: Res<Time>, mut game_score: ResMut<GameScore>) {
     game_score.tick(&time);
 }
@@ -40,34 +53,63 @@ fn update_score_on_deaths(
     mut death_events: EventReader<DeathEvent>,
 ) {
     for ev in death_events.read() {
-        match ev._type() {
-            GameEntityType::Enemy => {
-                game_score.add_score(10);
-                game_score.increment_multiplier();
-            }
-            GameEntityType::Player => game_score.reset(),
-            _ => (),
+        if ev._type() == GameEntityType::Enemy {
+            game_score.add_score(10);
+            game_score.increment_multiplier();
         }
     }
 }
 
-fn update_high_scores_on_game_over(
-    mut high_scores: ResMut<HighScores>,
+/// Bonus for clearing a wave, multiplied by the wave number.
+const WAVE_CLEARED_BONUS: u64 = 50;
+
+fn update_score_on_wave_cleared(
+    mut game_score: ResMut<GameScore>,
+    mut wave_cleared_events: EventReader<WaveCleared>,
+) {
+    for WaveCleared { wave } in wave_cleared_events.read() {
+        game_score.add_bonus(WAVE_CLEARED_BONUS * *wave as u64);
+    }
+}
+
+/// The name suggested when entering a new high score.
+const DEFAULT_PLAYER_NAME: &str = "Player";
+
+fn check_high_score_on_game_over(
+    high_scores: Res<HighScores>,
     mut game_over_events: EventReader<GameOverEvent>,
     mut high_score_events: EventWriter<HighScoreEvent>,
 ) {
     for GameOverEvent { score } in game_over_events.read() {
-        let player_name = String::from("Player");
-        if let Some(placement) = high_scores.add_name_score(player_name.clone(), *score) {
+        if let Some(placement) = high_scores.placement(*score) {
             high_score_events.send(HighScoreEvent {
-                player_name,
+                player_name: String::from(DEFAULT_PLAYER_NAME),
                 placement,
                 score: *score,
             });
+        }
+    }
+}
 
-            // Need some way of telling the user that the game is saving...
-            // How to prevent corrupted data?
-            let _ = high_scores.save_to_file("high_scores.toml");
+fn save_submitted_high_scores(
+    mut high_scores: ResMut<HighScores>,
+    mut submitted_events: EventReader<HighScoreSubmitted>,
+    level: Option<Res<Level>>,
+    game_rng: Res<GameRng>,
+    paths: Res<Paths>,
+) {
+    for HighScoreSubmitted { player_name, score } in submitted_events.read() {
+        let level = level
+            .as_ref()
+            .map(|level| level.name.clone())
+            .unwrap_or_default();
+        let high_score = HighScore::now(player_name.clone(), *score, level, game_rng.seed());
+
+        if high_scores.add_score(high_score).is_some() {
+            let path = paths.high_scores_file();
+            if let Err(err) = high_scores.save_to_file(&path) {
+                eprintln!("Error saving {}: {}", path, err);
+            }
         }
     }
 }
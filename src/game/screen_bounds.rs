@@ -1,4 +1,6 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{ecs::system::SystemParam, prelude::*, window::PrimaryWindow};
+
+use crate::settings::ResolutionSetting;
 
 ////////////////////////////////////////////////////////////////////////////////
 // Plugin
@@ -72,22 +74,54 @@ impl ScreenBounds {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Viewport
+////////////////////////////////////////////////////////////////////////////////
+
+/// The size of the area the player is looking at.
+///
+/// Uses the primary window if there is one, otherwise it falls back to the
+/// resolution in the settings. This way systems that care about what is on
+/// screen keep working when the game runs headless.
+#[derive(SystemParam)]
+pub struct Viewport<'w, 's> {
+    window_query: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    resolution: Option<Res<'w, ResolutionSetting>>,
+}
+
+impl<'w, 's> Viewport<'w, 's> {
+    /// The width and height of the viewport in pixels.
+    pub fn size(&self) -> Vec2 {
+        if let Ok(window) = self.window_query.get_single() {
+            Vec2::new(window.width(), window.height())
+        } else {
+            let resolution = self.resolution.as_deref().cloned().unwrap_or_default();
+            Vec2::new(resolution.x as f32, resolution.y as f32)
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.size().x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.size().y
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Systems
 ////////////////////////////////////////////////////////////////////////////////
 
 fn update_screen_bounds(
     mut screen_bounds: ResMut<ScreenBounds>,
-    window_query: Query<&Window, (With<PrimaryWindow>, Without<Camera>)>,
+    viewport: Viewport,
     camera_query: Query<&Transform, (Without<PrimaryWindow>, With<Camera>)>,
 ) {
-    if let Ok(window) = window_query.get_single() {
-        if let Ok(transform) = camera_query.get_single() {
-            let width = window.width();
-            let height = window.height();
-            let position = transform.translation.xy();
+    if let Ok(transform) = camera_query.get_single() {
+        let size = viewport.size();
+        let position = transform.translation.xy();
 
-            screen_bounds.update(width, height, position);
-        }
+        screen_bounds.update(size.x, size.y, position);
     }
 }
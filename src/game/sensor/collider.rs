@@ -2,7 +2,7 @@ use bevy::prelude::*;
 
 use super::SensorTarget;
 use super::SensorTargets;
-use crate::game::assets::groups;
+use crate::game::faction::{Faction, FactionBody};
 use bevy_rapier2d::{geometry::*, prelude::CollisionEvent};
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -25,7 +25,9 @@ where
     pub active_events: ActiveEvents,
     pub mass: ColliderMassProperties,
     pub sensor: Sensor,
-    pub collision_groups: CollisionGroups,
+    /// Sees the ships of the factions this one is hostile to.
+    pub faction: Faction,
+    pub body: FactionBody,
     pub label: ColliderSensorLabel,
     pub targets: SensorTargets,
     pub target: SensorTarget<D>,
@@ -40,7 +42,8 @@ where
             circle: Collider::ball(500.0),
             active_events: ActiveEvents::COLLISION_EVENTS,
             mass: ColliderMassProperties::Density(0.0),
-            collision_groups: CollisionGroups::default(),
+            faction: Faction::Neutral,
+            body: FactionBody::Sensor,
             sensor: Sensor,
             label: ColliderSensorLabel,
             targets: SensorTargets::default(),
@@ -53,10 +56,10 @@ impl<D> ColliderSensorBundle<D>
 where
     D: Send + Sync + 'static,
 {
-    pub fn ball(radius: f32, filters: Group) -> Self {
+    pub fn ball(radius: f32, faction: Faction) -> Self {
         Self {
             circle: Collider::ball(radius),
-            collision_groups: CollisionGroups::new(groups::SENSOR_GROUP.into(), filters.into()),
+            faction,
             ..Default::default()
         }
     }
@@ -3,7 +3,18 @@ pub struct TraumaPlugin;
 
 impl Plugin for TraumaPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_trauma);
+        app.init_resource::<ShakeIntensity>()
+            .add_systems(Update, update_trauma);
+    }
+}
+
+/// Scales how much the camera shakes from trauma, from 0 (not at all) to 1.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct ShakeIntensity(pub f32);
+
+impl Default for ShakeIntensity {
+    fn default() -> Self {
+        Self(1.0)
     }
 }
 
@@ -1,21 +1,171 @@
-// use bevy_rapier2d::geometry::Group;
-
+use crate::game::faction::FactionBody;
 use bevy::prelude::*;
-#[derive(Component, Debug, Clone, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Debug, Clone, PartialEq, Default)]
 pub struct TurretAI {
     pub state: TurretState,
-    // target_group: Group, //Used to allow turrets to target different groups, player, enemies, etc
+    pub targeting: Targeting,
+    /// The target picked last frame, if any.
+    pub target: Option<Aim>,
 }
 
-impl Default for TurretAI {
-    fn default() -> Self {
+impl TurretAI {
+    pub fn new(targeting: Targeting) -> Self {
         Self {
             state: TurretState::default(),
-            // target_group: Group::default(),
+            targeting,
+            target: None,
         }
     }
+
+    pub fn has_target(&self) -> bool {
+        self.target.is_some()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Targeting
+////////////////////////////////////////////////////////////////////////////////
+
+/// Which of the ships a turret sees it goes after.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Targeting {
+    #[serde(default)]
+    pub group: TargetGroup,
+    #[serde(default)]
+    pub priority: TargetPriority,
+}
+
+/// The kinds of hostile entities a turret shoots at.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TargetGroup {
+    #[default]
+    Any,
+    Player,
+    /// Ships and turrets, but not drones.
+    Ships,
+    Drones,
+}
+
+impl TargetGroup {
+    pub fn contains(&self, body: FactionBody, is_player: bool) -> bool {
+        match self {
+            TargetGroup::Any => true,
+            TargetGroup::Player => is_player,
+            TargetGroup::Ships => body == FactionBody::Ship,
+            TargetGroup::Drones => body == FactionBody::Drone,
+        }
+    }
+}
+
+/// How a turret picks between the targets in its group.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TargetPriority {
+    #[default]
+    Closest,
+    /// The one with the least health left.
+    Weakest,
+    /// The one closing in the fastest for its distance.
+    MostThreatening,
+}
+
+/// What the turret knows about a target when picking one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub health: Option<u32>,
+}
+
+/// A closing speed of this many pixels per second makes a target twice as
+/// threatening as one that stands still.
+const THREAT_REFERENCE_SPEED: f32 = 100.0;
+
+impl TargetPriority {
+    /// The best candidate for a turret at `position`.
+    pub fn pick(
+        &self,
+        position: Vec2,
+        candidates: impl Iterator<Item = Candidate>,
+    ) -> Option<Candidate> {
+        // Lower scores are better, ties go to the closest
+        let score = |candidate: &Candidate| {
+            let distance = candidate.position.distance(position);
+            let score = match self {
+                TargetPriority::Closest => 0.0,
+                TargetPriority::Weakest => candidate.health.unwrap_or(u32::MAX) as f32,
+                TargetPriority::MostThreatening => {
+                    let towards = (position - candidate.position).normalize_or_zero();
+                    let closing_speed = candidate.velocity.dot(towards);
+                    -(THREAT_REFERENCE_SPEED + closing_speed) / distance.max(1.0)
+                }
+            };
+            (score, distance)
+        };
+
+        candidates.min_by(|a, b| {
+            let (a, b) = (score(a), score(b));
+            a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+        })
+    }
 }
 
+/// Where a turret aims at its target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aim {
+    pub entity: Entity,
+    /// Where the target will be when the projectile gets there.
+    pub point: Vec2,
+    /// False when a meteor is in the way.
+    pub line_of_fire: bool,
+}
+
+/// Where to shoot a projectile of `speed` from `origin` to hit a target moving
+/// at a constant `velocity`. `None` if the projectile can not catch it.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use space_game::game::turret::intercept_point;
+///
+/// // A target crossing in front, at the same speed as the projectile
+/// let point = intercept_point(Vec2::ZERO, Vec2::new(-100.0, 100.0), Vec2::new(100.0, 0.0), 100.0);
+///
+/// assert!(point.unwrap().abs_diff_eq(Vec2::new(0.0, 100.0), 1e-3));
+/// ```
+pub fn intercept_point(origin: Vec2, target: Vec2, velocity: Vec2, speed: f32) -> Option<Vec2> {
+    // Solve |offset + velocity * t| = speed * t for the earliest t > 0
+    let offset = target - origin;
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2.0 * offset.dot(velocity);
+    let c = offset.length_squared();
+
+    let time = if a.abs() < f32::EPSILON {
+        // As fast as the projectile, only one solution
+        -c / b
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+            .into_iter()
+            .filter(|time| *time > 0.0)
+            .reduce(f32::min)?
+    };
+
+    (time.is_finite() && time > 0.0).then(|| target + velocity * time)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// State
+////////////////////////////////////////////////////////////////////////////////
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TurretState {
     Idle,
@@ -80,3 +230,69 @@ impl TurretState {
         }
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// Stats
+////////////////////////////////////////////////////////////////////////////////
+
+/// How well a turret shoots, for tuning.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TurretStats {
+    pub shots_fired: u32,
+    /// Shots that hit a ship, turret or drone.
+    pub hits: u32,
+    /// Shots held back because a meteor was in the way.
+    pub shots_blocked: u32,
+}
+
+impl TurretStats {
+    /// The fraction of shots fired that hit, `None` before the first shot.
+    pub fn accuracy(&self) -> Option<f32> {
+        (self.shots_fired > 0).then(|| self.hits as f32 / self.shots_fired as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(index: u32, position: Vec2, velocity: Vec2, health: u32) -> Candidate {
+        Candidate {
+            entity: Entity::from_raw(index),
+            position,
+            velocity,
+            health: Some(health),
+        }
+    }
+
+    #[test]
+    fn test_priorities_pick_different_targets() {
+        let candidates = [
+            // Close, but flying away
+            candidate(0, Vec2::new(100.0, 0.0), Vec2::new(300.0, 0.0), 50),
+            // Far, almost dead
+            candidate(1, Vec2::new(0.0, 400.0), Vec2::ZERO, 5),
+            // Far, coming in fast
+            candidate(2, Vec2::new(-300.0, 0.0), Vec2::new(400.0, 0.0), 50),
+        ];
+        let pick = |priority: TargetPriority| {
+            priority
+                .pick(Vec2::ZERO, candidates.into_iter())
+                .map(|candidate| candidate.entity.index())
+        };
+
+        assert_eq!(pick(TargetPriority::Closest), Some(0));
+        assert_eq!(pick(TargetPriority::Weakest), Some(1));
+        assert_eq!(pick(TargetPriority::MostThreatening), Some(2));
+    }
+
+    #[test]
+    fn test_slow_projectile_can_not_catch_target() {
+        let fleeing = intercept_point(Vec2::ZERO, Vec2::Y * 100.0, Vec2::Y * 200.0, 100.0);
+        assert_eq!(fleeing, None);
+
+        // A target standing still is hit where it is
+        let standing = intercept_point(Vec2::ZERO, Vec2::Y * 100.0, Vec2::ZERO, 100.0);
+        assert!(standing.unwrap().abs_diff_eq(Vec2::Y * 100.0, 1e-3));
+    }
+}
@@ -1,22 +1,39 @@
 use crate::misc::control::{PID, PID2D};
 use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
 
 /// Label to identify a turret.
 #[derive(Component)]
 pub struct TurretLabel;
 
-// Controlls the turret's rotation.
+/// The part of a turret that turns towards the target, carrying the weapon.
+/// A child of the turret.
 #[derive(Component)]
-pub struct RotationControl {
-    pub control: PID,
+pub struct TurretGun {
+    /// Radians per second.
+    pub turn_rate: f32,
 }
 
-impl Default for RotationControl {
-    fn default() -> Self {
-        Self {
-            control: PID::rotation(0.05, 0.0, 0.05, 0.0),
-        }
+/// Close enough to the aim to fire, in radians.
+const AIM_TOLERANCE: f32 = 0.1;
+
+impl TurretGun {
+    /// Turn from `angle` towards `target`, no faster than the turn rate.
+    /// Angles are in radians, the result is between -PI and PI.
+    pub fn turn(&self, angle: f32, target: f32, dt: f32) -> f32 {
+        let difference = angle_difference(angle, target);
+        let max_turn = self.turn_rate * dt;
+        angle_difference(0.0, angle + difference.clamp(-max_turn, max_turn))
     }
+
+    pub fn is_aimed(angle: f32, target: f32) -> bool {
+        angle_difference(angle, target).abs() <= AIM_TOLERANCE
+    }
+}
+
+/// The shortest turn from `from` to `to`, between -PI and PI.
+fn angle_difference(from: f32, to: f32) -> f32 {
+    (to - from + PI).rem_euclid(TAU) - PI
 }
 
 /// Tries to keep the turret stationary.
@@ -39,3 +56,21 @@ impl Default for StationaryControl {
 /// Label to identify a turret's sensor.
 #[derive(Component)]
 pub struct TurretSensorLabel;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gun_turns_the_short_way_round() {
+        let gun = TurretGun { turn_rate: 1.0 };
+
+        // From just left of straight down to just right of it
+        let angle = gun.turn(PI - 0.1, -PI + 0.1, 0.1);
+        assert!((angle - PI).abs() < 1e-5 || (angle + PI).abs() < 1e-5);
+
+        // Limited by the turn rate
+        assert!((gun.turn(0.0, 2.0, 0.5) - 0.5).abs() < 1e-5);
+        assert!(TurretGun::is_aimed(gun.turn(0.0, 0.05, 0.1), 0.05));
+    }
+}
@@ -3,14 +3,17 @@ mod components;
 mod draw;
 mod systems;
 
-use self::ai::TurretAI;
+pub use self::ai::{
+    intercept_point, TargetGroup, TargetPriority, Targeting, TurretAI, TurretStats,
+};
 use super::game_entity::Enemy;
 use super::{
-    assets::{self, groups},
+    assets::{self, Asset},
+    faction::{Faction, FactionBody},
     game_entity::GameEntityType,
     sensor::ColliderSensorBundle,
-    vitality::Health,
-    weapon::Weapon,
+    vitality::{self, Armor, Health, Shield, ShieldConfig},
+    weapon::{Weapon, WeaponArchetype},
 };
 use crate::{
     parent_child_no_rotation::{NoRotationChild, NoRotationParent},
@@ -20,26 +23,43 @@ use bevy::prelude::*;
 use bevy_prototype_lyon::prelude::*;
 use bevy_rapier2d::{
     geometry::*,
-    prelude::{ExternalForce, ExternalImpulse, RigidBody, Velocity},
+    prelude::{ExternalForce, ExternalImpulse, LockedAxes, RigidBody, Velocity},
 };
 use components::*;
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
+use std::fmt;
 
 ////////////////////////////////////////////////////////////////////////////////
 /// Config
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Everything that makes one turret different from another. Fields that are
+/// not set in a level are those of the `GunMount` preset.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
 pub struct TurretConfig {
+    pub base: TurretBase,
+    pub gun: TurretGunSprite,
     pub max_health: u32,
+    /// Taken off the damage of every hit, see `Armor`.
+    pub armor: u32,
+    /// No shield if not set.
+    pub shield: Option<ShieldConfig>,
+    pub weapon: WeaponArchetype,
     pub weapon_damage: u32,
+    /// Seconds, scaled by the archetype like the player's weapon.
+    pub weapon_cooldown: f32,
+    /// Radians per second the gun turns at, the base does not turn.
+    pub gun_turn_rate: f32,
+    pub sensor_range: f32,
+    /// Shoots at any hostile, closest first, if not set.
+    pub targeting: Targeting,
 }
 
 impl Default for TurretConfig {
     fn default() -> Self {
-        Self {
-            max_health: 30,
-            weapon_damage: 10,
-        }
+        TurretPreset::GunMount.config()
     }
 }
 
@@ -48,6 +68,106 @@ impl TurretConfig {
         Self {
             max_health,
             weapon_damage,
+            ..Default::default()
+        }
+    }
+}
+
+/// Turrets that can be picked by name, in levels and the turret scene.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurretPreset {
+    /// A laser on a quick rotating mount.
+    GunMount,
+    /// Slow to turn, but its missiles find their own way from far away.
+    MissileBattery,
+    /// Armored and shielded, with a short range spread gun.
+    ShieldedBunker,
+}
+
+impl TurretPreset {
+    pub const ALL: [TurretPreset; 3] = [
+        TurretPreset::GunMount,
+        TurretPreset::MissileBattery,
+        TurretPreset::ShieldedBunker,
+    ];
+
+    pub fn config(&self) -> TurretConfig {
+        match self {
+            TurretPreset::GunMount => TurretConfig {
+                base: TurretBase::Big,
+                gun: TurretGunSprite::Gun8,
+                max_health: 30,
+                armor: 0,
+                shield: None,
+                weapon: WeaponArchetype::Laser,
+                weapon_damage: 10,
+                weapon_cooldown: 1.0,
+                gun_turn_rate: 3.0,
+                sensor_range: 500.0,
+                targeting: Targeting::default(),
+            },
+            TurretPreset::MissileBattery => TurretConfig {
+                base: TurretBase::Small,
+                max_health: 40,
+                weapon: WeaponArchetype::HomingMissile,
+                weapon_cooldown: 0.5,
+                gun_turn_rate: 1.0,
+                sensor_range: 750.0,
+                ..TurretPreset::GunMount.config()
+            },
+            TurretPreset::ShieldedBunker => TurretConfig {
+                max_health: 80,
+                armor: 4,
+                shield: Some(ShieldConfig {
+                    capacity: 40,
+                    recharge_delay: 4.0,
+                    recharge_rate: 8.0,
+                }),
+                weapon: WeaponArchetype::Spread,
+                weapon_damage: 6,
+                weapon_cooldown: 0.4,
+                gun_turn_rate: 1.5,
+                sensor_range: 400.0,
+                ..TurretPreset::GunMount.config()
+            },
+        }
+    }
+}
+
+impl fmt::Display for TurretPreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TurretPreset::GunMount => write!(f, "Gun Mount"),
+            TurretPreset::MissileBattery => write!(f, "Missile Battery"),
+            TurretPreset::ShieldedBunker => write!(f, "Shielded Bunker"),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurretBase {
+    Big,
+    Small,
+}
+
+impl TurretBase {
+    pub fn asset(&self) -> &'static Asset {
+        match self {
+            TurretBase::Big => &assets::TURRET_BASE_BIG,
+            TurretBase::Small => &assets::TURRET_BASE_SMALL,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurretGunSprite {
+    Gun8,
+}
+
+impl TurretGunSprite {
+    pub fn asset(&self) -> &'static Asset {
+        match self {
+            TurretGunSprite::Gun8 => &assets::GUN_8,
         }
     }
 }
@@ -62,12 +182,16 @@ impl Plugin for TurretPlugin {
         app.add_systems(
             Update,
             (
-                systems::update_turret_rotation.before(systems::update_stationary_control),
+                systems::select_targets
+                    .before(systems::update_gun_rotation)
+                    .before(systems::fire_weapon),
+                systems::update_gun_rotation.before(systems::fire_weapon),
                 systems::update_turret_radius_outline,
                 systems::fire_weapon,
                 systems::update_ai
                     .after(systems::fire_weapon)
-                    .after(systems::update_turret_rotation),
+                    .after(systems::update_gun_rotation),
+                systems::count_hits,
             ),
         );
     }
@@ -86,55 +210,60 @@ pub fn spawn(
     asset_server: &Res<AssetServer>,
     turret_config: &TurretConfig,
     spawn_transform: Transform,
+    faction: Faction,
 ) {
-    let turret_base = assets::TURRET_BASE_BIG;
-    let gun = assets::GUN_8;
+    let turret_base = turret_config.base.asset();
+    let gun = turret_config.gun.asset();
 
-    commands
+    let turret = commands
         .spawn(TurretLabel)
         .insert(Enemy)
-        .insert(TurretAI::default())
+        .insert(TurretAI::new(turret_config.targeting))
+        .insert(TurretStats::default())
         .insert(GameEntityType::Enemy)
         // Properties
         .insert(Health::at_max(turret_config.max_health))
+        .insert(Armor(turret_config.armor))
         // Physics
         .insert(SpatialBundle::from_transform(spawn_transform))
         .insert(NoRotationParent)
         .insert(RigidBody::Dynamic)
-        .insert(CollisionGroups::new(
-            groups::ENEMY_GROUP.into(),
-            groups::ENEMY_FILTER_MASK.into(),
-        ))
+        // Only the gun turns
+        .insert(LockedAxes::ROTATION_LOCKED)
+        .insert((faction, FactionBody::Ship))
         .insert(turret_base.collider())
-        .insert(SolverGroups::new(
-            groups::ENEMY_GROUP.into(),
-            groups::ENEMY_FILTER_MASK.into(),
-        ))
         .insert(Velocity::default())
         .insert(ExternalForce::default())
         .insert(ExternalImpulse::default())
-        .insert(RotationControl::default())
         .insert(StationaryControl::default())
-        .insert(Weapon::laser(
-            turret_config.weapon_damage,
-            1000.0,
-            Timer::from_seconds(1.0, TimerMode::Once),
-            None,
-            groups::ENEMY_PROJECTILE_GROUP,
-            groups::ENEMY_PROJECTILE_FILTER_MASK,
-        ))
         .with_children(|parent| {
-            let mut gun_transform = Transform::from_translation(Vec3::new(0.0, 20.0, 0.0));
-
-            gun_transform.rotate(Quat::from_rotation_z(PI));
-
+            // Above the base
             parent
-                .spawn(SpriteBundle {
-                    texture: asset_server.load(gun.sprite_path),
-                    transform: gun_transform,
-                    ..Default::default()
+                .spawn(SpatialBundle::from_transform(Transform::from_xyz(
+                    0.0, 0.0, 0.1,
+                )))
+                .insert(TurretGun {
+                    turn_rate: turret_config.gun_turn_rate,
                 })
-                .insert(CollisionGroups::new(Group::NONE, Group::NONE));
+                .insert(Weapon::from_archetype(
+                    turret_config.weapon,
+                    turret_config.weapon_damage,
+                    turret_config.weapon_cooldown,
+                    faction,
+                ))
+                .with_children(|parent| {
+                    let mut gun_transform = Transform::from_translation(Vec3::new(0.0, 20.0, 0.0));
+
+                    gun_transform.rotate(Quat::from_rotation_z(PI));
+
+                    parent
+                        .spawn(SpriteBundle {
+                            texture: asset_server.load(gun.sprite_path),
+                            transform: gun_transform,
+                            ..Default::default()
+                        })
+                        .insert(CollisionGroups::new(Group::NONE, Group::NONE));
+                });
 
             parent
                 .spawn(SpriteBundle {
@@ -150,18 +279,30 @@ pub fn spawn(
             stroke.options.start_cap = LineCap::Round;
             stroke.options.end_cap = LineCap::Round;
 
-            let sensor_range = 500.0;
+            let sensor_range = turret_config.sensor_range;
 
             parent
-                .spawn(ColliderSensorBundle::<Vec2>::ball(
-                    sensor_range,
-                    groups::PLAYER_GROUP,
-                ))
+                .spawn(ColliderSensorBundle::<Vec2>::ball(sensor_range, faction))
                 .insert((
                     draw::dashed_circle(sensor_range, 10.0, 10.0),
                     stroke,
                     NoRotationChild,
                 ))
                 .insert(TurretSensorLabel);
-        });
+        })
+        .id();
+
+    if let Some(shield) = turret_config.shield {
+        commands.entity(turret).insert(Shield::new(shield));
+        vitality::spawn_bubble(
+            commands,
+            turret,
+            turret_base
+                .collider()
+                .raw
+                .compute_local_bounding_sphere()
+                .radius()
+                + 10.0,
+        );
+    }
 }
@@ -1,77 +1,239 @@
-use super::ai::TurretAI;
-use super::{ai, components::*};
-use crate::game::sensor::SensorTargetVec2;
+use super::ai::{self, Aim, Candidate, TurretAI, TurretStats};
+use super::components::*;
+use crate::game::assets::groups;
+use crate::game::faction::{Faction, FactionBody};
+use crate::game::player::Player;
+use crate::game::projectile::ProjectileImpact;
+use crate::game::sensor::{SensorTargetVec2, SensorTargets};
+use crate::game::vitality::Health;
 use crate::game::weapon::Weapon;
 use bevy::{math::Vec3Swizzles, prelude::*};
 use bevy_prototype_lyon::prelude::*;
-use bevy_rapier2d::prelude::{ExternalImpulse, Velocity};
+use bevy_rapier2d::prelude::*;
 
-pub fn update_ai(
-    mut turret_query: Query<&mut ai::TurretAI>,
-    sensor_query: Query<(&Parent, &SensorTargetVec2)>,
-    time: Res<Time>,
+type TurretTargetingQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut TurretAI,
+        &'static Transform,
+        &'static Faction,
+        &'static Children,
+    ),
+>;
+
+type CandidateQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static FactionBody,
+        Option<&'static Velocity>,
+        Option<&'static Health>,
+        Has<Player>,
+    ),
+>;
+
+/// Pick a target among what the sensor sees, lead it and check that no
+/// meteor is in the way.
+pub fn select_targets(
+    mut turret_query: TurretTargetingQuery,
+    sensor_query: Query<&SensorTargets, With<TurretSensorLabel>>,
+    gun_query: Query<&Weapon, With<TurretGun>>,
+    candidate_query: CandidateQuery,
+    rapier_context: Res<RapierContext>,
 ) {
-    for (parent, target) in sensor_query.iter() {
-        if let Ok(mut turret_ai) = turret_query.get_mut(parent.get()) {
-            turret_ai.state.update(&time, target.has_target());
-        }
+    for (mut turret_ai, transform, faction, children) in turret_query.iter_mut() {
+        let position = transform.translation.xy();
+        let group = turret_ai.targeting.group;
+
+        let Some(sensor) = children
+            .iter()
+            .find_map(|child| sensor_query.get(*child).ok())
+        else {
+            continue;
+        };
+        let Some(weapon) = children.iter().find_map(|child| gun_query.get(*child).ok()) else {
+            continue;
+        };
+
+        // Targets that died are still in the sensor until it notices
+        let candidates = sensor.iter().filter_map(|entity| {
+            let (target_transform, body, velocity, health, is_player) =
+                candidate_query.get(*entity).ok()?;
+            group.contains(*body, is_player).then(|| Candidate {
+                entity: *entity,
+                position: target_transform.translation.xy(),
+                velocity: velocity.map_or(Vec2::ZERO, |velocity| velocity.linvel),
+                health: health.map(Health::current),
+            })
+        });
+
+        turret_ai.target = turret_ai
+            .targeting
+            .priority
+            .pick(position, candidates)
+            .map(|target| {
+                let point = ai::intercept_point(
+                    position,
+                    target.position,
+                    target.velocity,
+                    weapon.projectile().speed,
+                )
+                .unwrap_or(target.position);
+
+                Aim {
+                    entity: target.entity,
+                    point,
+                    line_of_fire: !meteor_in_the_way(&rapier_context, *faction, position, point),
+                }
+            });
     }
 }
 
+/// Cast a ray along the line of fire, only meteors block it.
+fn meteor_in_the_way(
+    rapier_context: &RapierContext,
+    faction: Faction,
+    from: Vec2,
+    to: Vec2,
+) -> bool {
+    let Some(direction) = (to - from).try_normalize() else {
+        return false;
+    };
+    // Seen as a projectile, so that it can hit meteors
+    let filter = QueryFilter::new()
+        .exclude_sensors()
+        .groups(CollisionGroups::new(
+            faction.projectile_group(),
+            groups::METEOR_GROUP,
+        ));
+
+    rapier_context
+        .cast_ray(from, direction, from.distance(to), true, filter)
+        .is_some()
+}
+
+pub fn update_ai(mut turret_query: Query<&mut TurretAI>, time: Res<Time>) {
+    for mut turret_ai in turret_query.iter_mut() {
+        let has_target = turret_ai.has_target();
+        turret_ai.state.update(&time, has_target);
+    }
+}
+
+type GunQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Parent,
+        &'static TurretGun,
+        &'static mut Weapon,
+        &'static mut Transform,
+    ),
+>;
+
+type TurretQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static TurretAI,
+        &'static Transform,
+        &'static mut TurretStats,
+    ),
+    Without<TurretGun>,
+>;
+
+/// Fire when the gun points at the target and nothing is in the way.
 pub fn fire_weapon(
-    mut query: Query<(&ai::TurretAI, &mut Weapon, &Transform)>,
+    mut gun_query: GunQuery,
+    mut turret_query: TurretQuery,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
 ) {
-    for (turret_ai, mut weapon, transform) in query.iter_mut() {
-        if turret_ai.state.is_firing() {
-            weapon.fire(&mut commands, &asset_server, *transform)
+    for (parent, _, mut weapon, gun_transform) in gun_query.iter_mut() {
+        let Ok((turret_ai, turret_transform, mut stats)) = turret_query.get_mut(parent.get())
+        else {
+            continue;
+        };
+        if !turret_ai.state.is_firing() || !weapon.can_fire() {
+            continue;
+        }
+        let Some(aim) = &turret_ai.target else {
+            continue;
+        };
+
+        let rotation = turret_transform.rotation * gun_transform.rotation;
+        let spawn_transform =
+            Transform::from_translation(turret_transform.translation).with_rotation(rotation);
+        if !TurretGun::is_aimed(angle_of(rotation), aim_angle(turret_transform, aim)) {
+            continue;
+        }
+
+        if aim.line_of_fire {
+            weapon.fire(&mut commands, &asset_server, spawn_transform);
+            stats.shots_fired += 1;
+        } else {
+            stats.shots_blocked += 1;
         }
     }
 }
 
-pub fn update_turret_rotation(
-    mut query: Query<(
-        &TurretAI,
-        &mut RotationControl,
-        &Transform,
-        &Children,
-        &mut ExternalImpulse,
-    )>,
-    target_query: Query<&SensorTargetVec2>,
-    time: Res<Time>,
+/// Credit turrets for the shots that hit a ship, turret or drone.
+pub fn count_hits(
+    mut impact_events: EventReader<ProjectileImpact>,
+    mut stats_query: Query<&mut TurretStats>,
+    gun_query: Query<&Parent, With<TurretGun>>,
+    target_query: Query<(), (With<Faction>, With<Health>)>,
 ) {
-    let dt = time.delta_seconds();
-    if dt == 0.0 {
-        return;
-    }
-    for (turret_ai, mut rotation_control, turret_global_transform, children, mut turret_impulse) in
-        query.iter_mut()
-    {
-        if !turret_ai.state.is_targeting() {
+    for impact in impact_events.read() {
+        let (Some(shooter), Some(target)) = (impact.shooter, impact.target) else {
             continue;
+        };
+
+        // The weapon is on the gun, the stats on the turret
+        let Ok(turret) = gun_query.get(shooter) else {
+            continue;
+        };
+        if target_query.contains(target) {
+            if let Ok(mut stats) = stats_query.get_mut(turret.get()) {
+                stats.hits += 1;
+            }
         }
+    }
+}
 
-        // If we have a target, set the desired angle to the angle between the turret and the target
-        for child in children.iter() {
-            if let Ok(Some((_, location))) = target_query.get(*child).map(|t| t.get()) {
-                let desired_angel =
-                    Vec2::Y.angle_between(*location - turret_global_transform.translation.xy());
+/// Turn the guns towards where their target will be.
+pub fn update_gun_rotation(mut gun_query: GunQuery, turret_query: TurretQuery, time: Res<Time>) {
+    let dt = time.delta_seconds();
 
-                if desired_angel.is_nan() {
-                    continue;
-                }
+    for (parent, gun, _, mut gun_transform) in gun_query.iter_mut() {
+        let Ok((turret_ai, turret_transform, _)) = turret_query.get(parent.get()) else {
+            continue;
+        };
+        let Some(aim) = &turret_ai.target else {
+            continue;
+        };
 
-                rotation_control.control.set_setpoint(desired_angel);
-            }
-        }
+        // The turret does not turn, but may have been spawned turned
+        let turret_angle = angle_of(turret_transform.rotation);
+        let target = aim_angle(turret_transform, aim) - turret_angle;
+        let angle = gun.turn(angle_of(gun_transform.rotation), target, dt);
 
-        // Rotate the turret towards the desired angle
-        let (_, _, current_angle) = turret_global_transform.rotation.to_euler(EulerRot::XYZ);
+        gun_transform.rotation = Quat::from_rotation_z(angle);
+    }
+}
 
-        let control_signal = rotation_control.control.update(current_angle, dt);
+fn angle_of(rotation: Quat) -> f32 {
+    rotation.to_euler(EulerRot::XYZ).2
+}
 
-        turret_impulse.torque_impulse = control_signal * 0.001;
+/// The angle from the turret to where it aims, zero is up.
+fn aim_angle(turret_transform: &Transform, aim: &Aim) -> f32 {
+    let angle = Vec2::Y.angle_between(aim.point - turret_transform.translation.xy());
+    if angle.is_nan() {
+        0.0
+    } else {
+        angle
     }
 }
 
@@ -1,8 +1,10 @@
 pub mod health;
+pub mod shield;
 
 use bevy::prelude::*;
 
 pub use health::*;
+pub use shield::*;
 
 /// Set enum for the systems relating to vitality
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
@@ -18,7 +20,16 @@ pub struct VitalityPlugin;
 impl Plugin for VitalityPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<DeathEvent>()
-            .add_systems(Update, update_death.in_set(VitalitySystem::DeathCheck));
+            .add_event::<ShieldBroken>()
+            .add_event::<ShieldRestored>()
+            .add_systems(
+                Update,
+                (
+                    update_shields.before(VitalitySystem::DeathCheck),
+                    update_shield_bubbles.after(update_shields),
+                    update_death.in_set(VitalitySystem::DeathCheck),
+                ),
+            );
     }
 }
 
@@ -37,15 +48,40 @@ impl Damage {
     }
 }
 
-#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Taken off the damage of every projectile that hits, but a hit always does
+/// at least one damage.
+///
+/// # Examples
+///
+/// ```
+/// use space_game::game::vitality::Armor;
+///
+/// assert_eq!(Armor(5).reduce(12), 7);
+/// assert_eq!(Armor(5).reduce(3), 1);
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Armor(pub u32);
+
+impl Armor {
+    pub fn reduce(&self, damage: u32) -> u32 {
+        damage.saturating_sub(self.0).max(damage.min(1))
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
 pub struct DeathEvent {
     entity: Entity,
     _type: GameEntityType,
+    location: Vec2,
 }
 
 impl DeathEvent {
-    pub fn new(entity: Entity, _type: GameEntityType) -> Self {
-        Self { entity, _type }
+    pub fn new(entity: Entity, _type: GameEntityType, location: Vec2) -> Self {
+        Self {
+            entity,
+            _type,
+            location,
+        }
     }
 
     pub fn entity(&self) -> Entity {
@@ -55,19 +91,25 @@ impl DeathEvent {
     pub fn _type(&self) -> GameEntityType {
         return self._type;
     }
+
+    /// Where the entity was when it died.
+    pub fn location(&self) -> Vec2 {
+        self.location
+    }
 }
 
 pub fn update_death(
     mut commands: Commands,
     mut death_event_writer: EventWriter<DeathEvent>,
-    query: Query<(Entity, &Health, Option<&GameEntityType>)>,
+    query: Query<(Entity, &Health, Option<&GameEntityType>, Option<&Transform>)>,
 ) {
-    for (entity, health, game_entity_type) in query.iter() {
+    for (entity, health, game_entity_type, transform) in query.iter() {
         if health.is_dead() {
             commands.entity(entity).despawn_recursive();
 
             if let Some(game_entity_type) = game_entity_type {
-                death_event_writer.send(DeathEvent::new(entity, *game_entity_type));
+                let location = transform.map_or(Vec2::ZERO, |t| t.translation.truncate());
+                death_event_writer.send(DeathEvent::new(entity, *game_entity_type, location));
             }
         }
     }
@@ -0,0 +1,317 @@
+use super::Health;
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How strong a shield is and how fast it comes back.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ShieldConfig {
+    pub capacity: u32,
+    /// Seconds after the last hit before the shield starts to recharge.
+    pub recharge_delay: f32,
+    /// Shield points per second.
+    pub recharge_rate: f32,
+}
+
+impl Default for ShieldConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 30,
+            recharge_delay: 3.0,
+            recharge_rate: 10.0,
+        }
+    }
+}
+
+/// Shield component for entities. A shield absorbs damage before it reaches
+/// the `Health` of the entity, and recharges when the entity has not been hit
+/// for a while.
+///
+/// Damage should be applied with `take_damage`, so that it goes through the
+/// shield if there is one.
+#[derive(Component, Debug, Clone)]
+pub struct Shield {
+    config: ShieldConfig,
+    current: f32,
+    recharge_delay: Timer,
+    broken: bool,
+}
+
+impl Shield {
+    /// Creates a fully charged shield.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use space_game::game::vitality::{Shield, ShieldConfig};
+    ///
+    /// let shield = Shield::new(ShieldConfig::default());
+    ///
+    /// assert_eq!(shield.current(), shield.capacity());
+    /// ```
+    pub fn new(config: ShieldConfig) -> Self {
+        let mut recharge_delay = Timer::from_seconds(config.recharge_delay, TimerMode::Once);
+        recharge_delay.tick(recharge_delay.duration());
+
+        Self {
+            config,
+            current: config.capacity as f32,
+            recharge_delay,
+            broken: false,
+        }
+    }
+
+    pub fn current(&self) -> u32 {
+        self.current as u32
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.config.capacity
+    }
+
+    /// How charged the shield is, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.config.capacity == 0 {
+            return 0.0;
+        }
+        self.current / self.config.capacity as f32
+    }
+
+    /// If the shield has been emptied and not yet fully recharged.
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    /// Absorb as much of the damage as the shield can. Returns the damage
+    /// that gets through.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use space_game::game::vitality::{Shield, ShieldConfig};
+    ///
+    /// let mut shield = Shield::new(ShieldConfig {
+    ///     capacity: 30,
+    ///     ..Default::default()
+    /// });
+    ///
+    /// assert_eq!(shield.absorb(20), 0);
+    /// assert_eq!(shield.absorb(20), 10);
+    /// assert_eq!(shield.current(), 0);
+    /// ```
+    pub fn absorb(&mut self, damage: u32) -> u32 {
+        if damage == 0 {
+            return 0;
+        }
+
+        self.recharge_delay.reset();
+
+        let absorbed = (damage as f32).min(self.current);
+        self.current -= absorbed;
+
+        damage - absorbed as u32
+    }
+
+    /// Charge the shield right away, up to its capacity.
+    pub fn restore(&mut self, amount: u32) {
+        self.current = (self.current + amount as f32).min(self.config.capacity as f32);
+    }
+
+    fn recharge(&mut self, time: &Time) {
+        if !self.recharge_delay.tick(time.delta()).finished() {
+            return;
+        }
+
+        let capacity = self.config.capacity as f32;
+        self.current =
+            (self.current + self.config.recharge_rate * time.delta_seconds()).min(capacity);
+    }
+}
+
+/// Take damage, through the shield if there is one.
+pub fn take_damage(health: &mut Health, shield: Option<&mut Shield>, damage: u32) {
+    let damage = match shield {
+        Some(shield) => shield.absorb(damage),
+        None => damage,
+    };
+    health.take_damage_u32(damage);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+/// The shield of the entity was emptied.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShieldBroken {
+    pub entity: Entity,
+}
+
+/// The shield of the entity is fully recharged after having been broken.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShieldRestored {
+    pub entity: Entity,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Bubble
+////////////////////////////////////////////////////////////////////////////////
+
+/// The bubble drawn around an entity with a shield. Spawned as a child of the
+/// entity.
+#[derive(Component)]
+pub struct ShieldBubble;
+
+const BUBBLE_COLOR: Color = Color::rgb(0.3, 0.7, 1.0);
+
+pub fn spawn_bubble(commands: &mut Commands, parent: Entity, radius: f32) {
+    let shape = shapes::Circle {
+        radius,
+        center: Vec2::ZERO,
+    };
+
+    let bubble = commands
+        .spawn((
+            ShieldBubble,
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shape),
+                ..default()
+            },
+            Fill::color(BUBBLE_COLOR.with_a(0.1)),
+            Stroke::new(BUBBLE_COLOR.with_a(0.6), 2.0),
+        ))
+        .id();
+
+    commands.entity(parent).add_child(bubble);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn update_shields(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Shield)>,
+    mut shield_broken_events: EventWriter<ShieldBroken>,
+    mut shield_restored_events: EventWriter<ShieldRestored>,
+) {
+    for (entity, mut shield) in query.iter_mut() {
+        // The recharge delay ticks every frame, only flag the shield as changed
+        // when the bubble has something to show for it
+        let changed = {
+            let shield = shield.bypass_change_detection();
+            let before = (shield.current, shield.broken);
+
+            shield.recharge(&time);
+
+            if !shield.broken && shield.current <= 0.0 {
+                shield.broken = true;
+                shield_broken_events.send(ShieldBroken { entity });
+            } else if shield.broken && shield.fraction() >= 1.0 {
+                shield.broken = false;
+                shield_restored_events.send(ShieldRestored { entity });
+            }
+
+            (shield.current, shield.broken) != before
+        };
+
+        if changed {
+            shield.set_changed();
+        }
+    }
+}
+
+// Only touch the bubble when the shield changed, lyon rebuilds the mesh of
+// shapes whose components changed.
+pub fn update_shield_bubbles(
+    shield_query: Query<(&Shield, &Children), Changed<Shield>>,
+    mut bubble_query: Query<(&mut Fill, &mut Stroke, &mut Visibility), With<ShieldBubble>>,
+) {
+    for (shield, children) in shield_query.iter() {
+        for child in children.iter() {
+            if let Ok((mut fill, mut stroke, mut visibility)) = bubble_query.get_mut(*child) {
+                let fraction = shield.fraction();
+
+                visibility.set_if_neq(if fraction > 0.0 {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                });
+                fill.set_if_neq(Fill {
+                    color: BUBBLE_COLOR.with_a(0.1 * fraction),
+                    ..*fill
+                });
+                stroke.set_if_neq(Stroke {
+                    color: BUBBLE_COLOR.with_a(0.2 + 0.4 * fraction),
+                    ..*stroke
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_shield_recharges_after_delay() {
+        let mut shield = Shield::new(ShieldConfig {
+            capacity: 10,
+            recharge_delay: 1.0,
+            recharge_rate: 5.0,
+        });
+        let mut health = Health::at_max(100);
+
+        take_damage(&mut health, Some(&mut shield), 15);
+        assert_eq!(shield.current(), 0);
+        assert_eq!(health.current(), 95);
+
+        let mut time = Time::default();
+        time.advance_by(Duration::from_secs_f32(0.5));
+
+        // Still waiting for the delay
+        shield.recharge(&time);
+        assert_eq!(shield.current(), 0);
+
+        shield.recharge(&time);
+        assert_eq!(shield.current(), 2);
+        shield.recharge(&time);
+        assert_eq!(shield.current(), 5);
+    }
+
+    #[test]
+    fn test_full_shield_is_not_changed_every_frame() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<ShieldBroken>>();
+        world.init_resource::<Events<ShieldRestored>>();
+        let entity = world.spawn(Shield::new(ShieldConfig::default())).id();
+
+        let mut update = IntoSystem::into_system(update_shields);
+        let mut changed =
+            IntoSystem::into_system(|query: Query<(), Changed<Shield>>| !query.is_empty());
+        update.initialize(&mut world);
+        changed.initialize(&mut world);
+        // Sees the spawn
+        changed.run((), &mut world);
+
+        update.run((), &mut world);
+        assert!(!changed.run((), &mut world));
+
+        // Recharging is a change
+        world
+            .get_mut::<Shield>(entity)
+            .unwrap()
+            .bypass_change_detection()
+            .absorb(10);
+        world
+            .resource_mut::<Time>()
+            .advance_by(std::time::Duration::from_secs(4));
+        update.run((), &mut world);
+        update.run((), &mut world);
+        assert!(changed.run((), &mut world));
+    }
+}
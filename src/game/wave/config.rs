@@ -0,0 +1,232 @@
+use crate::game::assets::{self, Asset};
+use crate::game::boids::Formation;
+use crate::game::faction::Faction;
+use crate::game::turret::TurretPreset;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+////////////////////////////////////////////////////////////////////////////////
+// Enemy Kinds
+////////////////////////////////////////////////////////////////////////////////
+
+/// The enemies a wave can be made of.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EnemyKind {
+    Turret,
+    EnemyShip,
+    KamikazeDrone,
+}
+
+impl EnemyKind {
+    /// The asset whose collider is used when searching for a spawn location.
+    pub fn asset(&self) -> Asset {
+        match self {
+            EnemyKind::Turret => assets::TURRET_BASE_BIG,
+            EnemyKind::EnemyShip => assets::ENEMY_SHIP_1,
+            EnemyKind::KamikazeDrone => assets::KAMIKAZE_DRONE,
+        }
+    }
+}
+
+impl fmt::Display for EnemyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnemyKind::Turret => write!(f, "Turret"),
+            EnemyKind::EnemyShip => write!(f, "Enemy Ship"),
+            EnemyKind::KamikazeDrone => write!(f, "Kamikaze Drone"),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Waves
+////////////////////////////////////////////////////////////////////////////////
+
+/// How the enemies of a group are placed in the arena.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum SpawnPattern {
+    /// Every enemy is spawned at its own location.
+    Scattered,
+    /// All enemies are spawned together in a ring with the given radius.
+    Cluster { radius: f32 },
+    /// Enemies are spawned one at a time with `interval` seconds in between.
+    Stream { interval: f32 },
+}
+
+/// A number of enemies of the same kind.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnGroup {
+    pub kind: EnemyKind,
+    pub count: u32,
+    pub pattern: SpawnPattern,
+    /// Seconds after the start of the wave before the group is spawned.
+    #[serde(default)]
+    pub delay: f32,
+    /// `Enemy1` if not set.
+    #[serde(default = "default_faction")]
+    pub faction: Faction,
+    /// The preset of `Turret` groups, `[enemies.turret]` of the level if not
+    /// set.
+    #[serde(default)]
+    pub turret: Option<TurretPreset>,
+    /// The formation `EnemyShip` groups fly in, led by the first ship. Only
+    /// for `Cluster` groups, the ships of other patterns fly alone.
+    #[serde(default)]
+    pub formation: Option<Formation>,
+}
+
+fn default_faction() -> Faction {
+    Faction::Enemy1
+}
+
+impl SpawnGroup {
+    pub fn new(kind: EnemyKind, count: u32, pattern: SpawnPattern) -> Self {
+        Self {
+            kind,
+            count,
+            pattern,
+            delay: 0.0,
+            faction: default_faction(),
+            turret: None,
+            formation: None,
+        }
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_faction(mut self, faction: Faction) -> Self {
+        self.faction = faction;
+        self
+    }
+
+    pub fn with_turret(mut self, preset: TurretPreset) -> Self {
+        self.turret = Some(preset);
+        self
+    }
+
+    pub fn with_formation(mut self, formation: Formation) -> Self {
+        self.formation = Some(formation);
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Wave {
+    pub groups: Vec<SpawnGroup>,
+    /// Seconds of rest after the wave is cleared before the next one starts.
+    pub rest: f32,
+}
+
+impl Wave {
+    pub fn new(groups: Vec<SpawnGroup>, rest: f32) -> Self {
+        Self { groups, rest }
+    }
+
+    /// The total number of enemies in the wave.
+    pub fn enemy_count(&self) -> u32 {
+        self.groups.iter().map(|group| group.count).sum()
+    }
+}
+
+/// All the waves of a game.
+///
+/// When the last wave is cleared it is repeated over and over, with 25% more
+/// enemies each time.
+#[derive(Resource, Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WaveSchedule {
+    /// Seconds before the first wave starts.
+    pub initial_delay: f32,
+    /// Written as `[[waves.wave]]` tables in level files.
+    #[serde(rename = "wave")]
+    pub waves: Vec<Wave>,
+}
+
+impl WaveSchedule {
+    /// The wave at `index`, starting from zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use space_game::game::wave::{EnemyKind, SpawnGroup, SpawnPattern, Wave, WaveSchedule};
+    ///
+    /// let schedule = WaveSchedule {
+    ///     initial_delay: 0.0,
+    ///     waves: vec![Wave::new(
+    ///         vec![SpawnGroup::new(EnemyKind::Turret, 4, SpawnPattern::Scattered)],
+    ///         5.0,
+    ///     )],
+    /// };
+    ///
+    /// assert_eq!(schedule.wave(0).unwrap().enemy_count(), 4);
+    /// assert_eq!(schedule.wave(1).unwrap().enemy_count(), 5);
+    /// assert_eq!(schedule.wave(2).unwrap().enemy_count(), 6);
+    /// ```
+    pub fn wave(&self, index: usize) -> Option<Wave> {
+        if let Some(wave) = self.waves.get(index) {
+            return Some(wave.clone());
+        }
+
+        let last = self.waves.last()?;
+        let repeats = (index + 1 - self.waves.len()) as f32;
+        let scale = 1.0 + 0.25 * repeats;
+
+        Some(Wave {
+            groups: last
+                .groups
+                .iter()
+                .map(|group| SpawnGroup {
+                    count: (group.count as f32 * scale).ceil() as u32,
+                    ..*group
+                })
+                .collect(),
+            rest: last.rest,
+        })
+    }
+}
+
+impl Default for WaveSchedule {
+    fn default() -> Self {
+        use EnemyKind::*;
+        use SpawnPattern::*;
+
+        Self {
+            initial_delay: 10.0,
+            waves: vec![
+                Wave::new(vec![SpawnGroup::new(Turret, 3, Scattered)], 5.0),
+                Wave::new(
+                    vec![
+                        SpawnGroup::new(Turret, 4, Scattered),
+                        SpawnGroup::new(EnemyShip, 2, Stream { interval: 3.0 }).with_delay(5.0),
+                    ],
+                    5.0,
+                ),
+                Wave::new(
+                    vec![
+                        SpawnGroup::new(Turret, 3, Scattered),
+                        SpawnGroup::new(KamikazeDrone, 6, Cluster { radius: 60.0 }).with_delay(3.0),
+                    ],
+                    8.0,
+                ),
+                Wave::new(
+                    vec![
+                        SpawnGroup::new(Turret, 3, Scattered),
+                        SpawnGroup::new(Turret, 2, Scattered)
+                            .with_turret(TurretPreset::ShieldedBunker),
+                        SpawnGroup::new(EnemyShip, 3, Cluster { radius: 130.0 })
+                            .with_formation(Formation::V { spacing: 130.0 }),
+                        SpawnGroup::new(KamikazeDrone, 8, Cluster { radius: 80.0 })
+                            .with_delay(10.0),
+                    ],
+                    10.0,
+                ),
+            ],
+        }
+    }
+}
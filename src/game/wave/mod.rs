@@ -0,0 +1,467 @@
+//! # Waves
+//!
+//! The wave director spawns enemies in waves. What a wave is made of is data,
+//! see `WaveSchedule`. A wave starts after a rest period, spawns its groups of
+//! enemies over time and is cleared once every enemy has been spawned and
+//! killed.
+//!
+//! The current wave and the number of enemies left can be read from the
+//! `WaveState` resource. `WaveStarted` and `WaveCleared` are sent when a wave
+//! begins and ends.
+
+mod config;
+
+use super::arena::SpawnLocationFinder;
+use super::boids::Formation;
+use super::events::{WaveCleared, WaveStarted};
+use super::faction::Faction;
+use super::game_entity::Enemy;
+use super::level::{EnemyStats, Level};
+use super::rng::{GameRng, RngStream};
+use super::turret::{self, TurretPreset};
+use super::{enemy, kamikaze_drone};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use std::f32::consts::PI;
+
+pub use config::*;
+
+/// How many frames in a row a spawn may find no free location before it is
+/// given up on.
+const MAX_SPAWN_ATTEMPTS: u32 = 300;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WaveStarted>()
+            .add_event::<WaveCleared>()
+            .add_systems(
+                Update,
+                (update_wave_state, spawn_wave_enemies)
+                    .chain()
+                    .run_if(resource_exists::<WaveState>()),
+            );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Spawn & Despawn
+////////////////////////////////////////////////////////////////////////////////
+
+/// Start the wave director with the waves and enemies of the level, or the
+/// defaults if no level has been inserted.
+pub fn spawn(mut commands: Commands, level: Option<Res<Level>>) {
+    let (schedule, stats) = level
+        .map(|level| (level.waves.clone(), level.enemies))
+        .unwrap_or_default();
+
+    commands.insert_resource(WaveState::new(&schedule));
+    commands.insert_resource(schedule);
+    commands.insert_resource(stats);
+}
+
+pub fn despawn(mut commands: Commands) {
+    commands.remove_resource::<WaveState>();
+    commands.remove_resource::<WaveSchedule>();
+    commands.remove_resource::<EnemyStats>();
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Resources
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WavePhase {
+    /// Waiting for the next wave to start.
+    Resting,
+    /// Enemies are spawning or still alive.
+    InProgress,
+}
+
+#[derive(Resource, Debug)]
+pub struct WaveState {
+    /// The number of the current wave, starting from 1. Zero before the first
+    /// wave has started.
+    wave: u32,
+    phase: WavePhase,
+    rest_timer: Timer,
+    /// Seconds since the current wave started.
+    elapsed: f32,
+    /// Spawns that are waiting for their time, sorted by time.
+    pending: Vec<PendingSpawn>,
+    enemies_alive: usize,
+}
+
+impl WaveState {
+    pub fn new(schedule: &WaveSchedule) -> Self {
+        Self {
+            wave: 0,
+            phase: WavePhase::Resting,
+            rest_timer: Timer::from_seconds(schedule.initial_delay, TimerMode::Once),
+            elapsed: 0.0,
+            pending: Vec::new(),
+            enemies_alive: 0,
+        }
+    }
+
+    pub fn wave(&self) -> u32 {
+        self.wave
+    }
+
+    pub fn phase(&self) -> WavePhase {
+        self.phase
+    }
+
+    /// Enemies of the current wave that are alive or have yet to spawn.
+    pub fn remaining_enemies(&self) -> usize {
+        let pending: u32 = self.pending.iter().map(|spawn| spawn.count).sum();
+        self.enemies_alive + pending as usize
+    }
+
+    /// Seconds until the next wave starts, if resting.
+    pub fn time_until_next_wave(&self) -> Option<f32> {
+        match self.phase {
+            WavePhase::Resting => Some(self.rest_timer.remaining_secs()),
+            WavePhase::InProgress => None,
+        }
+    }
+
+    fn start_wave(&mut self, wave: &Wave) {
+        self.wave += 1;
+        self.phase = WavePhase::InProgress;
+        self.elapsed = 0.0;
+        self.pending = PendingSpawn::from_wave(wave);
+    }
+
+    fn clear_wave(&mut self, rest: f32) {
+        self.phase = WavePhase::Resting;
+        self.rest_timer = Timer::from_seconds(rest, TimerMode::Once);
+    }
+
+    /// Take the next spawn whose time has come.
+    fn next_ready_spawn(&mut self) -> Option<PendingSpawn> {
+        if self.pending.first()?.at <= self.elapsed {
+            Some(self.pending.remove(0))
+        } else {
+            None
+        }
+    }
+
+    /// Put back a spawn that could not be placed, behind the other spawns
+    /// that are due, to try again next frame. Returns false if it was tried
+    /// too often and is dropped.
+    fn retry_spawn(&mut self, mut spawn: PendingSpawn) -> bool {
+        spawn.attempts += 1;
+        if spawn.attempts >= MAX_SPAWN_ATTEMPTS {
+            return false;
+        }
+        let elapsed = self.elapsed;
+        let index = self
+            .pending
+            .partition_point(|pending| pending.at <= elapsed);
+        self.pending.insert(index, spawn);
+        true
+    }
+}
+
+/// Enemies that will be spawned together at `at` seconds into the wave.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PendingSpawn {
+    at: f32,
+    kind: EnemyKind,
+    faction: Faction,
+    turret: Option<TurretPreset>,
+    formation: Option<Formation>,
+    count: u32,
+    /// If set, the enemies are placed in a ring with this radius.
+    cluster_radius: Option<f32>,
+    /// The frames no location to spawn at was found.
+    attempts: u32,
+}
+
+impl PendingSpawn {
+    fn from_wave(wave: &Wave) -> Vec<PendingSpawn> {
+        let mut pending = Vec::new();
+
+        for group in wave.groups.iter().filter(|group| group.count > 0) {
+            let spawn = PendingSpawn {
+                at: group.delay,
+                kind: group.kind,
+                faction: group.faction,
+                turret: group.turret,
+                formation: group.formation,
+                count: 1,
+                cluster_radius: None,
+                attempts: 0,
+            };
+
+            match group.pattern {
+                SpawnPattern::Scattered => {
+                    pending.extend((0..group.count).map(|_| spawn));
+                }
+                SpawnPattern::Cluster { radius } => pending.push(PendingSpawn {
+                    count: group.count,
+                    cluster_radius: Some(radius),
+                    ..spawn
+                }),
+                SpawnPattern::Stream { interval } => {
+                    pending.extend((0..group.count).map(|i| PendingSpawn {
+                        at: group.delay + i as f32 * interval,
+                        ..spawn
+                    }));
+                }
+            }
+        }
+
+        pending.sort_by(|a, b| a.at.total_cmp(&b.at));
+        pending
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn update_wave_state(
+    time: Res<Time>,
+    schedule: Res<WaveSchedule>,
+    mut wave_state: ResMut<WaveState>,
+    enemy_query: Query<(), With<Enemy>>,
+    mut wave_started_events: EventWriter<WaveStarted>,
+    mut wave_cleared_events: EventWriter<WaveCleared>,
+) {
+    wave_state.enemies_alive = enemy_query.iter().len();
+
+    match wave_state.phase {
+        WavePhase::Resting => {
+            if wave_state.rest_timer.tick(time.delta()).finished() {
+                if let Some(wave) = schedule.wave(wave_state.wave as usize) {
+                    wave_state.start_wave(&wave);
+                    wave_started_events.send(WaveStarted {
+                        wave: wave_state.wave,
+                        enemies: wave.enemy_count(),
+                    });
+                }
+            }
+        }
+        WavePhase::InProgress => {
+            wave_state.elapsed += time.delta_seconds();
+
+            if wave_state.remaining_enemies() == 0 {
+                let rest = schedule
+                    .wave(wave_state.wave as usize - 1)
+                    .map_or(0.0, |wave| wave.rest);
+                wave_state.clear_wave(rest);
+                wave_cleared_events.send(WaveCleared {
+                    wave: wave_state.wave,
+                });
+            }
+        }
+    }
+}
+
+/// Spawn at most one pending spawn each frame. New colliders are not part of
+/// the physics world until the next frame, so spawning more at once could
+/// place them on top of each other.
+fn spawn_wave_enemies(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut wave_state: ResMut<WaveState>,
+    mut game_rng: ResMut<GameRng>,
+    stats: Res<EnemyStats>,
+    finder: SpawnLocationFinder,
+) {
+    let Some(spawn) = wave_state.next_ready_spawn() else {
+        return;
+    };
+
+    let collider = spawn.kind.asset().collider();
+    let collider = match spawn.cluster_radius {
+        // Make room for the whole cluster
+        Some(radius) => {
+            Collider::ball(radius + collider.raw.compute_local_bounding_sphere().radius())
+        }
+        None => collider,
+    };
+
+    let Some(location) = finder.find(&collider, game_rng.stream(RngStream::EnemySpawn)) else {
+        if !wave_state.retry_spawn(spawn) {
+            warn!(
+                "Could not find a location to spawn {} after {} attempts",
+                spawn.kind, MAX_SPAWN_ATTEMPTS
+            );
+        }
+        return;
+    };
+
+    let angle_offset = game_rng.stream(RngStream::EnemySpawn).gen::<f32>() * 2.0 * PI;
+    let mut spawned = Vec::new();
+
+    for i in 0..spawn.count {
+        let angle = angle_offset + 2.0 * PI * i as f32 / spawn.count as f32;
+        let position = location
+            + spawn
+                .cluster_radius
+                .map_or(Vec2::ZERO, |radius| Vec2::from_angle(angle) * radius);
+
+        spawned.extend(spawn_enemy_kind(
+            &mut commands,
+            &asset_server,
+            &mut game_rng,
+            &stats,
+            &spawn,
+            position,
+            angle,
+        ));
+    }
+
+    // Clusters fly together
+    match (spawn.kind, spawn.formation) {
+        (EnemyKind::EnemyShip, Some(formation)) => {
+            enemy::form_squad(&mut commands, &spawned, formation);
+        }
+        (EnemyKind::KamikazeDrone, _) => kamikaze_drone::form_swarm(&mut commands, &spawned),
+        _ => {}
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Helpers
+////////////////////////////////////////////////////////////////////////////////
+
+fn spawn_enemy_kind(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    game_rng: &mut GameRng,
+    stats: &EnemyStats,
+    spawn: &PendingSpawn,
+    position: Vec2,
+    rotation: f32,
+) -> Option<Entity> {
+    match spawn.kind {
+        EnemyKind::Turret => {
+            turret::spawn(
+                commands,
+                asset_server,
+                &spawn.turret.map_or(stats.turret, |preset| preset.config()),
+                Transform::from_translation(position.extend(0.0)),
+                spawn.faction,
+            );
+            None
+        }
+        EnemyKind::EnemyShip => Some(enemy::spawn(
+            commands,
+            asset_server,
+            game_rng.stream(RngStream::EnemyAI),
+            position,
+            rotation,
+            spawn.faction,
+        )),
+        EnemyKind::KamikazeDrone => Some(kamikaze_drone::spawn(
+            commands,
+            asset_server,
+            position,
+            rotation,
+            spawn.faction,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wave() -> Wave {
+        Wave::new(
+            vec![
+                SpawnGroup::new(EnemyKind::Turret, 2, SpawnPattern::Scattered)
+                    .with_delay(1.0)
+                    .with_turret(TurretPreset::MissileBattery),
+                SpawnGroup::new(
+                    EnemyKind::KamikazeDrone,
+                    5,
+                    SpawnPattern::Cluster { radius: 50.0 },
+                ),
+                SpawnGroup::new(
+                    EnemyKind::EnemyShip,
+                    3,
+                    SpawnPattern::Stream { interval: 2.0 },
+                ),
+            ],
+            5.0,
+        )
+    }
+
+    #[test]
+    fn test_pending_spawns_follow_the_patterns() {
+        let pending = PendingSpawn::from_wave(&wave());
+
+        let spawns: Vec<(f32, EnemyKind, u32)> = pending
+            .iter()
+            .map(|spawn| (spawn.at, spawn.kind, spawn.count))
+            .collect();
+
+        assert_eq!(
+            spawns,
+            vec![
+                (0.0, EnemyKind::KamikazeDrone, 5),
+                (0.0, EnemyKind::EnemyShip, 1),
+                (1.0, EnemyKind::Turret, 1),
+                (1.0, EnemyKind::Turret, 1),
+                (2.0, EnemyKind::EnemyShip, 1),
+                (4.0, EnemyKind::EnemyShip, 1),
+            ]
+        );
+        assert!(pending
+            .iter()
+            .filter(|spawn| spawn.kind == EnemyKind::Turret)
+            .all(|spawn| spawn.turret == Some(TurretPreset::MissileBattery)));
+    }
+
+    #[test]
+    fn test_remaining_enemies_counts_pending_and_alive() {
+        let mut wave_state = WaveState::new(&WaveSchedule::default());
+        wave_state.start_wave(&wave());
+        assert_eq!(wave_state.remaining_enemies(), 10);
+
+        // Only the spawns that are due are taken
+        wave_state.next_ready_spawn();
+        wave_state.next_ready_spawn();
+        assert_eq!(wave_state.next_ready_spawn(), None);
+
+        wave_state.enemies_alive = 6;
+        assert_eq!(wave_state.remaining_enemies(), 10);
+    }
+
+    #[test]
+    fn test_spawn_without_location_is_retried_behind_due_spawns() {
+        let mut wave_state = WaveState::new(&WaveSchedule::default());
+        wave_state.start_wave(&wave());
+        let first = wave_state.next_ready_spawn().unwrap();
+
+        assert_eq!(first.kind, EnemyKind::KamikazeDrone);
+        assert!(wave_state.retry_spawn(first));
+        assert_eq!(wave_state.remaining_enemies(), 10);
+
+        // The other due spawn goes first
+        let next = wave_state.next_ready_spawn().unwrap();
+        assert_eq!(next.kind, EnemyKind::EnemyShip);
+        let retried = wave_state.next_ready_spawn().unwrap();
+        assert_eq!(
+            (retried.kind, retried.attempts),
+            (EnemyKind::KamikazeDrone, 1)
+        );
+
+        let spawn = PendingSpawn {
+            attempts: MAX_SPAWN_ATTEMPTS - 1,
+            ..first
+        };
+        assert!(!wave_state.retry_spawn(spawn));
+    }
+}
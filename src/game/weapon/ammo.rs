@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A finite number of rounds that are reloaded when they run out.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AmmoConfig {
+    pub capacity: u32,
+    /// Seconds it takes to reload.
+    pub reload: f32,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Ammo {
+    config: AmmoConfig,
+    rounds: u32,
+    reload: Option<Timer>,
+}
+
+impl Ammo {
+    pub fn new(config: AmmoConfig) -> Self {
+        Self {
+            config,
+            rounds: config.capacity,
+            reload: None,
+        }
+    }
+
+    pub fn rounds(&self) -> u32 {
+        self.rounds
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.config.capacity
+    }
+
+    /// How far along the reload is, from 0 to 1. `None` if not reloading.
+    pub fn reload_progress(&self) -> Option<f32> {
+        self.reload.as_ref().map(Timer::percent)
+    }
+
+    pub fn has_rounds(&self) -> bool {
+        self.rounds > 0
+    }
+
+    /// Use a round, the reload starts when the last one is gone.
+    pub fn take_round(&mut self) {
+        self.rounds = self.rounds.saturating_sub(1);
+        if self.rounds == 0 && self.reload.is_none() {
+            self.reload = Some(Timer::from_seconds(self.config.reload, TimerMode::Once));
+        }
+    }
+
+    pub fn update(&mut self, time: &Time) {
+        if let Some(reload) = &mut self.reload {
+            if reload.tick(time.delta()).finished() {
+                self.reload = None;
+                self.rounds = self.config.capacity;
+            }
+        }
+    }
+}
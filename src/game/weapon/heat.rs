@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How a weapon heats up. Heat goes from 0 to 1, at 1 the weapon overheats
+/// and can not fire until the lockout is over.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HeatConfig {
+    /// Heat added by every shot.
+    pub per_shot: f32,
+    /// Heat lost per second.
+    pub dissipation: f32,
+    /// Seconds the weapon is locked after overheating.
+    pub lockout: f32,
+}
+
+impl Default for HeatConfig {
+    fn default() -> Self {
+        Self {
+            per_shot: 0.04,
+            dissipation: 0.25,
+            lockout: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Heat {
+    config: HeatConfig,
+    current: f32,
+    lockout: Option<Timer>,
+}
+
+impl Heat {
+    pub fn new(config: HeatConfig) -> Self {
+        Self {
+            config,
+            current: 0.0,
+            lockout: None,
+        }
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn is_overheated(&self) -> bool {
+        self.lockout.is_some()
+    }
+
+    pub fn add_shot(&mut self) {
+        self.current = (self.current + self.config.per_shot).min(1.0);
+        if self.current >= 1.0 && self.lockout.is_none() {
+            self.lockout = Some(Timer::from_seconds(self.config.lockout, TimerMode::Once));
+        }
+    }
+
+    pub fn update(&mut self, time: &Time) {
+        self.current = (self.current - self.config.dissipation * time.delta_seconds()).max(0.0);
+
+        if let Some(lockout) = &mut self.lockout {
+            if lockout.tick(time.delta()).finished() {
+                self.lockout = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_overheating_locks_until_lockout_is_over() {
+        let mut heat = Heat::new(HeatConfig {
+            per_shot: 0.5,
+            dissipation: 0.1,
+            lockout: 1.0,
+        });
+
+        heat.add_shot();
+        assert!(!heat.is_overheated());
+        heat.add_shot();
+        assert!(heat.is_overheated());
+        assert_eq!(heat.current(), 1.0);
+
+        let mut time = Time::default();
+        time.advance_by(Duration::from_secs_f32(0.5));
+        heat.update(&time);
+        assert!(heat.is_overheated());
+        assert!((heat.current() - 0.95).abs() < 1e-5);
+
+        heat.update(&time);
+        assert!(!heat.is_overheated());
+    }
+}
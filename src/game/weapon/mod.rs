@@ -1,7 +1,16 @@
+mod ammo;
+mod heat;
+
 use bevy::prelude::*;
-use bevy_rapier2d::prelude::Group;
+use serde::{Deserialize, Serialize};
+
+use super::faction::Faction;
+use super::projectile::{self, ProjectileStats};
+use ammo::Ammo;
+use heat::Heat;
 
-use super::projectile;
+pub use ammo::AmmoConfig;
+pub use heat::HeatConfig;
 
 ////////////////////////////////////////////////////////////////////////////////
 // Plugin
@@ -11,40 +20,283 @@ pub struct WeaponPlugin;
 
 impl Plugin for WeaponPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_weapon);
+        app.add_event::<WeaponFired>()
+            .add_systems(Update, (assign_shooters, update_weapon));
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+/// Sent for every shot fired with `Weapon::fire` or `Weapon::release`, and
+/// for the rest of a burst.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct WeaponFired {
+    pub position: Vec2,
+}
+
+/// `fire` only has `Commands`, so the event is sent when they are applied.
+fn send_weapon_fired(commands: &mut Commands, spawn_transform: &Transform) {
+    let event = WeaponFired {
+        position: spawn_transform.translation.truncate(),
+    };
+    commands.add(move |world: &mut World| {
+        world.send_event(event);
+    });
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Components
 ////////////////////////////////////////////////////////////////////////////////
 
+/// A weapon that can be mounted on anything with a `Transform`. Whoever pulls
+/// the trigger calls `fire` while `can_fire` is true, and `release` when the
+/// trigger is let go.
+///
+/// On top of the cooldowns of its type a weapon can heat up and run out of
+/// ammo, see `Weapon::with_heat` and `Weapon::with_ammo`.
 #[derive(Component)]
-pub struct Weapon(WeaponType);
+pub struct Weapon {
+    weapon_type: WeaponType,
+    heat: Option<Heat>,
+    ammo: Option<Ammo>,
+}
+
+/// What the HUD needs to know about a weapon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeaponStatus {
+    /// From 0 to 1, `None` if the weapon does not heat up.
+    pub heat: Option<f32>,
+    pub overheated: bool,
+    /// Rounds left and capacity, `None` if the ammo is unlimited.
+    pub ammo: Option<(u32, u32)>,
+    /// From 0 to 1 while reloading.
+    pub reload: Option<f32>,
+    /// From 0 to 1 while charging.
+    pub charge: Option<f32>,
+}
 
 pub enum WeaponType {
+    /// One projectile per shot.
     Laser {
-        projectile_damage: u32,
-        projectile_speed: f32,
-        projectile_time_to_live: Timer,
-        projectile_collision_membership: Group,
-        projectile_collision_filter: Group,
+        projectile: ProjectileStats,
         cooldown: Option<Timer>,
     },
+    /// `count` projectiles fanned out over `angle` radians per shot.
+    Spread {
+        projectile: ProjectileStats,
+        count: u32,
+        angle: f32,
+        cooldown: Timer,
+    },
+    /// `shots` projectiles fired one after the other, `interval` apart. The
+    /// cooldown starts after the last shot of the burst.
+    Burst {
+        projectile: ProjectileStats,
+        shots: u32,
+        interval: Timer,
+        remaining: u32,
+        cooldown: Timer,
+    },
+    /// Charges while the trigger is held and fires a piercing beam when it is
+    /// released. A fully charged beam is fired right away.
+    ChargedBeam {
+        projectile: ProjectileStats,
+        charge: Timer,
+        charging: bool,
+        pierce: u32,
+        cooldown: Timer,
+    },
+    /// Missiles that steer towards the closest target.
+    HomingMissile {
+        projectile: ProjectileStats,
+        turn_rate: f32,
+        range: f32,
+        cooldown: Timer,
+    },
+    /// Drops mines that go off when a target comes close.
+    MineLayer {
+        projectile: ProjectileStats,
+        fuse_radius: f32,
+        blast_radius: f32,
+        cooldown: Timer,
+    },
+}
+
+/// The kinds of weapon that can be picked in config files.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WeaponArchetype {
+    #[default]
+    Laser,
+    Spread,
+    Burst,
+    ChargedBeam,
+    HomingMissile,
+    MineLayer,
 }
 
 impl Weapon {
     pub fn new(weapon_type: WeaponType) -> Self {
-        Self(weapon_type)
+        Self {
+            weapon_type,
+            heat: None,
+            ammo: None,
+        }
+    }
+
+    pub fn with_heat(mut self, config: HeatConfig) -> Self {
+        self.heat = Some(Heat::new(config));
+        self
+    }
+
+    pub fn with_ammo(mut self, config: AmmoConfig) -> Self {
+        self.ammo = Some(Ammo::new(config));
+        self
+    }
+
+    pub fn status(&self) -> WeaponStatus {
+        WeaponStatus {
+            heat: self.heat.as_ref().map(Heat::current),
+            overheated: self.heat.as_ref().is_some_and(Heat::is_overheated),
+            ammo: self
+                .ammo
+                .as_ref()
+                .map(|ammo| (ammo.rounds(), ammo.capacity())),
+            reload: self.ammo.as_ref().and_then(Ammo::reload_progress),
+            charge: self.charge(),
+        }
+    }
+
+    /// A weapon of the given archetype, firing for `faction`. The damage is
+    /// per projectile and the cooldown is in seconds, everything else uses the
+    /// archetype's defaults.
+    pub fn from_archetype(
+        archetype: WeaponArchetype,
+        damage: u32,
+        cooldown: f32,
+        faction: Faction,
+    ) -> Self {
+        let projectile = ProjectileStats {
+            damage,
+            speed: 1000.0,
+            time_to_live: 3.0,
+            faction,
+            shooter: None,
+        };
+        let cooldown_timer =
+            |scale: f32| Timer::from_seconds(cooldown_secs(cooldown) * scale, TimerMode::Once);
+
+        Self::new(match archetype {
+            WeaponArchetype::Laser => WeaponType::Laser {
+                projectile,
+                cooldown: Some(cooldown_timer(1.0)),
+            },
+            WeaponArchetype::Spread => WeaponType::Spread {
+                projectile,
+                count: 5,
+                angle: 0.6,
+                cooldown: cooldown_timer(4.0),
+            },
+            WeaponArchetype::Burst => WeaponType::Burst {
+                projectile,
+                shots: 3,
+                interval: Timer::from_seconds(0.06, TimerMode::Once),
+                remaining: 0,
+                cooldown: cooldown_timer(5.0),
+            },
+            WeaponArchetype::ChargedBeam => WeaponType::ChargedBeam {
+                projectile: ProjectileStats {
+                    speed: 2000.0,
+                    time_to_live: 1.0,
+                    ..projectile
+                },
+                charge: Timer::from_seconds(1.0, TimerMode::Once),
+                charging: false,
+                pierce: 3,
+                cooldown: cooldown_timer(5.0),
+            },
+            WeaponArchetype::HomingMissile => WeaponType::HomingMissile {
+                projectile: ProjectileStats {
+                    damage: damage * 3,
+                    speed: 500.0,
+                    time_to_live: 5.0,
+                    ..projectile
+                },
+                turn_rate: 3.0,
+                range: 800.0,
+                cooldown: cooldown_timer(8.0),
+            },
+            WeaponArchetype::MineLayer => WeaponType::MineLayer {
+                projectile: ProjectileStats {
+                    damage: damage * 5,
+                    speed: 0.0,
+                    time_to_live: 30.0,
+                    ..projectile
+                },
+                fuse_radius: 80.0,
+                blast_radius: 150.0,
+                cooldown: cooldown_timer(10.0),
+            },
+        })
     }
 
     pub fn can_fire(&self) -> bool {
-        match &self.0 {
-            WeaponType::Laser {
-                cooldown: Some(cooldown),
+        self.has_heat_and_ammo() && self.is_ready()
+    }
+
+    /// Not overheated and with rounds left.
+    fn has_heat_and_ammo(&self) -> bool {
+        !self.heat.as_ref().is_some_and(Heat::is_overheated)
+            && self.ammo.as_ref().map_or(true, Ammo::has_rounds)
+    }
+
+    /// Heat up and use a round for every shot.
+    fn use_shot(&mut self) {
+        if let Some(heat) = &mut self.heat {
+            heat.add_shot();
+        }
+        if let Some(ammo) = &mut self.ammo {
+            ammo.take_round();
+        }
+    }
+
+    /// What the weapon fires, before any charge is added.
+    pub fn projectile(&self) -> &ProjectileStats {
+        match &self.weapon_type {
+            WeaponType::Laser { projectile, .. }
+            | WeaponType::Spread { projectile, .. }
+            | WeaponType::Burst { projectile, .. }
+            | WeaponType::ChargedBeam { projectile, .. }
+            | WeaponType::HomingMissile { projectile, .. }
+            | WeaponType::MineLayer { projectile, .. } => projectile,
+        }
+    }
+
+    fn projectile_mut(&mut self) -> &mut ProjectileStats {
+        match &mut self.weapon_type {
+            WeaponType::Laser { projectile, .. }
+            | WeaponType::Spread { projectile, .. }
+            | WeaponType::Burst { projectile, .. }
+            | WeaponType::ChargedBeam { projectile, .. }
+            | WeaponType::HomingMissile { projectile, .. }
+            | WeaponType::MineLayer { projectile, .. } => projectile,
+        }
+    }
+
+    /// Whether the cooldowns of the weapon type are over.
+    fn is_ready(&self) -> bool {
+        match &self.weapon_type {
+            WeaponType::Laser { cooldown, .. } => cooldown.as_ref().map_or(true, Timer::finished),
+            WeaponType::Burst {
+                remaining,
+                cooldown,
                 ..
-            } => cooldown.finished(),
-            _ => true,
+            } => *remaining == 0 && cooldown.finished(),
+            WeaponType::Spread { cooldown, .. }
+            | WeaponType::ChargedBeam { cooldown, .. }
+            | WeaponType::HomingMissile { cooldown, .. }
+            | WeaponType::MineLayer { cooldown, .. } => cooldown.finished(),
         }
     }
 
@@ -54,27 +306,170 @@ impl Weapon {
         asset_server: &Res<AssetServer>,
         spawn_transform: Transform,
     ) {
-        match &mut self.0 {
+        match &mut self.weapon_type {
             WeaponType::Laser {
+                projectile,
                 cooldown,
-                projectile_collision_membership,
-                projectile_collision_filter,
-                projectile_damage,
-                ..
             } => {
                 projectile::spawn_laser_projectile(
                     commands,
-                    &asset_server,
+                    asset_server,
                     spawn_transform,
-                    projectile_collision_membership,
-                    projectile_collision_filter,
-                    *projectile_damage,
+                    projectile,
                 );
 
                 if let Some(cooldown) = cooldown {
                     cooldown.reset()
                 }
             }
+            WeaponType::Spread {
+                projectile,
+                count,
+                angle,
+                cooldown,
+            } => {
+                for i in 0..*count {
+                    let offset = if *count > 1 {
+                        -*angle / 2.0 + *angle * i as f32 / (*count - 1) as f32
+                    } else {
+                        0.0
+                    };
+                    let mut transform = spawn_transform;
+                    transform.rotate_z(offset);
+
+                    projectile::spawn_laser_projectile(
+                        commands,
+                        asset_server,
+                        transform,
+                        projectile,
+                    );
+                }
+
+                cooldown.reset();
+            }
+            WeaponType::Burst {
+                projectile,
+                shots,
+                interval,
+                remaining,
+                cooldown,
+            } => {
+                projectile::spawn_laser_projectile(
+                    commands,
+                    asset_server,
+                    spawn_transform,
+                    projectile,
+                );
+
+                // The rest of the burst is fired by `update_weapon`
+                *remaining = shots.saturating_sub(1);
+                interval.reset();
+                cooldown.reset();
+            }
+            WeaponType::ChargedBeam {
+                charge, charging, ..
+            } => {
+                if !*charging {
+                    *charging = true;
+                    charge.reset();
+                } else if charge.finished() {
+                    self.release(commands, asset_server, spawn_transform);
+                }
+                // Charging is not a shot, the beam is fired on release
+                return;
+            }
+            WeaponType::HomingMissile {
+                projectile,
+                turn_rate,
+                range,
+                cooldown,
+            } => {
+                projectile::spawn_missile_projectile(
+                    commands,
+                    asset_server,
+                    spawn_transform,
+                    projectile,
+                    *turn_rate,
+                    *range,
+                );
+
+                cooldown.reset();
+            }
+            WeaponType::MineLayer {
+                projectile,
+                fuse_radius,
+                blast_radius,
+                cooldown,
+            } => {
+                projectile::spawn_mine_projectile(
+                    commands,
+                    spawn_transform,
+                    projectile,
+                    *fuse_radius,
+                    *blast_radius,
+                );
+
+                cooldown.reset();
+            }
+        }
+
+        self.use_shot();
+        send_weapon_fired(commands, &spawn_transform);
+    }
+
+    /// The trigger was let go. Only does something for weapons that charge.
+    pub fn release(
+        &mut self,
+        commands: &mut Commands,
+        asset_server: &Res<AssetServer>,
+        spawn_transform: Transform,
+    ) {
+        if let WeaponType::ChargedBeam {
+            projectile,
+            charge,
+            charging,
+            pierce,
+            cooldown,
+        } = &mut self.weapon_type
+        {
+            if !*charging {
+                return;
+            }
+
+            // A fuller charge hits harder, goes further and pierces more
+            let fraction = charge.percent();
+            let stats = ProjectileStats {
+                damage: (projectile.damage as f32 * (1.0 + 2.0 * fraction)).round() as u32,
+                ..*projectile
+            };
+
+            projectile::spawn_beam_projectile(
+                commands,
+                asset_server,
+                spawn_transform,
+                &stats,
+                1.0 + 2.0 * fraction,
+                (*pierce as f32 * fraction).round() as u32,
+            );
+
+            *charging = false;
+            charge.reset();
+            cooldown.reset();
+            self.use_shot();
+            send_weapon_fired(commands, &spawn_transform);
+        }
+    }
+
+    /// How far the weapon is charged, from 0 to 1. `None` if the weapon does
+    /// not charge or is not charging.
+    pub fn charge(&self) -> Option<f32> {
+        match &self.weapon_type {
+            WeaponType::ChargedBeam {
+                charge,
+                charging: true,
+                ..
+            } => Some(charge.percent()),
+            _ => None,
         }
     }
 
@@ -83,48 +478,235 @@ impl Weapon {
         projectile_speed: f32,
         projectile_time_to_live: Timer,
         cooldown: Option<Timer>,
-        projectile_collision_membership: Group,
-        projectile_collision_filter: Group,
+        faction: Faction,
     ) -> Self {
-        Self(WeaponType::Laser {
-            projectile_damage,
-            projectile_speed,
-            projectile_time_to_live,
+        Self::new(WeaponType::Laser {
+            projectile: ProjectileStats {
+                damage: projectile_damage,
+                speed: projectile_speed,
+                time_to_live: projectile_time_to_live.duration().as_secs_f32(),
+                faction,
+                shooter: None,
+            },
             cooldown,
-            projectile_collision_membership,
-            projectile_collision_filter,
         })
     }
 
-    pub fn simple_laser(
-        projectile_collision_membership: Group,
-        projectile_collision_filter: Group,
-    ) -> Self {
-        Self(WeaponType::Laser {
-            projectile_damage: 1,
-            projectile_speed: 1000.0,
-            projectile_time_to_live: Timer::from_seconds(1.0, TimerMode::Once),
-            cooldown: Some(Timer::from_seconds(0.1, TimerMode::Repeating)),
-            projectile_collision_membership,
-            projectile_collision_filter,
-        })
+    pub fn simple_laser(faction: Faction) -> Self {
+        Self::laser(
+            1,
+            1000.0,
+            Timer::from_seconds(3.0, TimerMode::Once),
+            Some(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            faction,
+        )
     }
 }
 
+/// Cooldowns of zero would fire every frame.
+fn cooldown_secs(cooldown: f32) -> f32 {
+    cooldown.max(0.01)
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Systems
 ////////////////////////////////////////////////////////////////////////////////
 
-fn update_weapon(time: Res<Time>, mut query: Query<&mut Weapon>) {
-    for mut weapon in query.iter_mut() {
-        match &mut weapon.0 {
+/// Projectiles remember who fired them, so hits can be credited.
+fn assign_shooters(mut query: Query<(Entity, &mut Weapon), Added<Weapon>>) {
+    for (entity, mut weapon) in query.iter_mut() {
+        weapon.projectile_mut().shooter = Some(entity);
+    }
+}
+
+fn update_weapon(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut query: Query<(&mut Weapon, &GlobalTransform)>,
+    mut weapon_fired_events: EventWriter<WeaponFired>,
+) {
+    // Global, as weapons can be mounted on a child that turns on its own
+    for (mut weapon, global_transform) in query.iter_mut() {
+        let transform = global_transform.compute_transform();
+        let can_shoot = weapon.has_heat_and_ammo();
+        let Weapon {
+            weapon_type,
+            heat,
+            ammo,
+        } = &mut *weapon;
+
+        if let Some(heat) = heat {
+            heat.update(&time);
+        }
+        if let Some(ammo) = ammo {
+            ammo.update(&time);
+        }
+
+        match weapon_type {
             WeaponType::Laser {
                 cooldown: Some(cooldown),
                 ..
-            } => {
+            }
+            | WeaponType::Spread { cooldown, .. }
+            | WeaponType::HomingMissile { cooldown, .. }
+            | WeaponType::MineLayer { cooldown, .. } => {
                 cooldown.tick(time.delta());
             }
-            _ => {}
+            WeaponType::Laser { cooldown: None, .. } => {}
+            WeaponType::Burst {
+                projectile,
+                interval,
+                remaining,
+                cooldown,
+                ..
+            } => {
+                if *remaining == 0 {
+                    cooldown.tick(time.delta());
+                } else if !can_shoot {
+                    // Overheated or out of ammo, the rest of the burst is lost
+                    *remaining = 0;
+                } else if interval.tick(time.delta()).finished() {
+                    projectile::spawn_laser_projectile(
+                        &mut commands,
+                        &asset_server,
+                        transform,
+                        projectile,
+                    );
+                    *remaining -= 1;
+                    interval.reset();
+                    cooldown.reset();
+                    weapon.use_shot();
+                    weapon_fired_events.send(WeaponFired {
+                        position: transform.translation.truncate(),
+                    });
+                }
+            }
+            WeaponType::ChargedBeam {
+                charge,
+                charging,
+                cooldown,
+                ..
+            } => {
+                if *charging {
+                    charge.tick(time.delta());
+                } else {
+                    cooldown.tick(time.delta());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weapon(archetype: WeaponArchetype) -> Weapon {
+        Weapon::from_archetype(archetype, 10, 0.1, Faction::Player)
+    }
+
+    #[test]
+    fn test_every_archetype_can_fire_when_new() {
+        for archetype in [
+            WeaponArchetype::Laser,
+            WeaponArchetype::Spread,
+            WeaponArchetype::Burst,
+            WeaponArchetype::ChargedBeam,
+            WeaponArchetype::HomingMissile,
+            WeaponArchetype::MineLayer,
+        ] {
+            // Ready once the first cooldown has run out
+            let mut weapon = weapon(archetype);
+            if let WeaponType::Laser {
+                cooldown: Some(cooldown),
+                ..
+            }
+            | WeaponType::Spread { cooldown, .. }
+            | WeaponType::Burst { cooldown, .. }
+            | WeaponType::ChargedBeam { cooldown, .. }
+            | WeaponType::HomingMissile { cooldown, .. }
+            | WeaponType::MineLayer { cooldown, .. } = &mut weapon.weapon_type
+            {
+                cooldown.tick(cooldown.duration());
+            }
+
+            assert!(weapon.can_fire(), "{:?}", archetype);
+        }
+    }
+
+    #[test]
+    fn test_charge_is_only_reported_while_charging() {
+        let mut weapon = weapon(WeaponArchetype::ChargedBeam);
+        assert_eq!(weapon.charge(), None);
+
+        if let WeaponType::ChargedBeam { charging, .. } = &mut weapon.weapon_type {
+            *charging = true;
         }
+        assert_eq!(weapon.charge(), Some(0.0));
+    }
+
+    #[test]
+    fn test_status_reports_heat_and_ammo() {
+        let mut weapon = weapon(WeaponArchetype::Laser)
+            .with_heat(HeatConfig {
+                per_shot: 0.5,
+                dissipation: 0.0,
+                lockout: 1.0,
+            })
+            .with_ammo(AmmoConfig {
+                capacity: 3,
+                reload: 1.0,
+            });
+
+        weapon.use_shot();
+        assert_eq!(weapon.status().heat, Some(0.5));
+        assert_eq!(weapon.status().ammo, Some((2, 3)));
+        assert!(weapon.has_heat_and_ammo());
+
+        weapon.use_shot();
+        assert!(weapon.status().overheated);
+        assert!(!weapon.can_fire());
+    }
+
+    #[test]
+    fn test_every_shot_of_a_burst_is_reported() {
+        use bevy::ecs::system::RunSystemOnce;
+        use bevy::time::TimeUpdateStrategy;
+        use std::time::Duration;
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            ImagePlugin::default(),
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            0.1,
+        )))
+        .add_plugins(WeaponPlugin);
+
+        let burst = weapon(WeaponArchetype::Burst);
+        let WeaponType::Burst { shots, .. } = burst.weapon_type else {
+            unreachable!();
+        };
+        let entity = app.world.spawn((burst, GlobalTransform::default())).id();
+        app.world.run_system_once(
+            move |mut commands: Commands,
+                  asset_server: Res<AssetServer>,
+                  mut query: Query<&mut Weapon>| {
+                let mut weapon = query.get_mut(entity).unwrap();
+                weapon.fire(&mut commands, &asset_server, Transform::default());
+            },
+        );
+
+        let mut fired = 0;
+        for _ in 0..20 {
+            app.update();
+            fired += app.world.resource::<Events<WeaponFired>>().len();
+            app.world.resource_mut::<Events<WeaponFired>>().clear();
+        }
+
+        assert_eq!(fired, shots as usize);
     }
 }
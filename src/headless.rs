@@ -0,0 +1,205 @@
+//! # Headless
+//!
+//! Runs the game without a window or GPU. The scene is simulated with a fixed
+//! virtual timestep for a set number of ticks, after which a summary is printed
+//! and the app exits. Used to run full game sessions on CI and dev boxes
+//! without a display.
+
+use crate::game::{
+    game_entity::Enemy, player::Player, rng::GameRng, score::GameScore, turret::TurretStats,
+    vitality::Health,
+};
+use crate::scene::GameScene;
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use std::fmt;
+use std::time::Duration;
+
+/// How many ticks make up one second of simulated time.
+pub const TICKS_PER_SECOND: f64 = 60.0;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct HeadlessPlugin {
+    pub ticks: u32,
+}
+
+impl HeadlessPlugin {
+    pub fn new(ticks: u32) -> Self {
+        Self { ticks }
+    }
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / TICKS_PER_SECOND,
+        )))
+        .insert_resource(SimulationTicks::new(self.ticks))
+        // Exit before `Last` so systems there can react to the `AppExit` event
+        .add_systems(
+            PostUpdate,
+            (tick_simulation, print_summary.run_if(simulation_is_done)).chain(),
+        );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Resources
+////////////////////////////////////////////////////////////////////////////////
+
+/// Keeps track of how many ticks have been simulated.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulationTicks {
+    elapsed: u32,
+    limit: u32,
+}
+
+impl SimulationTicks {
+    pub fn new(limit: u32) -> Self {
+        Self { elapsed: 0, limit }
+    }
+
+    pub fn elapsed(&self) -> u32 {
+        self.elapsed
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Advance one tick. Returns true once the limit has been reached.
+    pub fn tick(&mut self) -> bool {
+        self.elapsed += 1;
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.limit
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Summary
+////////////////////////////////////////////////////////////////////////////////
+
+/// What the world looked like when the simulation stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadlessSummary {
+    pub scene: GameScene,
+    pub seed: u64,
+    pub ticks: u32,
+    pub simulated_seconds: f32,
+    pub score: u64,
+    pub enemies_alive: usize,
+    pub player_health: Option<u32>,
+    /// Added up over the turrets still standing.
+    pub turret_stats: TurretStats,
+}
+
+impl fmt::Display for HeadlessSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Headless simulation finished")?;
+        writeln!(f, "  scene:         {}", self.scene)?;
+        writeln!(f, "  seed:          {}", self.seed)?;
+        writeln!(f, "  ticks:         {}", self.ticks)?;
+        writeln!(f, "  time:          {:.2}s", self.simulated_seconds)?;
+        writeln!(f, "  score:         {}", self.score)?;
+        writeln!(f, "  enemies alive: {}", self.enemies_alive)?;
+        match self.turret_stats.accuracy() {
+            Some(accuracy) => writeln!(
+                f,
+                "  turret hits:   {}/{} ({:.0}%), {} blocked",
+                self.turret_stats.hits,
+                self.turret_stats.shots_fired,
+                accuracy * 100.0,
+                self.turret_stats.shots_blocked
+            )?,
+            None => writeln!(
+                f,
+                "  turret hits:   no shots fired, {} blocked",
+                self.turret_stats.shots_blocked
+            )?,
+        }
+        match self.player_health {
+            Some(health) => write!(f, "  player health: {}", health),
+            None => write!(f, "  player health: no player"),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn simulation_is_done(ticks: Res<SimulationTicks>) -> bool {
+    ticks.is_done()
+}
+
+fn tick_simulation(
+    mut ticks: ResMut<SimulationTicks>,
+    mut app_exit_event_writer: EventWriter<AppExit>,
+) {
+    if ticks.tick() {
+        app_exit_event_writer.send(AppExit);
+    }
+}
+
+/// What was simulated, for the summary.
+#[derive(SystemParam)]
+struct Simulation<'w> {
+    ticks: Res<'w, SimulationTicks>,
+    time: Res<'w, Time>,
+    scene: Res<'w, State<GameScene>>,
+    game_rng: Res<'w, GameRng>,
+}
+
+fn print_summary(
+    simulation: Simulation,
+    game_score: Res<GameScore>,
+    enemy_query: Query<&Enemy>,
+    player_query: Query<&Health, With<Player>>,
+    turret_query: Query<&TurretStats>,
+) {
+    let summary = HeadlessSummary {
+        scene: *simulation.scene.get(),
+        seed: simulation.game_rng.seed(),
+        ticks: simulation.ticks.elapsed(),
+        simulated_seconds: simulation.time.elapsed_seconds(),
+        score: game_score.total(),
+        enemies_alive: enemy_query.iter().len(),
+        player_health: player_query
+            .get_single()
+            .ok()
+            .map(|health| health.current()),
+        turret_stats: turret_query
+            .iter()
+            .fold(TurretStats::default(), |total, stats| TurretStats {
+                shots_fired: total.shots_fired + stats.shots_fired,
+                hits: total.hits + stats.hits,
+                shots_blocked: total.shots_blocked + stats.shots_blocked,
+            }),
+    };
+
+    println!("{}", summary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulation_ticks_reaches_limit() {
+        let mut ticks = SimulationTicks::new(3);
+
+        assert!(!ticks.tick());
+        assert!(!ticks.tick());
+        assert!(ticks.tick());
+        assert_eq!(ticks.elapsed(), 3);
+        assert!(ticks.is_done());
+    }
+}
@@ -1,50 +1,158 @@
 #![allow(dead_code)]
 
 pub mod app_extension;
+mod audio;
 pub mod cli;
 pub mod file_save;
 pub mod game;
+pub mod headless;
 pub mod misc;
 mod parent_child_no_rotation;
+pub mod paths;
 pub mod prelude;
+pub mod replay;
 pub mod scene;
+mod screenshot;
 pub mod settings;
+pub mod state;
 mod ui;
 pub mod utility_systems;
 
+use audio::GameAudioPlugin;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
+use bevy::input::InputPlugin;
+use bevy::log::LogPlugin;
 use bevy::prelude::*;
+use bevy::window::ExitCondition;
 use bevy_prototype_lyon::prelude::*;
-use game::{score::high_score, GamePlugin};
+use game::{level::Level, score::high_score, GamePlugin};
+use headless::HeadlessPlugin;
 use parent_child_no_rotation::NoRotationPlugin;
-use scene::ScenePlugin;
+use paths::Paths;
+use replay::ReplayPlugin;
+use scene::{GameScene, ScenePlugin};
+use screenshot::ScreenshotPlugin;
 use settings::{Settings, SettingsPlugin};
-use ui::hud::HudPlugin;
-use utility_systems::*;
+use state::{AppState, StatePlugin};
+use std::collections::HashSet;
+use ui::{hud::HudPlugin, menu::MenuPlugin};
 
 // pub fn run(config: Config, settings: Settings) {
-pub fn run(settings: Settings, _high_scores: high_score::HighScores) {
+/// Run the game. Settings changed in game are saved to `settings_file`, if
+/// there is one.
+pub fn run(
+    settings: Settings,
+    settings_file: Option<String>,
+    paths: Paths,
+    high_scores: high_score::HighScores,
+    level: Level,
+    replay: Option<ReplayPlugin>,
+) {
     let mut app = App::new();
 
+    let mut settings_plugin = SettingsPlugin::new(settings.clone());
+    if let Some(path) = settings_file.as_deref() {
+        settings_plugin = settings_plugin.with_file(path);
+    }
+
+    // Without a scene to start in, the game starts in the main menu
+    let (scene, start) = match settings.scene {
+        Some(scene) => (scene, AppState::Playing),
+        None => (GameScene::None, AppState::MainMenu),
+    };
+
     // Defaults
     app.add_plugins(DefaultPlugins);
 
     // Add 2D drawing Plugin
-    app.insert_resource(Msaa::Sample4).add_plugins(ShapePlugin);
+    app.insert_resource(Msaa::from(settings.window.msaa))
+        .add_plugins(ShapePlugin);
 
     // Add Internal Plugins
-    app.add_plugins(GamePlugin {
-        visual_debug: settings.visual_debug.clone(),
-    })
-    .add_plugins(NoRotationPlugin)
-    .add_plugins(HudPlugin)
-    .add_plugins(FrameTimeDiagnosticsPlugin::default())
-    .add_plugins(ScenePlugin {
-        scene: settings.scene.clone(),
-    })
-    .add_plugins(SettingsPlugin::new(settings))
-    // Systems
-    .add_systems(Update, exit_game);
+    app.insert_resource(paths)
+        .insert_resource(high_scores)
+        .add_plugins(GamePlugin {
+            visual_debug: settings.visual_debug.clone(),
+            seed: settings.seed,
+            level,
+        })
+        .add_plugins(NoRotationPlugin)
+        .add_plugins(GameAudioPlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(MenuPlugin)
+        .add_plugins(FrameTimeDiagnosticsPlugin::default())
+        .add_plugins(ScreenshotPlugin)
+        .add_plugins(StatePlugin::new(start))
+        .add_plugins(ScenePlugin { scene: Some(scene) })
+        .add_plugins(settings_plugin);
+
+    if let Some(replay) = replay {
+        app.add_plugins(replay);
+    }
 
     app.run()
 }
+
+/// Run the game without a window or GPU. The scene is simulated for `ticks`
+/// fixed time steps, then a summary is printed and the game exits.
+pub fn run_headless(
+    settings: Settings,
+    paths: Paths,
+    level: Level,
+    ticks: u32,
+    replay: Option<ReplayPlugin>,
+) {
+    headless_app(settings, paths, level, ticks, replay).run()
+}
+
+/// The app `run_headless` runs, for tests to step through frame by frame.
+pub(crate) fn headless_app(
+    settings: Settings,
+    paths: Paths,
+    level: Level,
+    ticks: u32,
+    replay: Option<ReplayPlugin>,
+) -> App {
+    let mut app = App::new();
+
+    // Only what is needed to simulate the game, nothing that renders
+    app.add_plugins((
+        MinimalPlugins,
+        LogPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+        InputPlugin,
+        WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        },
+        AssetPlugin::default(),
+        ImagePlugin::default(),
+    ));
+
+    // Add Internal Plugins
+    app.insert_resource(paths)
+        .add_plugins(GamePlugin {
+            // Visual debugging draws with gizmos, which are not available
+            visual_debug: HashSet::new(),
+            seed: settings.seed,
+            level,
+        })
+        .add_plugins(NoRotationPlugin)
+        // Nothing is played without bevy's AudioPlugin
+        .add_plugins(GameAudioPlugin)
+        .add_plugins(StatePlugin::new(AppState::Playing))
+        .add_plugins(ScenePlugin {
+            scene: settings.scene,
+        })
+        .add_plugins(SettingsPlugin::new(settings))
+        .add_plugins(HeadlessPlugin::new(ticks));
+
+    // After the HeadlessPlugin, the replay only paces frames with a window
+    if let Some(replay) = replay {
+        app.add_plugins(replay);
+    }
+
+    app
+}
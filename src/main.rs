@@ -2,36 +2,119 @@
 
 use clap::Parser;
 use space_game::file_save::FileSave;
+use space_game::game::level::{Level, DEFAULT_LEVEL};
+use space_game::game::score::HighScores;
+use space_game::paths::Paths;
+use space_game::replay::{Replay, ReplayPlugin};
 use space_game::settings::Settings;
+use std::path::Path;
 
 fn main() {
     // Parse Command Line Arguments
     let cli = space_game::cli::Cli::parse();
 
-    let settings_path = cli.settings.as_deref().unwrap_or("settings.toml");
+    if let Some(path) = cli.check_config.as_deref() {
+        match space_game::cli::check_config(path) {
+            Ok(report) => print!("{}", report),
+            Err(err) => {
+                eprintln!("{} is not valid: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let paths = Paths::new(cli.data_dir.as_deref());
+    if let Err(err) = paths.create_dirs() {
+        eprintln!("Error creating the game directories: {}", err);
+    }
+
+    // Older versions kept their files in the working directory
+    match paths.migrate_from(Path::new(".")) {
+        Ok(copied) => {
+            for (old, new) in copied {
+                eprintln!(
+                    "Copied {} to {}, the old file is no longer used.",
+                    old.display(),
+                    new.display()
+                );
+            }
+        }
+        Err(err) => eprintln!("Error copying the files of an older version: {}", err),
+    }
+
+    let default_settings_path = paths.settings_file();
+    let settings_path = cli.settings.as_deref().unwrap_or(&default_settings_path);
 
     // Load Settings
-    let settings = Settings::load_from_file(settings_path).unwrap_or_else(|err| {
-        eprintln!("Error loading settings.toml: {}", err);
-        eprintln!("Using default settings.");
-        Settings::default()
-    });
+    let settings_exist = Path::new(settings_path).exists();
+    let (settings, settings_loaded) = match Settings::load_from_file(settings_path) {
+        Ok(settings) => (settings, true),
+        Err(err) => {
+            eprintln!("Error loading {}: {}", settings_path, err);
+            eprintln!("Using default settings.");
+            (Settings::default(), false)
+        }
+    };
 
-    if cli.settings.is_none() {
-        settings
-            .save_to_file("settings.toml")
-            .unwrap_or_else(|err| {
-                eprintln!("Error saving settings.toml: {}", err);
-            });
+    for conflict in settings.keybindings.conflicts() {
+        eprintln!("Warning: {}.", conflict);
+    }
+
+    // Saving upgrades the file to the current version. A file that did not
+    // load is left alone, so it is not replaced with the defaults.
+    if cli.settings.is_none() && (settings_loaded || !settings_exist) {
+        settings.save_to_file(settings_path).unwrap_or_else(|err| {
+            eprintln!("Error saving {}: {}", settings_path, err);
+        });
     }
 
     // Load High Scores
-    let high_scores = space_game::game::score::HighScores::load_from_file("high_scores.toml")
-        .unwrap_or_else(|err| {
-            eprintln!("Error loading high_scores.toml: {}", err);
-            eprintln!("Using default high scores.");
-            space_game::game::score::HighScores::default()
+    let high_scores_path = paths.high_scores_file();
+    let high_scores = HighScores::load_from_file(&high_scores_path).unwrap_or_else(|err| {
+        eprintln!("Error loading {}: {}", high_scores_path, err);
+        eprintln!("Using default high scores.");
+        HighScores::default()
+    });
+
+    let mut settings = cli.override_settings(&settings);
+    let mut ticks = cli.ticks;
+
+    let replay = if let Some(path) = cli.replay.as_deref() {
+        let path = paths.find_replay(path);
+        let replay = Replay::load_from_file(&path).unwrap_or_else(|err| {
+            eprintln!("Error loading replay {}: {}", path, err);
+            std::process::exit(1);
         });
+        settings.scene = Some(replay.scene);
+        settings.seed = Some(replay.seed);
+        settings.level = replay.level.clone();
+        ticks = replay.ticks;
+        Some(ReplayPlugin::replay(replay))
+    } else {
+        cli.record
+            .as_deref()
+            .map(|name| ReplayPlugin::record(&paths.replay_file(name)))
+    };
+
+    // Load Level
+    let level = match settings.level.as_deref() {
+        Some(name) => Level::load(name).unwrap_or_else(|err| {
+            eprintln!("Error loading level {}:", name);
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }),
+        None => Level::load(DEFAULT_LEVEL).unwrap_or_else(|err| {
+            eprintln!("Error loading level {}: {}", DEFAULT_LEVEL, err);
+            eprintln!("Using the built in default level.");
+            Level::default()
+        }),
+    };
 
-    space_game::run(cli.override_settings(&settings), high_scores);
+    if cli.headless {
+        space_game::run_headless(settings, paths, level, ticks, replay);
+    } else {
+        let settings_file = cli.settings.is_none().then_some(default_settings_path);
+        space_game::run(settings, settings_file, paths, high_scores, level, replay);
+    }
 }
@@ -0,0 +1,297 @@
+//! # Paths
+//!
+//! Where the game keeps its files, so it behaves the same no matter which
+//! directory it is started from.
+//!
+//! - Linux: `$XDG_CONFIG_HOME/space-game` for the settings,
+//!   `$XDG_DATA_HOME/space-game` for high scores, replays and screenshots and
+//!   `$XDG_CACHE_HOME/space-game` for files that can be thrown away.
+//! - macOS: `~/Library/Application Support/space-game`, and
+//!   `~/Library/Caches/space-game` for the cache.
+//! - Windows: `%APPDATA%\space-game`, and `%LOCALAPPDATA%\space-game` for the
+//!   cache.
+//!
+//! On Linux the XDG variables default to `~/.config`, `~/.local/share` and
+//! `~/.cache`. Setting `SPACE_GAME_DATA_DIR`, or passing `--data-dir`, keeps
+//! everything in that one directory instead, with the cache in `cache/`.
+//!
+//! Older versions kept `settings.toml` and `high_scores.toml` in the working
+//! directory, see [`Paths::migrate_from`]. Logs are only written to stdout,
+//! there are no log files.
+
+use bevy::prelude::*;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The environment variable that overrides where all files are kept.
+pub const DATA_DIR_ENV: &str = "SPACE_GAME_DATA_DIR";
+
+const APP_DIR: &str = "space-game";
+
+/// The directories the game reads and writes its files in.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    config: PathBuf,
+    data: PathBuf,
+    cache: PathBuf,
+}
+
+impl Paths {
+    /// Resolve the directories for this platform. `data_dir` is the
+    /// `--data-dir` argument, it takes precedence over `SPACE_GAME_DATA_DIR`.
+    pub fn new(data_dir: Option<&str>) -> Self {
+        Self::resolve(data_dir, |name| std::env::var(name).ok())
+    }
+
+    /// Every file in one directory, the cache in `cache/` inside it.
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Self {
+            config: dir.clone(),
+            data: dir.clone(),
+            cache: dir.join("cache"),
+        }
+    }
+
+    /// Resolve the directories with `env` to read environment variables.
+    fn resolve(data_dir: Option<&str>, env: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| env(name).filter(|value| !value.is_empty());
+
+        if let Some(dir) = data_dir.map(String::from).or_else(|| var(DATA_DIR_ENV)) {
+            return Self::in_dir(dir);
+        }
+
+        let home = var("HOME").or_else(|| var("USERPROFILE"));
+        let Some(home) = home.map(PathBuf::from) else {
+            // Nowhere better to go
+            return Self::in_dir(".");
+        };
+
+        if cfg!(target_os = "windows") {
+            let roaming = var("APPDATA")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join("AppData").join("Roaming"));
+            let local = var("LOCALAPPDATA")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join("AppData").join("Local"));
+            Self {
+                config: roaming.join(APP_DIR),
+                data: roaming.join(APP_DIR),
+                cache: local.join(APP_DIR),
+            }
+        } else if cfg!(target_os = "macos") {
+            let library = home.join("Library");
+            Self {
+                config: library.join("Application Support").join(APP_DIR),
+                data: library.join("Application Support").join(APP_DIR),
+                cache: library.join("Caches").join(APP_DIR),
+            }
+        } else {
+            let xdg = |name: &str, default: &str| {
+                var(name)
+                    .map(PathBuf::from)
+                    .filter(|path| path.is_absolute())
+                    .unwrap_or_else(|| home.join(default))
+                    .join(APP_DIR)
+            };
+            Self {
+                config: xdg("XDG_CONFIG_HOME", ".config"),
+                data: xdg("XDG_DATA_HOME", ".local/share"),
+                cache: xdg("XDG_CACHE_HOME", ".cache"),
+            }
+        }
+    }
+
+    /// Create all the directories that files are saved in.
+    pub fn create_dirs(&self) -> io::Result<()> {
+        for dir in [
+            &self.config,
+            &self.data,
+            &self.cache,
+            &self.replays_dir(),
+            &self.screenshots_dir(),
+        ] {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    pub fn config_dir(&self) -> &Path {
+        &self.config
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache
+    }
+
+    pub fn settings_file(&self) -> String {
+        file(&self.config, "settings.toml")
+    }
+
+    pub fn high_scores_file(&self) -> String {
+        file(&self.data, "high_scores.toml")
+    }
+
+    pub fn replays_dir(&self) -> PathBuf {
+        self.data.join("replays")
+    }
+
+    pub fn screenshots_dir(&self) -> PathBuf {
+        self.data.join("screenshots")
+    }
+
+    /// Where to record a replay. A bare file name goes in the replays
+    /// directory, a path with a directory is used as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use space_game::paths::Paths;
+    ///
+    /// let paths = Paths::in_dir("/games");
+    ///
+    /// assert_eq!(paths.replay_file("run.replay"), "/games/replays/run.replay");
+    /// assert_eq!(paths.replay_file("./run.replay"), "./run.replay");
+    /// ```
+    pub fn replay_file(&self, name: &str) -> String {
+        let path = Path::new(name);
+        let is_bare_name = path
+            .parent()
+            .map_or(true, |parent| parent.as_os_str().is_empty());
+        if is_bare_name && !path.is_absolute() {
+            file(&self.replays_dir(), name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Copy the settings and high scores that older versions kept in
+    /// `old_dir`, unless there already are files in their new place. Returns
+    /// the files that were copied, as (old, new) paths. The old files are left
+    /// alone.
+    pub fn migrate_from(&self, old_dir: &Path) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+        let mut copied = Vec::new();
+        for new in [self.settings_file(), self.high_scores_file()] {
+            let new = PathBuf::from(new);
+            let Some(name) = new.file_name() else {
+                continue;
+            };
+            let old = old_dir.join(name);
+            if !old.is_file() || new.exists() || same_file(&old, &new) {
+                continue;
+            }
+            std::fs::copy(&old, &new)?;
+            copied.push((old, new));
+        }
+        Ok(copied)
+    }
+
+    /// Find a replay to play back: the path as is if it exists, otherwise the
+    /// file in the replays directory.
+    pub fn find_replay(&self, name: &str) -> String {
+        if Path::new(name).exists() {
+            name.to_string()
+        } else {
+            self.replay_file(name)
+        }
+    }
+}
+
+fn file(dir: &Path, name: &str) -> String {
+    dir.join(name).to_string_lossy().into_owned()
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn resolve(data_dir: Option<&str>, vars: &[(&str, &str)]) -> Paths {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Paths::resolve(data_dir, |name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_data_dir_argument_overrides_env_var() {
+        let vars = [(DATA_DIR_ENV, "/env"), ("HOME", "/home/pilot")];
+
+        assert_eq!(resolve(None, &vars), Paths::in_dir("/env"));
+        assert_eq!(resolve(Some("/arg"), &vars), Paths::in_dir("/arg"));
+        assert_eq!(
+            Paths::in_dir("/arg").settings_file(),
+            "/arg/settings.toml".to_string()
+        );
+    }
+
+    #[test]
+    fn test_old_files_are_copied_once() {
+        let dir = std::env::temp_dir().join(format!("space-game-paths-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let old = dir.join("old");
+        let paths = Paths::in_dir(dir.join("new"));
+        std::fs::create_dir_all(&old).unwrap();
+        paths.create_dirs().unwrap();
+        std::fs::write(old.join("settings.toml"), "old").unwrap();
+
+        let copied = paths.migrate_from(&old).unwrap();
+        assert_eq!(
+            copied,
+            vec![(old.join("settings.toml"), dir.join("new/settings.toml"))]
+        );
+        assert_eq!(
+            std::fs::read_to_string(paths.settings_file()).unwrap(),
+            "old"
+        );
+
+        // The new file is kept from now on
+        std::fs::write(paths.settings_file(), "new").unwrap();
+        assert!(paths.migrate_from(&old).unwrap().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(paths.settings_file()).unwrap(),
+            "new"
+        );
+
+        // Nothing to do when the old and new directory are the same
+        assert!(Paths::in_dir(&old).migrate_from(&old).unwrap().is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_xdg_directories() {
+        let paths = resolve(
+            None,
+            &[
+                ("HOME", "/home/pilot"),
+                ("XDG_CONFIG_HOME", "/xdg/config"),
+                // Relative XDG paths are invalid and ignored
+                ("XDG_DATA_HOME", "relative"),
+            ],
+        );
+
+        assert_eq!(paths.config_dir(), Path::new("/xdg/config/space-game"));
+        assert_eq!(
+            paths.data_dir(),
+            Path::new("/home/pilot/.local/share/space-game")
+        );
+        assert_eq!(
+            paths.cache_dir(),
+            Path::new("/home/pilot/.cache/space-game")
+        );
+    }
+}
@@ -0,0 +1,680 @@
+//! # Replay
+//!
+//! Records the player's actions for every tick of a session and plays them
+//! back through the same systems to reproduce the run. Together with the seed
+//! and the scene this is enough to recreate a session exactly.
+//!
+//! A recording starts on the frame a scene is entered, so a game started from
+//! the main menu is recorded from the press of Play. It stops when the scene
+//! is left, a replay covers a single run. Playing back starts in the recorded
+//! scene and counts ticks the same way.
+//!
+//! Both recording and replaying run with the same fixed timestep as headless
+//! mode, one tick per frame. With a window the frames are paced to
+//! `TICKS_PER_SECOND`, so the game runs at its normal speed on any monitor. A
+//! machine that can not keep up plays slower, but still in sync. Every
+//! `checksum_interval` ticks a checksum of the `GameScore` and the player
+//! `Transform` is stored. When replaying the checksums are compared and the
+//! first tick where they differ is reported.
+//!
+//! Only `PlayerShipAction`s are recorded. Debug keys, like switching scenes,
+//! and pausing are not part of a replay.
+
+use crate::file_save::{self, FileSave};
+use crate::game::{
+    level::Level,
+    player::{AdjustPlayerActions, Player, PlayerShipAction},
+    rng::GameRng,
+    score::GameScore,
+};
+use crate::headless::{HeadlessPlugin, TICKS_PER_SECOND};
+use crate::scene::GameScene;
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use leafwing_input_manager::axislike::DualAxisData;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::{Duration, Instant};
+use toml::from_str;
+
+/// The version of the replay file format. Bump it whenever the format or the
+/// simulation changes in a way that makes old replays play out differently.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// How many ticks there are between two checksums by default.
+pub const DEFAULT_CHECKSUM_INTERVAL: u32 = 60;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub enum ReplayPlugin {
+    /// Record the session and write it to `path` when the game exits.
+    Record { path: String },
+    /// Play back a recorded session. Exits when the replay is over.
+    Replay(Replay),
+}
+
+impl ReplayPlugin {
+    pub fn record(path: &str) -> Self {
+        Self::Record {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn replay(replay: Replay) -> Self {
+        Self::Replay(replay)
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        // Headless already steps one tick per frame, as fast as it can
+        if !app.is_plugin_added::<HeadlessPlugin>() {
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(tick_duration()))
+                .init_resource::<FramePacing>()
+                .add_systems(Last, pace_frames);
+        }
+
+        match self {
+            ReplayPlugin::Record { path } => {
+                app.insert_resource(Recorder::new(path))
+                    .add_systems(
+                        PreUpdate,
+                        record_actions
+                            .after(AdjustPlayerActions)
+                            .run_if(is_recording),
+                    )
+                    .add_systems(
+                        Last,
+                        (
+                            update_recording_stage,
+                            record_checksum.run_if(is_recording),
+                            save_recording,
+                        )
+                            .chain(),
+                    );
+            }
+            ReplayPlugin::Replay(replay) => {
+                app.insert_resource(Playback::new(replay.clone()))
+                    .add_systems(
+                        PreUpdate,
+                        replay_actions
+                            .after(AdjustPlayerActions)
+                            .run_if(playback_started),
+                    )
+                    .add_systems(
+                        Last,
+                        (start_playback, verify_checksum.run_if(playback_started)).chain(),
+                    );
+            }
+        }
+    }
+}
+
+fn tick_duration() -> Duration {
+    Duration::from_secs_f64(1.0 / TICKS_PER_SECOND)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Frame Pacing
+////////////////////////////////////////////////////////////////////////////////
+
+/// When the next frame may start, so every tick takes as long in real time as
+/// it does in game time.
+#[derive(Resource, Debug)]
+struct FramePacing {
+    next_frame: Instant,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        Self {
+            next_frame: Instant::now(),
+        }
+    }
+}
+
+fn pace_frames(mut pacing: ResMut<FramePacing>) {
+    let now = Instant::now();
+    if let Some(wait) = pacing.next_frame.checked_duration_since(now) {
+        std::thread::sleep(wait);
+    }
+    // Frames that took too long are not made up for
+    pacing.next_frame = pacing.next_frame.max(now) + tick_duration();
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Replay File
+////////////////////////////////////////////////////////////////////////////////
+
+/// A recorded session.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub version: u32,
+    /// Stored as a string since TOML integers can not hold every `u64`.
+    #[serde(with = "seed_string")]
+    pub seed: u64,
+    pub scene: GameScene,
+    /// The level the main game was played on, if not the default one.
+    #[serde(default)]
+    pub level: Option<String>,
+    /// The number of ticks that were recorded.
+    pub ticks: u32,
+    pub checksum_interval: u32,
+    /// The actions that were pressed, only stored for the ticks where they
+    /// changed.
+    pub inputs: Vec<InputFrame>,
+    pub checksums: Vec<Checksum>,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed: 0,
+            scene: GameScene::default(),
+            level: None,
+            ticks: 0,
+            checksum_interval: DEFAULT_CHECKSUM_INTERVAL,
+            inputs: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+}
+
+impl FileSave for Replay {
+    type Item = Replay;
+    fn load_from_file(path: &str) -> Result<Replay, Box<dyn Error>> {
+        let contents = file_save::load_from_file(path)?;
+        let replay: Replay = from_str(&contents)?;
+
+        if replay.version != REPLAY_VERSION {
+            return Err(format!(
+                "unsupported replay version {} (expected {})",
+                replay.version, REPLAY_VERSION
+            )
+            .into());
+        }
+
+        Ok(replay)
+    }
+
+    fn save_to_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let contents = toml::to_string(&self)?;
+        file_save::save_to_file(path, &contents)?;
+        Ok(())
+    }
+}
+
+/// The actions that are pressed from `tick` until the next frame.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct InputFrame {
+    pub tick: u32,
+    pub actions: Vec<RecordedAction>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct RecordedAction {
+    pub action: PlayerShipAction,
+    pub value: f32,
+    pub axis_pair: Option<[f32; 2]>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checksum {
+    pub tick: u32,
+    pub value: u32,
+}
+
+mod seed_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&seed.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let seed = String::deserialize(deserializer)?;
+        seed.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Resources
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Resource, Debug)]
+struct Recorder {
+    path: String,
+    replay: Replay,
+    current: Option<Vec<RecordedAction>>,
+    stage: RecordingStage,
+}
+
+/// Where the recorder is in the session, see the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordingStage {
+    /// No scene was entered yet, for example while in the main menu.
+    Waiting,
+    Recording,
+    /// The recorded scene was left.
+    Stopped,
+}
+
+impl Recorder {
+    fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            replay: Replay::default(),
+            current: None,
+            stage: RecordingStage::Waiting,
+        }
+    }
+
+    /// Record the pressed actions of the current tick.
+    fn record(&mut self, actions: Vec<RecordedAction>) {
+        if self.current.as_ref() != Some(&actions) {
+            self.replay.inputs.push(InputFrame {
+                tick: self.replay.ticks,
+                actions: actions.clone(),
+            });
+            self.current = Some(actions);
+        }
+    }
+}
+
+/// Plays back a replay, one tick at a time.
+#[derive(Resource, Debug)]
+struct Playback {
+    replay: Replay,
+    tick: u32,
+    next_input: usize,
+    current: Vec<RecordedAction>,
+    next_checksum: usize,
+    first_mismatch: Option<u32>,
+    /// Ticks are counted from the frame the scene is entered, like when
+    /// recording.
+    started: bool,
+}
+
+impl Playback {
+    fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            tick: 0,
+            next_input: 0,
+            current: Vec::new(),
+            next_checksum: 0,
+            first_mismatch: None,
+            started: false,
+        }
+    }
+
+    /// The actions to press this tick.
+    fn actions(&mut self) -> &[RecordedAction] {
+        while let Some(frame) = self.replay.inputs.get(self.next_input) {
+            if frame.tick > self.tick {
+                break;
+            }
+            self.current = frame.actions.clone();
+            self.next_input += 1;
+        }
+        &self.current
+    }
+
+    /// Compare the checksum of this tick against the recording. Returns the
+    /// expected checksum if it differs.
+    fn verify(&mut self, value: u32) -> Option<u32> {
+        let expected = self
+            .replay
+            .checksums
+            .get(self.next_checksum)
+            .filter(|checksum| checksum.tick == self.tick)?;
+        self.next_checksum += 1;
+
+        if expected.value != value && self.first_mismatch.is_none() {
+            self.first_mismatch = Some(self.tick);
+            return Some(expected.value);
+        }
+        None
+    }
+
+    fn is_done(&self) -> bool {
+        self.tick >= self.replay.ticks
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn is_recording(recorder: Res<Recorder>) -> bool {
+    recorder.stage == RecordingStage::Recording
+}
+
+/// Start recording when a scene is entered and stop when it is left.
+fn update_recording_stage(mut recorder: ResMut<Recorder>, scene: Res<State<GameScene>>) {
+    let scene = *scene.get();
+    match recorder.stage {
+        RecordingStage::Waiting if scene != GameScene::None => {
+            recorder.replay.scene = scene;
+            recorder.stage = RecordingStage::Recording;
+        }
+        RecordingStage::Recording if scene != recorder.replay.scene => {
+            println!(
+                "Stopped recording after {} ticks, the scene was left",
+                recorder.replay.ticks
+            );
+            recorder.stage = RecordingStage::Stopped;
+        }
+        _ => (),
+    }
+}
+
+fn record_actions(
+    mut recorder: ResMut<Recorder>,
+    query: Query<&ActionState<PlayerShipAction>, With<Player>>,
+) {
+    let actions = query.get_single().map(pressed_actions).unwrap_or_default();
+    recorder.record(actions);
+}
+
+fn record_checksum(
+    mut recorder: ResMut<Recorder>,
+    game_score: Res<GameScore>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    let tick = recorder.replay.ticks;
+    if tick % recorder.replay.checksum_interval == 0 {
+        let value = checksum(&game_score, player_query.get_single().ok());
+        recorder.replay.checksums.push(Checksum { tick, value });
+    }
+    recorder.replay.ticks += 1;
+}
+
+fn save_recording(
+    mut recorder: ResMut<Recorder>,
+    game_rng: Res<GameRng>,
+    level: Res<Level>,
+    mut app_exit_events: EventReader<AppExit>,
+) {
+    if app_exit_events.read().next().is_none() {
+        return;
+    }
+
+    if recorder.stage == RecordingStage::Waiting {
+        println!("No scene was played, no replay saved");
+        return;
+    }
+
+    recorder.replay.seed = game_rng.seed();
+    recorder.replay.level = (*level != Level::default()).then(|| level.name.clone());
+    match recorder.replay.save_to_file(&recorder.path) {
+        Ok(()) => println!(
+            "Saved replay of {} ticks to {}",
+            recorder.replay.ticks, recorder.path
+        ),
+        Err(err) => eprintln!("Error saving replay to {}: {}", recorder.path, err),
+    }
+}
+
+fn playback_started(playback: Res<Playback>) -> bool {
+    playback.started
+}
+
+fn start_playback(mut playback: ResMut<Playback>, scene: Res<State<GameScene>>) {
+    if !playback.started && *scene.get() != GameScene::None {
+        playback.started = true;
+    }
+}
+
+fn replay_actions(
+    mut playback: ResMut<Playback>,
+    mut query: Query<&mut ActionState<PlayerShipAction>, With<Player>>,
+) {
+    let actions = playback.actions();
+
+    for mut action_state in query.iter_mut() {
+        action_state.release_all();
+        for recorded in actions {
+            action_state.press(recorded.action);
+            let data = action_state.action_data_mut(recorded.action);
+            data.value = recorded.value;
+            data.axis_pair = recorded.axis_pair.map(|[x, y]| DualAxisData::new(x, y));
+        }
+    }
+}
+
+fn verify_checksum(
+    mut playback: ResMut<Playback>,
+    game_score: Res<GameScore>,
+    player_query: Query<&Transform, With<Player>>,
+    mut app_exit_event_writer: EventWriter<AppExit>,
+) {
+    if playback.is_done() {
+        return;
+    }
+
+    let value = checksum(&game_score, player_query.get_single().ok());
+    if let Some(expected) = playback.verify(value) {
+        println!(
+            "Replay diverged at tick {}: expected checksum {:08x}, got {:08x}",
+            playback.tick, expected, value
+        );
+    }
+
+    playback.tick += 1;
+
+    if playback.is_done() {
+        match playback.first_mismatch {
+            Some(tick) => println!(
+                "Replay finished after {} ticks, first divergence at tick {}",
+                playback.tick, tick
+            ),
+            None => println!(
+                "Replay finished after {} ticks, no divergence",
+                playback.tick
+            ),
+        }
+        app_exit_event_writer.send(AppExit);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Helpers
+////////////////////////////////////////////////////////////////////////////////
+
+fn pressed_actions(action_state: &ActionState<PlayerShipAction>) -> Vec<RecordedAction> {
+    action_state
+        .get_pressed()
+        .into_iter()
+        .map(|action| RecordedAction {
+            action,
+            value: action_state.value(action),
+            axis_pair: action_state
+                .axis_pair(action)
+                .map(|axis_pair| [axis_pair.x(), axis_pair.y()]),
+        })
+        .collect()
+}
+
+/// FNV-1a hash of the score and the player transform.
+fn checksum(game_score: &GameScore, player_transform: Option<&Transform>) -> u32 {
+    const FNV_OFFSET_BASIS: u32 = 0x811c_9dc5;
+    const FNV_PRIME: u32 = 0x0100_0193;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&game_score.total().to_le_bytes());
+    if let Some(transform) = player_transform {
+        for value in transform
+            .translation
+            .to_array()
+            .into_iter()
+            .chain(transform.rotation.to_array())
+        {
+            bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+    }
+
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{paths::Paths, settings::Settings, state::RestartGame};
+
+    fn action(action: PlayerShipAction) -> RecordedAction {
+        RecordedAction {
+            action,
+            value: 1.0,
+            axis_pair: None,
+        }
+    }
+
+    #[test]
+    fn test_recorder_only_stores_changes() {
+        let mut recorder = Recorder::new("test.replay");
+        let forward = vec![action(PlayerShipAction::ThrottleForward)];
+
+        for actions in [vec![], forward.clone(), forward.clone(), vec![]] {
+            recorder.record(actions);
+            recorder.replay.ticks += 1;
+        }
+
+        let ticks: Vec<u32> = recorder.replay.inputs.iter().map(|f| f.tick).collect();
+        assert_eq!(ticks, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_player_holds_actions_between_frames() {
+        let forward = vec![action(PlayerShipAction::ThrottleForward)];
+        let mut playback = Playback::new(Replay {
+            ticks: 4,
+            inputs: vec![
+                InputFrame {
+                    tick: 1,
+                    actions: forward.clone(),
+                },
+                InputFrame {
+                    tick: 3,
+                    actions: vec![],
+                },
+            ],
+            ..Default::default()
+        });
+
+        let mut pressed = Vec::new();
+        while !playback.is_done() {
+            pressed.push(playback.actions().len());
+            playback.tick += 1;
+        }
+
+        assert_eq!(pressed, vec![0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_player_reports_first_mismatch() {
+        let mut playback = Playback::new(Replay {
+            ticks: 3,
+            checksum_interval: 1,
+            checksums: (0..3).map(|tick| Checksum { tick, value: 7 }).collect(),
+            ..Default::default()
+        });
+
+        assert_eq!(playback.verify(7), None);
+        playback.tick += 1;
+        assert_eq!(playback.verify(8), Some(7));
+        playback.tick += 1;
+        // Only the first divergence is reported
+        assert_eq!(playback.verify(8), None);
+        assert_eq!(playback.first_mismatch, Some(1));
+    }
+
+    #[test]
+    fn test_replay_round_trips_through_toml() {
+        let replay = Replay {
+            seed: u64::MAX,
+            ticks: 2,
+            inputs: vec![InputFrame {
+                tick: 0,
+                actions: vec![RecordedAction {
+                    action: PlayerShipAction::RotateShip,
+                    value: 0.5,
+                    axis_pair: Some([0.5, -0.25]),
+                }],
+            }],
+            checksums: vec![Checksum { tick: 0, value: 42 }],
+            ..Default::default()
+        };
+
+        let contents = toml::to_string(&replay).unwrap();
+        let loaded: Replay = from_str(&contents).unwrap();
+
+        assert_eq!(loaded, replay);
+    }
+
+    #[test]
+    fn test_game_started_from_the_menu_plays_back() {
+        let dir = std::env::temp_dir().join(format!("space-game-replay-{}", std::process::id()));
+        let path = dir.join("menu.replay").to_string_lossy().into_owned();
+        let paths = Paths::in_dir(&dir);
+        paths.create_dirs().unwrap();
+        let settings = Settings {
+            scene: Some(GameScene::None),
+            seed: Some(7),
+            ..Default::default()
+        };
+
+        let mut app = crate::headless_app(
+            settings,
+            paths.clone(),
+            Level::default(),
+            u32::MAX,
+            Some(ReplayPlugin::record(&path)),
+        );
+        // Wait in the menu, then press Play and fly forward
+        for _ in 0..30 {
+            app.update();
+        }
+        app.world.send_event(RestartGame);
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+        for _ in 0..120 {
+            app.update();
+        }
+        app.world.send_event(AppExit);
+        app.update();
+
+        let replay = Replay::load_from_file(&path).unwrap();
+        assert_eq!(replay.scene, GameScene::MainGame);
+        // The frames in the menu are not part of the replay
+        assert!(replay.ticks <= 120, "{} ticks", replay.ticks);
+        assert!(replay.inputs.iter().any(|frame| !frame.actions.is_empty()));
+
+        let settings = Settings {
+            scene: Some(replay.scene),
+            seed: Some(replay.seed),
+            ..Default::default()
+        };
+        let ticks = replay.ticks;
+        let mut app = crate::headless_app(
+            settings,
+            paths,
+            Level::default(),
+            ticks,
+            Some(ReplayPlugin::replay(replay)),
+        );
+        for _ in 0..ticks {
+            app.update();
+        }
+
+        let playback = app.world.resource::<Playback>();
+        assert!(playback.is_done());
+        assert_eq!(playback.first_mismatch, None);
+    }
+}
@@ -1,10 +1,11 @@
-//! # State
+//! # Scene
 //!
-//! The state of the game is managed by the `StatePlugin` and the `AppState` enum.
-//! Each state is a different "mode" of the game. For example, the `MainMenu` state
-//! is the state that is active when the game is first started. The `InGame` state
-//! is the state that is active when the player is playing the game. The `Paused`
-//! state is the state that is active when the player pauses the game.
+//! A scene decides what is spawned in the world: the main game or one of the
+//! debug scenes. Where the player is in the flow of the game (menus, playing,
+//! paused) is kept separately in `AppState`, see `crate::state`.
+//!
+//! Everything with a rigid body that is spawned while a scene is active is
+//! labeled with `SceneEntityLabel` and despawned when the scene is left.
 mod assets;
 mod boid;
 mod enemy_ship_ai;
@@ -16,11 +17,13 @@ mod turret;
 mod turret_performance;
 
 use crate::app_extension::*;
+use crate::settings::keybindings::{Binding, Bindings, Keybindings, Rebindable};
+use crate::state::AppState;
 use bevy::prelude::*;
+use bevy_rapier2d::prelude::RigidBody;
 use clap::ValueEnum;
 use leafwing_input_manager::{
-    input_map::InputMap, plugin::InputManagerPlugin, prelude::ActionState, user_input::InputKind,
-    Actionlike, InputManagerBundle,
+    plugin::InputManagerPlugin, prelude::ActionState, Actionlike, InputManagerBundle,
 };
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -59,7 +62,18 @@ impl Plugin for ScenePlugin {
                 enemy_ship_ai::EnemyShipAIScenePlugin,
             ))
             .add_systems(Startup, create)
-            .add_systems(Update, update_scene);
+            .add_systems(
+                Update,
+                (
+                    update_scene.run_if(in_state(AppState::Playing)),
+                    reload_scene.run_if(in_state(GameScene::None)),
+                ),
+            )
+            .add_systems(PostUpdate, label_scene_entities);
+
+        for scene in GameScene::value_variants() {
+            app.add_systems(OnExit(*scene), SceneEntityLabel::despawn);
+        }
     }
 }
 
@@ -123,13 +137,43 @@ impl fmt::Display for GameScene {
 fn create(mut commands: Commands) {
     commands.spawn(InputManagerBundle {
         action_state: ActionState::default(),
-        input_map: create_input_map(),
+        input_map: GameScene::default_bindings().input_map(),
     });
 }
 
+/// A scene to enter again after it has been left, see `Reload::reload`.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Default, Resource)]
 pub struct Reload(Option<GameScene>);
 
+impl Reload {
+    /// Enter `scene` on the next frame. The current scene has to be left by
+    /// going to `GameScene::None` first, a state can not be entered again
+    /// without leaving it.
+    pub fn reload(&mut self, scene: GameScene) {
+        self.0 = Some(scene);
+    }
+
+    /// The scene waiting to be entered again.
+    pub fn scene(&self) -> Option<GameScene> {
+        self.0
+    }
+}
+
+fn reload_scene(mut reload: ResMut<Reload>, mut next_scene: ResMut<NextState<GameScene>>) {
+    if let Some(scene) = reload.0.take() {
+        next_scene.set(scene);
+    }
+}
+
+fn label_scene_entities(
+    mut commands: Commands,
+    query: Query<Entity, (Added<RigidBody>, Without<SceneEntityLabel>)>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert(SceneEntityLabel);
+    }
+}
+
 fn update_scene(
     mut next_scene: ResMut<NextState<GameScene>>,
     input_query: Query<&ActionState<GameScene>>,
@@ -141,28 +185,26 @@ fn update_scene(
     }
 }
 
-fn create_input_map() -> InputMap<GameScene> {
-    let mut input_map: InputMap<GameScene> = InputMap::default();
-
-    // Add Camera inputs
-    input_map.insert_multiple(vec![
-        (InputKind::Keyboard(KeyCode::Key0), GameScene::None),
-        (InputKind::Keyboard(KeyCode::Key1), GameScene::MainGame),
-        (
-            InputKind::Keyboard(KeyCode::Key2),
-            GameScene::TurretPerformance,
-        ),
-        (InputKind::Keyboard(KeyCode::Key3), GameScene::PlayerDeath),
-        (InputKind::Keyboard(KeyCode::Key4), GameScene::EnemyShipAI),
-        (
-            InputKind::Keyboard(KeyCode::Key5),
-            GameScene::PlayerMovement,
-        ),
-        (InputKind::Keyboard(KeyCode::Key6), GameScene::Turret),
-        (InputKind::Keyboard(KeyCode::Key7), GameScene::KamikazeDrone),
-        (InputKind::Keyboard(KeyCode::Key8), GameScene::Boid),
-        (InputKind::Keyboard(KeyCode::Key9), GameScene::Assets),
-    ]);
-
-    input_map
+impl Rebindable for GameScene {
+    fn default_bindings() -> Bindings<Self> {
+        Bindings::new([
+            (GameScene::None, vec![Binding::Key(KeyCode::Key0)]),
+            (GameScene::MainGame, vec![Binding::Key(KeyCode::Key1)]),
+            (
+                GameScene::TurretPerformance,
+                vec![Binding::Key(KeyCode::Key2)],
+            ),
+            (GameScene::PlayerDeath, vec![Binding::Key(KeyCode::Key3)]),
+            (GameScene::EnemyShipAI, vec![Binding::Key(KeyCode::Key4)]),
+            (GameScene::PlayerMovement, vec![Binding::Key(KeyCode::Key5)]),
+            (GameScene::Turret, vec![Binding::Key(KeyCode::Key6)]),
+            (GameScene::KamikazeDrone, vec![Binding::Key(KeyCode::Key7)]),
+            (GameScene::Boid, vec![Binding::Key(KeyCode::Key8)]),
+            (GameScene::Assets, vec![Binding::Key(KeyCode::Key9)]),
+        ])
+    }
+
+    fn bindings(keybindings: &Keybindings) -> &Bindings<Self> {
+        &keybindings.scenes
+    }
 }
@@ -1,7 +1,12 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::prelude::*;
+use rand::Rng;
 
 use super::GameScene;
-use crate::game::{arena, background, boids};
+use crate::game::{
+    arena, background, boids,
+    rng::{GameRng, RngStream},
+    screen_bounds::Viewport,
+};
 use crate::utility_systems::cleanup;
 
 pub struct BoidScenePlugin;
@@ -24,26 +29,30 @@ impl Plugin for BoidScenePlugin {
 fn spawn(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    viewport: Viewport,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    let window = window_query.single();
-
     // Spawn the arena and player
     let arena = arena::Arena::new(1000.0, 200.0);
-    arena.spawn_asteroid_bounds(&mut commands, &asset_server);
+    arena.spawn_asteroid_bounds(
+        &mut commands,
+        &asset_server,
+        game_rng.stream(RngStream::Arena),
+    );
     // arena.spawn_random_asteroids(&mut commands, &asset_db, &asset_server, 50);
     // Spawn 100 boids at random locations
     let boid_count = 100;
+    let rng = game_rng.stream(RngStream::Scene);
 
     for _ in 0..boid_count {
         boids::spawn(
             &mut commands,
             &asset_server,
             Vec2::new(
-                (rand::random::<f32>() - 0.5) * window.width(),
-                (rand::random::<f32>() - 0.5) * window.height(),
+                (rng.gen::<f32>() - 0.5) * viewport.width(),
+                (rng.gen::<f32>() - 0.5) * viewport.height(),
             ),
-            rand::random::<f32>() * std::f32::consts::PI * 2.0,
+            rng.gen::<f32>() * std::f32::consts::PI * 2.0,
         );
     }
 
@@ -1,7 +1,16 @@
 use bevy::prelude::*;
+use std::f32::consts::PI;
 
 use super::{GameScene, SceneEntityLabel};
-use crate::game::{arena, background, enemy, movement::FollowEntityMovement, player_camera};
+use crate::game::{
+    arena, background,
+    boids::Formation,
+    enemy,
+    faction::Faction,
+    movement::FollowEntityMovement,
+    player_camera,
+    rng::{GameRng, RngStream},
+};
 
 pub struct EnemyShipAIScenePlugin;
 
@@ -22,15 +31,48 @@ impl Plugin for EnemyShipAIScenePlugin {
     }
 }
 
-fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn(mut commands: Commands, asset_server: Res<AssetServer>, mut game_rng: ResMut<GameRng>) {
     // Spawn the arena and player
     let arena = arena::Arena::new(1000.0, 200.0);
-    arena.spawn_asteroid_bounds(&mut commands, &asset_server);
-    arena.spawn_random_asteroids(&mut commands, &asset_server, 50);
+    let rng = game_rng.stream(RngStream::Arena);
+    arena.spawn_asteroid_bounds(&mut commands, &asset_server, rng);
+    arena.spawn_random_asteroids(&mut commands, &asset_server, rng, 50);
     // arena.spawn_player(&mut commands, &asset_db, &asset_server);
 
-    // Spawn an enemy ship
-    let enemy_entity = enemy::spawn(&mut commands, &asset_server, Vec2::new(0.0, 0.0), 0.0);
+    // Two squads of hostile factions, to watch them patrol, fight and flee
+    let rng = game_rng.stream(RngStream::EnemyAI);
+    let mut leaders = Vec::new();
+    for (faction, x, rotation, formation) in [
+        (
+            Faction::Enemy1,
+            -250.0,
+            -PI / 2.0,
+            Formation::V { spacing: 130.0 },
+        ),
+        (
+            Faction::Enemy2,
+            250.0,
+            PI / 2.0,
+            Formation::Line { spacing: 130.0 },
+        ),
+    ] {
+        let squad: Vec<Entity> = (0..3)
+            .map(|i| {
+                enemy::spawn(
+                    &mut commands,
+                    &asset_server,
+                    rng,
+                    Vec2::new(x, (i as f32 - 1.0) * 150.0),
+                    rotation,
+                    faction,
+                )
+            })
+            .collect();
+
+        enemy::form_squad(&mut commands, &squad, formation);
+        leaders.push(squad[0]);
+    }
+    let enemy_entity = leaders[0];
 
     commands
         .spawn(Camera2dBundle::default())
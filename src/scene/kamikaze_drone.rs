@@ -3,9 +3,12 @@ use bevy::prelude::*;
 use super::GameScene;
 use crate::{
     game::{
-        arena, background, kamikaze_drone,
+        arena, background,
+        faction::Faction,
+        kamikaze_drone,
         movement::FollowEntityMovement,
         player_camera::{self},
+        rng::{GameRng, RngStream},
     },
     utility_systems::cleanup,
 };
@@ -31,15 +34,24 @@ impl Plugin for KamikazeDroneScenePlugin {
     }
 }
 
-fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn(mut commands: Commands, asset_server: Res<AssetServer>, mut game_rng: ResMut<GameRng>) {
     // Spawn the arena and player
     let arena = arena::Arena::new(1000.0, 200.0);
-    arena.spawn_asteroid_bounds(&mut commands, &asset_server);
+    arena.spawn_asteroid_bounds(
+        &mut commands,
+        &asset_server,
+        game_rng.stream(RngStream::Arena),
+    );
     // arena.spawn_random_asteroids(&mut commands, &asset_db, &asset_server, 50);
 
     // Spawn an enemy ship
-    let kamikaze_drone_entity =
-        kamikaze_drone::spawn(&mut commands, &asset_server, Vec2::new(0.0, 0.0), 0.0);
+    let kamikaze_drone_entity = kamikaze_drone::spawn(
+        &mut commands,
+        &asset_server,
+        Vec2::new(0.0, 0.0),
+        0.0,
+        Faction::Enemy1,
+    );
 
     commands
         .spawn(Camera2dBundle::default())
@@ -47,9 +59,20 @@ fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
 
     // spawn 99 more drones
 
-    kamikaze_drone::spawn(&mut commands, &asset_server, Vec2::new(40.0, 0.0), 0.0);
+    let wingmen = [40.0, 80.0].map(|x| {
+        kamikaze_drone::spawn(
+            &mut commands,
+            &asset_server,
+            Vec2::new(x, 0.0),
+            0.0,
+            Faction::Enemy1,
+        )
+    });
 
-    kamikaze_drone::spawn(&mut commands, &asset_server, Vec2::new(80.0, 0.0), 0.0);
+    kamikaze_drone::form_swarm(
+        &mut commands,
+        &[kamikaze_drone_entity, wingmen[0], wingmen[1]],
+    );
 
     // let drone_count = 1;
 
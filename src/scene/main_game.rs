@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 
 use super::GameScene;
-use crate::game::{arena, background};
+use crate::game::{arena, background, pickup, score, wave};
 
 pub struct MainGameScenePlugin;
 
@@ -11,11 +11,22 @@ impl Plugin for MainGameScenePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             OnEnter(GameScene::MainGame),
-            (background::spawn, arena::spawn),
+            (
+                score::reset,
+                background::spawn,
+                arena::spawn,
+                wave::spawn,
+                pickup::spawn,
+            ),
         )
         .add_systems(
             OnExit(GameScene::MainGame),
-            (background::despawn, arena::despawn),
+            (
+                background::despawn,
+                arena::despawn,
+                wave::despawn,
+                pickup::despawn,
+            ),
         );
     }
 }
@@ -1,7 +1,10 @@
 use bevy::prelude::*;
 
 use super::GameScene;
-use crate::game::{arena, background, player, player_camera};
+use crate::game::{
+    arena, background, player, player_camera,
+    rng::{GameRng, RngStream},
+};
 
 pub struct PlayerMovementScenePlugin;
 
@@ -24,9 +27,14 @@ impl Plugin for PlayerMovementScenePlugin {
     }
 }
 
-fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn(mut commands: Commands, asset_server: Res<AssetServer>, mut game_rng: ResMut<GameRng>) {
     let arena = arena::Arena::new(1000.0, 200.0);
-    arena.spawn_random_asteroids(&mut commands, &asset_server, 50);
+    arena.spawn_random_asteroids(
+        &mut commands,
+        &asset_server,
+        game_rng.stream(RngStream::Arena),
+        50,
+    );
 
     let player_entity =
         player::spawn_player(&mut commands, &asset_server, Vec2::new(0.0, 0.0), 0.0);
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 
 use super::GameScene;
-use crate::game::{background, meteors, player, player_camera, turret};
+use crate::game::{background, faction::Faction, meteors, player, player_camera, turret};
 
 pub struct TurretScenePlugin;
 
@@ -25,15 +25,19 @@ impl Plugin for TurretScenePlugin {
 }
 
 fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let turret_location = Vec3::new(0.0, 600.0, 0.0);
-    let spawn_transform = Transform::from_translation(turret_location);
-
-    turret::spawn(
-        &mut commands,
-        &asset_server,
-        &turret::TurretConfig::default(),
-        spawn_transform,
-    );
+    // One of each preset, side by side
+    for (i, preset) in turret::TurretPreset::ALL.iter().enumerate() {
+        let turret_location = Vec3::new((i as f32 - 1.0) * 400.0, 600.0, 0.0);
+        let spawn_transform = Transform::from_translation(turret_location);
+
+        turret::spawn(
+            &mut commands,
+            &asset_server,
+            &preset.config(),
+            spawn_transform,
+            Faction::Enemy1,
+        );
+    }
 
     meteors::spawn_immovable_meteor(
         &asset_server,
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 
 use super::GameScene;
-use crate::game::{background, player, player_camera, turret};
+use crate::game::{background, faction::Faction, player, player_camera, turret};
 
 pub struct TurretPerformanceScenePlugin;
 
@@ -52,6 +52,7 @@ fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
                 &asset_server,
                 &turret::TurretConfig::default(),
                 spawn_transform,
+                Faction::Enemy1,
             )
         }
     }
@@ -0,0 +1,41 @@
+use crate::paths::Paths;
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::window::PrimaryWindow;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Press F12 to save a screenshot to the screenshots directory, see `Paths`.
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, take_screenshot);
+    }
+}
+
+fn take_screenshot(
+    keyboard_input: Res<Input<KeyCode>>,
+    paths: Res<Paths>,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F12) {
+        return;
+    }
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default();
+    let path = paths
+        .screenshots_dir()
+        .join(format!("screenshot-{}.png", millis));
+
+    match screenshot_manager.save_screenshot_to_disk(window, &path) {
+        Ok(()) => println!("Saved screenshot to {}", path.display()),
+        Err(err) => eprintln!("Error taking screenshot: {}", err),
+    }
+}
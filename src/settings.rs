@@ -1,64 +1,278 @@
+pub mod keybindings;
+
+use crate::game::{
+    accessibility::{ColorPalette, HighContrast, Palette},
+    particles::ParticleSettings,
+    player::FireMode,
+    trauma::ShakeIntensity,
+};
 use crate::{
-    file_save::{self, FileSave},
-    game::debug::VisualDebug,
+    file_save::{self, FileSave, Versioned},
+    game::{debug::VisualDebug, movement::KeyboardMovementAction, player::PlayerShipAction},
     scene::GameScene,
 };
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{
+    audio::GlobalVolume,
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use keybindings::{apply_keybindings, Keybindings};
+use leafwing_input_manager::plugin::InputManagerSystem;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, error::Error};
-use toml::from_str;
+use std::{collections::HashSet, error::Error, path::Path};
+use toml::Table;
 
 /// Settings are settings that the player is allow to change.
-#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+/// Fields missing from the file keep their default value.
+#[derive(Resource, Deserialize, Serialize, Debug, Default, PartialEq, Clone)]
+#[serde(default)]
 pub struct Settings {
     pub scene: Option<GameScene>,
+    /// Seed for all gameplay randomness. If not set a random seed is used.
+    pub seed: Option<u64>,
+    /// The level to play in the main game, see `Level::path`. The default
+    /// level is used if not set.
+    pub level: Option<String>,
     pub visual_debug: HashSet<VisualDebug>,
     pub window: WindowSettings,
+    pub audio: AudioSettings,
+    pub accessibility: AccessibilitySettings,
+    pub particles: ParticleSettings,
+    pub keybindings: Keybindings,
+}
+
+impl Settings {
+    /// Copy the settings that can be changed in the settings menu.
+    pub fn copy_menu_settings(&mut self, from: &Settings) {
+        self.window.mode = from.window.mode;
+        self.window.vsync = from.window.vsync;
+        self.window.msaa = from.window.msaa;
+        self.window.ui_scale = from.window.ui_scale;
+        self.audio = from.audio.clone();
+        self.accessibility = from.accessibility.clone();
+    }
 }
 
 impl FileSave for Settings {
     type Item = Settings;
     fn load_from_file(path: &str) -> Result<Settings, Box<dyn Error>> {
-        let contents = file_save::load_from_file(path)?;
-        let settings = from_str(&contents)?;
-        Ok(settings)
+        file_save::load_versioned(path)
     }
 
     fn save_to_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
-        let contents = toml::to_string(&self)?;
+        let contents = file_save::to_versioned_string(self)?;
         file_save::save_to_file(path, &contents)?;
         Ok(())
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+impl Versioned for Settings {
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, _document: &mut Table) -> Result<(), Box<dyn Error>> {
+        match version {
+            // Version 1 only added the `version` field
+            0 => Ok(()),
+            _ => Err(format!("no migration from version {}", version).into()),
+        }
+    }
+}
 
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(default)]
 pub struct WindowSettings {
     pub resolution: ResolutionSetting,
+    pub mode: WindowModeSetting,
+    pub vsync: bool,
+    pub msaa: MsaaSetting,
+    /// Scales all menus and the HUD.
+    #[serde(serialize_with = "serialize_fraction")]
+    pub ui_scale: f32,
 }
 
 impl Default for WindowSettings {
     fn default() -> Self {
         Self {
             resolution: ResolutionSetting::default(),
+            mode: WindowModeSetting::default(),
+            vsync: true,
+            msaa: MsaaSetting::default(),
+            ui_scale: 1.0,
         }
     }
 }
 
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    /// Fullscreen without changing the resolution of the monitor.
+    Borderless,
+    Fullscreen,
+}
+
+impl From<WindowModeSetting> for WindowMode {
+    fn from(mode: WindowModeSetting) -> Self {
+        match mode {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+/// The number of samples per pixel used to smooth edges.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum MsaaSetting {
+    Off,
+    Sample2,
+    #[default]
+    Sample4,
+    Sample8,
+}
+
+impl From<MsaaSetting> for Msaa {
+    fn from(msaa: MsaaSetting) -> Self {
+        match msaa {
+            MsaaSetting::Off => Msaa::Off,
+            MsaaSetting::Sample2 => Msaa::Sample2,
+            MsaaSetting::Sample4 => Msaa::Sample4,
+            MsaaSetting::Sample8 => Msaa::Sample8,
+        }
+    }
+}
+
+/// Volumes from 0 to 1. Music and sound effects are scaled by the master
+/// volume.
+#[derive(Resource, Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct AudioSettings {
+    #[serde(serialize_with = "serialize_fraction")]
+    pub master_volume: f32,
+    #[serde(serialize_with = "serialize_fraction")]
+    pub music_volume: f32,
+    #[serde(serialize_with = "serialize_fraction")]
+    pub sfx_volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            music_volume: 0.7,
+            sfx_volume: 1.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// How much the camera shakes, from 0 (not at all) to 1.
+    #[serde(serialize_with = "serialize_fraction")]
+    pub screen_shake: f32,
+    /// Colors for projectiles, enemies and the health bar.
+    pub palette: Palette,
+    /// Outlines around enemies and projectiles.
+    pub high_contrast: bool,
+    pub fire_mode: FireMode,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            screen_shake: 1.0,
+            palette: Palette::default(),
+            high_contrast: false,
+            fire_mode: FireMode::default(),
+        }
+    }
+}
+
+/// Write an `f32` the way it was typed, `0.7` instead of `0.699999988079071`.
+fn serialize_fraction<S: serde::Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    let rounded = (*value as f64 * 1000.0).round() / 1000.0;
+    serializer.serialize_f64(rounded)
+}
+
 /// A plugin that loads the settings from a file and inserts them into the app
 /// as resources at start.
-pub struct SettingsPlugin(Settings);
+pub struct SettingsPlugin {
+    settings: Settings,
+    file: Option<String>,
+}
 
 impl SettingsPlugin {
     pub fn new(settings: Settings) -> Self {
-        Self(settings)
+        Self {
+            settings,
+            file: None,
+        }
+    }
+
+    /// Save settings changed in game to `path`.
+    pub fn with_file(mut self, path: &str) -> Self {
+        self.file = Some(path.to_string());
+        self
     }
 }
 
 impl Plugin for SettingsPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(self.0.window.resolution.clone())
-            .add_systems(Update, update_resolution);
+        app.insert_resource(self.settings.clone())
+            .insert_resource(self.settings.audio.clone())
+            .insert_resource(self.settings.particles.clone())
+            .init_resource::<ShakeIntensity>()
+            .init_resource::<ColorPalette>()
+            .init_resource::<HighContrast>()
+            .init_resource::<FireMode>()
+            .insert_resource(SettingsFile(self.file.clone()))
+            .insert_resource(self.settings.window.resolution.clone())
+            .add_systems(Update, update_resolution)
+            .add_systems(
+                Update,
+                (
+                    apply_window_settings,
+                    apply_graphics_settings,
+                    apply_audio_settings,
+                    apply_accessibility_settings,
+                    apply_particle_settings,
+                )
+                    .run_if(resource_changed::<Settings>()),
+            )
+            .add_systems(
+                PreUpdate,
+                (
+                    apply_keybindings::<PlayerShipAction>,
+                    apply_keybindings::<KeyboardMovementAction>,
+                    apply_keybindings::<GameScene>,
+                    apply_keybindings::<VisualDebug>,
+                )
+                    .before(InputManagerSystem::Update),
+            );
+    }
+}
+
+/// The file settings changed in game are saved to. Not set when the settings
+/// should not be written, like a file given with `--settings`.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Default)]
+pub struct SettingsFile(pub Option<String>);
+
+impl SettingsFile {
+    /// Change the settings in the file. Only what `change` touches is
+    /// written, so overrides from the command line do not end up in the file.
+    pub fn update(&self, change: impl FnOnce(&mut Settings)) -> Result<(), Box<dyn Error>> {
+        let Some(path) = self.0.as_deref() else {
+            return Ok(());
+        };
+
+        let mut settings = if Path::new(path).exists() {
+            Settings::load_from_file(path)?
+        } else {
+            Settings::default()
+        };
+        change(&mut settings);
+        settings.save_to_file(path)
     }
 }
 
@@ -93,3 +307,95 @@ fn update_resolution(
         }
     }
 }
+
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if let Ok(mut window) = window_query.get_single_mut() {
+        let mode = settings.window.mode.into();
+        let present_mode = if settings.window.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        };
+
+        // Only touch the window when something changed, setting the mode
+        // again makes some platforms flicker
+        if window.mode != mode {
+            window.mode = mode;
+        }
+        if window.present_mode != present_mode {
+            window.present_mode = present_mode;
+        }
+    }
+}
+
+fn apply_graphics_settings(
+    settings: Res<Settings>,
+    msaa: Option<ResMut<Msaa>>,
+    ui_scale: Option<ResMut<UiScale>>,
+) {
+    if let Some(mut msaa) = msaa {
+        let samples = settings.window.msaa.into();
+        if *msaa != samples {
+            *msaa = samples;
+        }
+    }
+    if let Some(mut ui_scale) = ui_scale {
+        ui_scale.0 = settings.window.ui_scale as f64;
+    }
+}
+
+/// The game sounds read the volumes from `AudioSettings`, other sounds are
+/// only scaled by the master volume.
+fn apply_audio_settings(
+    settings: Res<Settings>,
+    mut audio: ResMut<AudioSettings>,
+    global_volume: Option<ResMut<GlobalVolume>>,
+) {
+    audio.set_if_neq(settings.audio.clone());
+    if let Some(mut global_volume) = global_volume {
+        *global_volume = GlobalVolume::new(settings.audio.master_volume);
+    }
+}
+
+fn apply_accessibility_settings(
+    settings: Res<Settings>,
+    mut shake: ResMut<ShakeIntensity>,
+    mut palette: ResMut<ColorPalette>,
+    mut high_contrast: ResMut<HighContrast>,
+    mut fire_mode: ResMut<FireMode>,
+) {
+    let accessibility = &settings.accessibility;
+    shake.set_if_neq(ShakeIntensity(accessibility.screen_shake));
+    // Only when changed, everything is recolored and outlined again
+    palette.set_if_neq(ColorPalette(accessibility.palette));
+    high_contrast.set_if_neq(HighContrast(accessibility.high_contrast));
+    fire_mode.set_if_neq(accessibility.fire_mode);
+}
+
+fn apply_particle_settings(settings: Res<Settings>, mut particles: ResMut<ParticleSettings>) {
+    particles.set_if_neq(settings.particles.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_save::parse_versioned;
+
+    #[test]
+    fn test_unversioned_settings_with_missing_fields_load() {
+        let migrated = parse_versioned::<Settings>(
+            r#"
+            seed = 7
+            visual_debug = []
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(migrated.from_version, 0);
+        assert_eq!(migrated.item.seed, Some(7));
+        assert_eq!(migrated.item.window, WindowSettings::default());
+    }
+}
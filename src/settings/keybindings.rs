@@ -0,0 +1,454 @@
+//! # Keybindings
+//!
+//! The inputs bound to every action, saved in the `[keybindings.*]` tables of
+//! the settings file. Each action lists its inputs by name:
+//!
+//! ```toml
+//! [keybindings.game]
+//! FireWeapon = ["L", "Mouse:Left", "Gamepad:South"]
+//! RotateShip = ["Gamepad:LeftStick"]
+//! ```
+//!
+//! Keys are named like bevy's `KeyCode` (`W`, `Space`, `ShiftLeft`, `Key1`).
+//! Mouse buttons are `Mouse:Left`, `Mouse:Right`, `Mouse:Middle`, and the
+//! wheel `Mouse:WheelUp` and `Mouse:WheelDown`. Gamepad buttons are named like
+//! `GamepadButtonType` (`Gamepad:South`, `Gamepad:RightTrigger2`), single
+//! axes like `GamepadAxisType` (`Gamepad:LeftStickX`) and whole sticks are
+//! `Gamepad:LeftStick` and `Gamepad:RightStick`. Buttons and axes without a
+//! name are numbered: `Mouse:Button4`, `Gamepad:Button20` and `Gamepad:Axis6`.
+//!
+//! Actions missing from the file keep their default inputs, an empty list
+//! unbinds an action.
+
+use crate::game::{debug::VisualDebug, movement::KeyboardMovementAction, player::PlayerShipAction};
+use crate::scene::GameScene;
+use bevy::input::gamepad::{GamepadAxisType, GamepadButtonType};
+use bevy::prelude::*;
+use leafwing_input_manager::{
+    axislike::{DualAxis, SingleAxis},
+    input_map::InputMap,
+    user_input::InputKind,
+    Actionlike,
+};
+use serde::{
+    de::{value::StrDeserializer, IntoDeserializer},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    marker::PhantomData,
+    str::FromStr,
+};
+
+/// Gamepad axes report values below this as zero.
+const AXIS_DEADZONE: f32 = 0.1;
+
+////////////////////////////////////////////////////////////////////////////////
+// Keybindings
+////////////////////////////////////////////////////////////////////////////////
+
+/// The bindings of all actions the player can rebind, one table per action
+/// type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct Keybindings {
+    /// Flying the player ship.
+    pub game: Bindings<PlayerShipAction>,
+    /// Moving the camera in the debug scenes.
+    pub camera: Bindings<KeyboardMovementAction>,
+    /// Switching between scenes.
+    pub scenes: Bindings<GameScene>,
+    /// Toggling the visual debug tools.
+    pub debug: Bindings<VisualDebug>,
+}
+
+impl Keybindings {
+    /// Inputs bound to more than one action of the same type, as messages
+    /// like `game: L is bound to FireWeapon and Boost`.
+    pub fn conflicts(&self) -> Vec<String> {
+        let describe = |table: &str, conflicts: Vec<(Binding, Vec<String>)>| {
+            conflicts
+                .into_iter()
+                .map(|(binding, actions)| {
+                    format!(
+                        "{}: {} is bound to {}",
+                        table,
+                        binding,
+                        actions.join(" and ")
+                    )
+                })
+                .collect::<Vec<String>>()
+        };
+
+        [
+            describe("game", self.game.conflicts()),
+            describe("camera", self.camera.conflicts()),
+            describe("scenes", self.scenes.conflicts()),
+            describe("debug", self.debug.conflicts()),
+        ]
+        .concat()
+    }
+}
+
+/// An action type that can be rebound in the settings.
+pub trait Rebindable: Actionlike + Copy + fmt::Debug {
+    /// The bindings of actions that are not in the settings file.
+    fn default_bindings() -> Bindings<Self>;
+
+    /// The bindings of this action type in `keybindings`.
+    fn bindings(keybindings: &Keybindings) -> &Bindings<Self>;
+}
+
+/// Replace the input maps of `A` when the settings change, and of every newly
+/// spawned input map, with the bindings from the settings.
+pub fn apply_keybindings<A: Rebindable>(
+    settings: Res<super::Settings>,
+    mut input_map_query: Query<&mut InputMap<A>>,
+) {
+    for mut input_map in input_map_query.iter_mut() {
+        if settings.is_changed() || input_map.is_added() {
+            *input_map = A::bindings(&settings.keybindings).input_map();
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Bindings
+////////////////////////////////////////////////////////////////////////////////
+
+/// The inputs bound to each action of `A`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bindings<A> {
+    /// Indexed by `Actionlike::index`.
+    bindings: Vec<Vec<Binding>>,
+    _action: PhantomData<A>,
+}
+
+impl<A: Rebindable> Bindings<A> {
+    /// Bindings for the listed actions, the rest are unbound.
+    pub fn new(bindings: impl IntoIterator<Item = (A, Vec<Binding>)>) -> Self {
+        let mut new = Self {
+            bindings: vec![Vec::new(); A::n_variants()],
+            _action: PhantomData,
+        };
+        for (action, action_bindings) in bindings {
+            new.bindings[action.index()] = action_bindings;
+        }
+        new
+    }
+
+    pub fn get(&self, action: A) -> &[Binding] {
+        &self.bindings[action.index()]
+    }
+
+    pub fn set(&mut self, action: A, bindings: Vec<Binding>) {
+        self.bindings[action.index()] = bindings;
+    }
+
+    /// Bind a button to an action. It replaces the buttons of the same device
+    /// bound to the action, axes are kept.
+    pub fn rebind(&mut self, action: A, binding: Binding) {
+        let action_bindings = &mut self.bindings[action.index()];
+        action_bindings
+            .retain(|bound| !(bound.is_button() && bound.is_gamepad() == binding.is_gamepad()));
+        action_bindings.push(binding);
+    }
+
+    /// Inputs bound to more than one action, with the names of the actions.
+    pub fn conflicts(&self) -> Vec<(Binding, Vec<String>)> {
+        let mut actions_by_binding: HashMap<Binding, Vec<A>> = HashMap::new();
+        let mut order = Vec::new();
+        for action in A::variants() {
+            for binding in self.get(action) {
+                let actions = actions_by_binding.entry(*binding).or_default();
+                if actions.is_empty() {
+                    order.push(*binding);
+                }
+                actions.push(action);
+            }
+        }
+
+        order
+            .into_iter()
+            .filter_map(|binding| {
+                let actions = &actions_by_binding[&binding];
+                (actions.len() > 1).then(|| {
+                    let names = actions.iter().map(|action| format!("{:?}", action));
+                    (binding, names.collect())
+                })
+            })
+            .collect()
+    }
+
+    pub fn input_map(&self) -> InputMap<A> {
+        let mut input_map = InputMap::default();
+        for action in A::variants() {
+            for binding in self.get(action) {
+                input_map.insert(binding.input_kind(), action);
+            }
+        }
+        input_map
+    }
+}
+
+impl<A: Rebindable> Default for Bindings<A> {
+    fn default() -> Self {
+        A::default_bindings()
+    }
+}
+
+impl<A: Rebindable> Serialize for Bindings<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(A::n_variants()))?;
+        for action in A::variants() {
+            map.serialize_entry(&format!("{:?}", action), self.get(action))?;
+        }
+        map.end()
+    }
+}
+
+/// Starts from the default bindings and replaces those of the actions in the
+/// file. Unknown actions are dropped, so they show up as unknown fields, and
+/// inputs that can not be read are skipped with a warning.
+impl<'de, A: Rebindable> Deserialize<'de> for Bindings<A> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names: BTreeMap<String, Vec<String>> = BTreeMap::deserialize(deserializer)?;
+
+        let mut bindings = A::default_bindings();
+        for action in A::variants() {
+            let Some(inputs) = names.get(&format!("{:?}", action)) else {
+                continue;
+            };
+
+            let action_bindings = inputs
+                .iter()
+                .filter_map(|input| match input.parse() {
+                    Ok(binding) => Some(binding),
+                    Err(err) => {
+                        eprintln!("Warning: {} for {:?} is ignored.", err, action);
+                        None
+                    }
+                })
+                .collect();
+            bindings.set(action, action_bindings);
+        }
+
+        Ok(bindings)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Binding
+////////////////////////////////////////////////////////////////////////////////
+
+/// A single input that can be bound to an action. Saved as a name, see the
+/// module documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    MouseWheelUp,
+    MouseWheelDown,
+    GamepadButton(GamepadButtonType),
+    GamepadAxis(GamepadAxisType),
+    LeftStick,
+    RightStick,
+}
+
+impl Binding {
+    /// Buttons are on or off, the rest are axes.
+    pub fn is_button(&self) -> bool {
+        matches!(
+            self,
+            Binding::Key(_)
+                | Binding::Mouse(_)
+                | Binding::MouseWheelUp
+                | Binding::MouseWheelDown
+                | Binding::GamepadButton(_)
+        )
+    }
+
+    pub fn is_gamepad(&self) -> bool {
+        matches!(
+            self,
+            Binding::GamepadButton(_)
+                | Binding::GamepadAxis(_)
+                | Binding::LeftStick
+                | Binding::RightStick
+        )
+    }
+
+    pub fn input_kind(&self) -> InputKind {
+        use leafwing_input_manager::buttonlike::MouseWheelDirection;
+
+        match *self {
+            Binding::Key(key) => InputKind::Keyboard(key),
+            Binding::Mouse(button) => InputKind::Mouse(button),
+            Binding::MouseWheelUp => InputKind::MouseWheel(MouseWheelDirection::Up),
+            Binding::MouseWheelDown => InputKind::MouseWheel(MouseWheelDirection::Down),
+            Binding::GamepadButton(button) => InputKind::GamepadButton(button),
+            Binding::GamepadAxis(axis) => SingleAxis::symmetric(axis, AXIS_DEADZONE).into(),
+            Binding::LeftStick => DualAxis::left_stick().into(),
+            Binding::RightStick => DualAxis::right_stick().into(),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(MouseButton::Other(number)) => write!(f, "Mouse:Button{}", number),
+            Binding::Mouse(button) => write!(f, "Mouse:{:?}", button),
+            Binding::MouseWheelUp => write!(f, "Mouse:WheelUp"),
+            Binding::MouseWheelDown => write!(f, "Mouse:WheelDown"),
+            Binding::GamepadButton(GamepadButtonType::Other(number)) => {
+                write!(f, "Gamepad:Button{}", number)
+            }
+            Binding::GamepadButton(button) => write!(f, "Gamepad:{:?}", button),
+            Binding::GamepadAxis(GamepadAxisType::Other(number)) => {
+                write!(f, "Gamepad:Axis{}", number)
+            }
+            Binding::GamepadAxis(axis) => write!(f, "Gamepad:{:?}", axis),
+            Binding::LeftStick => write!(f, "Gamepad:LeftStick"),
+            Binding::RightStick => write!(f, "Gamepad:RightStick"),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let unknown = || format!("unknown input `{}`", name);
+
+        let binding = match name.split_once(':') {
+            None => Binding::Key(from_name(name).ok_or_else(unknown)?),
+            Some(("Mouse", "WheelUp")) => Binding::MouseWheelUp,
+            Some(("Mouse", "WheelDown")) => Binding::MouseWheelDown,
+            Some(("Mouse", button)) => match number(button, "Button") {
+                Some(number) => Binding::Mouse(MouseButton::Other(number)),
+                None => Binding::Mouse(from_name(button).ok_or_else(unknown)?),
+            },
+            Some(("Gamepad", "LeftStick")) => Binding::LeftStick,
+            Some(("Gamepad", "RightStick")) => Binding::RightStick,
+            Some(("Gamepad", input)) => {
+                if let Some(number) = number(input, "Button") {
+                    Binding::GamepadButton(GamepadButtonType::Other(number))
+                } else if let Some(number) = number(input, "Axis") {
+                    Binding::GamepadAxis(GamepadAxisType::Other(number))
+                } else if let Some(button) = from_name(input) {
+                    Binding::GamepadButton(button)
+                } else {
+                    Binding::GamepadAxis(from_name(input).ok_or_else(unknown)?)
+                }
+            }
+            Some(_) => return Err(unknown()),
+        };
+        Ok(binding)
+    }
+}
+
+impl Serialize for Binding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Binding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The number of an input without a name, like `Button4`.
+fn number<T: FromStr>(name: &str, prefix: &str) -> Option<T> {
+    name.strip_prefix(prefix)?.parse().ok()
+}
+
+/// Look up a unit variant of a bevy input enum by name.
+fn from_name<'de, T: Deserialize<'de>>(name: &'de str) -> Option<T> {
+    let deserializer: StrDeserializer<serde::de::value::Error> = name.into_deserializer();
+    T::deserialize(deserializer).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binding_names_round_trip() {
+        for name in [
+            "W",
+            "ShiftLeft",
+            "Mouse:Left",
+            "Mouse:WheelDown",
+            "Gamepad:South",
+            "Gamepad:LeftStickX",
+            "Gamepad:RightStick",
+            "Mouse:Button4",
+            "Gamepad:Button20",
+            "Gamepad:Axis6",
+        ] {
+            let binding: Binding = name.parse().unwrap();
+            assert_eq!(binding.to_string(), name);
+        }
+
+        assert!("Kee".parse::<Binding>().is_err());
+        assert!("Keyboard:W".parse::<Binding>().is_err());
+        assert!("Gamepad:Button300".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn test_missing_actions_keep_their_defaults() {
+        let keybindings: Keybindings = toml::from_str(
+            r#"
+            [game]
+            FireWeapon = ["Space", "NotAKey"]
+            Boost = []
+            "#,
+        )
+        .unwrap();
+        let defaults = PlayerShipAction::default_bindings();
+
+        assert_eq!(
+            keybindings.game.get(PlayerShipAction::FireWeapon),
+            &[Binding::Key(KeyCode::Space)]
+        );
+        assert!(keybindings.game.get(PlayerShipAction::Boost).is_empty());
+        assert_eq!(
+            keybindings.game.get(PlayerShipAction::ThrottleForward),
+            defaults.get(PlayerShipAction::ThrottleForward)
+        );
+        assert_eq!(keybindings.scenes, GameScene::default_bindings());
+    }
+
+    #[test]
+    fn test_defaults_have_no_conflicts() {
+        assert!(Keybindings::default().conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_rebind_replaces_buttons_of_the_same_device_and_finds_conflicts() {
+        let mut bindings = PlayerShipAction::default_bindings();
+        bindings.rebind(PlayerShipAction::FireWeapon, Binding::Key(KeyCode::W));
+
+        assert_eq!(
+            bindings.get(PlayerShipAction::FireWeapon),
+            &[
+                Binding::GamepadButton(GamepadButtonType::South),
+                Binding::Key(KeyCode::W)
+            ]
+        );
+        assert_eq!(
+            bindings.conflicts(),
+            vec![(
+                Binding::Key(KeyCode::W),
+                vec!["ThrottleForward".to_string(), "FireWeapon".to_string()]
+            )]
+        );
+    }
+}
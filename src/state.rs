@@ -0,0 +1,259 @@
+//! # App State
+//!
+//! Where the player is in the flow of the game: booting, in the main menu,
+//! playing, paused or looking at the game over screen. This is separate from
+//! the `GameScene`, which decides what is spawned in the world.
+//!
+//! Pausing freezes the physics, the virtual clock that all gameplay timers run
+//! on, and the player's controls.
+
+use crate::game::{
+    events::GameOverEvent,
+    game_entity::GameEntityType,
+    player::PlayerShipAction,
+    score::GameScore,
+    vitality::{DeathEvent, VitalitySystem},
+};
+use crate::scene::{GameScene, Reload};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::RapierConfiguration;
+use leafwing_input_manager::plugin::ToggleActions;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+/// Adds the `AppState`. The app boots into `start`.
+pub struct StatePlugin {
+    pub start: AppState,
+}
+
+impl StatePlugin {
+    pub fn new(start: AppState) -> Self {
+        Self { start }
+    }
+}
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        let start = self.start;
+
+        app.add_state::<AppState>()
+            .add_event::<RestartGame>()
+            .add_event::<QuitToMainMenu>()
+            .add_systems(
+                OnEnter(AppState::Boot),
+                move |mut next_state: ResMut<NextState<AppState>>| next_state.set(start),
+            )
+            .add_systems(OnEnter(AppState::MainMenu), leave_scene)
+            .add_systems(OnEnter(AppState::Paused), pause)
+            .add_systems(OnExit(AppState::Paused), resume)
+            .add_systems(
+                Update,
+                (
+                    toggle_pause,
+                    game_over_on_player_death
+                        .after(VitalitySystem::DeathCheck)
+                        .run_if(in_state(AppState::Playing))
+                        .run_if(in_state(GameScene::MainGame)),
+                    restart_game,
+                    quit_to_main_menu,
+                ),
+            );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// State
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum AppState {
+    /// Decides where the app starts, then moves on right away.
+    #[default]
+    Boot,
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+/// Start the current scene over, or the main game if there is no scene.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RestartGame;
+
+/// Leave the game and go back to the main menu.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QuitToMainMenu;
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn leave_scene(mut next_scene: ResMut<NextState<GameScene>>) {
+    next_scene.set(GameScene::None);
+}
+
+fn pause(
+    mut commands: Commands,
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    time.pause();
+    rapier_config.physics_pipeline_active = false;
+    commands.insert_resource(ToggleActions::<PlayerShipAction>::DISABLED);
+}
+
+fn resume(
+    mut commands: Commands,
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    time.unpause();
+    rapier_config.physics_pipeline_active = true;
+    commands.insert_resource(ToggleActions::<PlayerShipAction>::ENABLED);
+}
+
+fn toggle_pause(
+    keyboard_input: Res<Input<KeyCode>>,
+    app_state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        match app_state.get() {
+            AppState::Playing => next_state.set(AppState::Paused),
+            AppState::Paused => next_state.set(AppState::Playing),
+            _ => (),
+        }
+    }
+}
+
+fn game_over_on_player_death(
+    game_score: Res<GameScore>,
+    mut death_events: EventReader<DeathEvent>,
+    mut game_over_events: EventWriter<GameOverEvent>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for death in death_events.read() {
+        if death._type() == GameEntityType::Player {
+            game_over_events.send(GameOverEvent {
+                score: game_score.total(),
+            });
+            next_state.set(AppState::GameOver);
+        }
+    }
+}
+
+/// The scene is left and entered again on the next frame, which despawns
+/// everything in it and spawns it anew.
+fn restart_game(
+    mut restart_events: EventReader<RestartGame>,
+    scene: Res<State<GameScene>>,
+    mut reload: ResMut<Reload>,
+    mut next_scene: ResMut<NextState<GameScene>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if restart_events.read().last().is_none() {
+        return;
+    }
+
+    let scene = match scene.get() {
+        GameScene::None => GameScene::MainGame,
+        scene => *scene,
+    };
+    reload.reload(scene);
+    next_scene.set(GameScene::None);
+    next_state.set(AppState::Playing);
+}
+
+fn quit_to_main_menu(
+    mut quit_events: EventReader<QuitToMainMenu>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if quit_events.read().last().is_some() {
+        next_state.set(AppState::MainMenu);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(start: AppState) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_state::<GameScene>()
+            .init_resource::<Reload>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<GameScore>()
+            .insert_resource(RapierConfiguration::default())
+            .add_event::<DeathEvent>()
+            .add_event::<GameOverEvent>()
+            .add_plugins(StatePlugin::new(start));
+        app.update();
+        app.update();
+        app
+    }
+
+    fn state(app: &App) -> AppState {
+        *app.world.resource::<State<AppState>>().get()
+    }
+
+    #[test]
+    fn test_pause_freezes_physics_and_time() {
+        let mut app = app(AppState::Playing);
+        assert_eq!(state(&app), AppState::Playing);
+
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Paused);
+        app.update();
+
+        assert!(app.world.resource::<Time<Virtual>>().is_paused());
+        assert!(
+            !app.world
+                .resource::<RapierConfiguration>()
+                .physics_pipeline_active
+        );
+
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Playing);
+        app.update();
+
+        assert!(!app.world.resource::<Time<Virtual>>().is_paused());
+        assert!(
+            app.world
+                .resource::<RapierConfiguration>()
+                .physics_pipeline_active
+        );
+    }
+
+    #[test]
+    fn test_restart_leaves_and_reloads_the_scene() {
+        let mut app = app(AppState::Playing);
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::GameOver);
+        app.update();
+
+        // The new states are entered on the next frame
+        app.world.send_event(RestartGame);
+        app.update();
+        app.update();
+
+        assert_eq!(state(&app), AppState::Playing);
+        assert_eq!(
+            *app.world.resource::<State<GameScene>>().get(),
+            GameScene::None
+        );
+        assert_eq!(
+            app.world.resource::<Reload>().scene(),
+            Some(GameScene::MainGame)
+        );
+    }
+}
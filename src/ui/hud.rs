@@ -1,6 +1,10 @@
+mod boost_bar;
 mod fps_counter;
 mod health_bar;
 mod score_tracker;
+mod shield_bar;
+mod wave_tracker;
+mod weapon_bar;
 
 use bevy::prelude::*;
 use bevy_progressbar::ProgressBarMaterial;
@@ -10,9 +14,13 @@ pub struct HudPlugin;
 impl Plugin for HudPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
+            boost_bar::BoostBarPlugin,
             fps_counter::FPSCounterPlugin,
             health_bar::HealthBarPlugin,
             score_tracker::ScoreTrackerPlugin,
+            shield_bar::ShieldBarPlugin,
+            wave_tracker::WaveTrackerPlugin,
+            weapon_bar::WeaponBarPlugin,
         ))
         .add_systems(Startup, spawn_hud);
     }
@@ -39,9 +47,28 @@ fn build_hud(
     materials: &mut ResMut<Assets<ProgressBarMaterial>>,
 ) {
     let health_bar_id = health_bar::build(commands, asset_server);
+    let shield_bar_id = shield_bar::build(commands, materials);
+    let boost_bar_id = boost_bar::build(commands, materials);
+    let weapon_bar_id = weapon_bar::build(commands, asset_server, materials);
     let fps_counter_id = fps_counter::build(commands, asset_server);
+    let wave_tracker_id = wave_tracker::build(commands, asset_server);
     let score_tracker_id = score_tracker::build(commands, asset_server, materials);
 
+    // Health, shield, boost and weapon on top of each other
+    let player_status_id = commands
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Start,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            ..default()
+        })
+        .push_children(&[health_bar_id, shield_bar_id, boost_bar_id, weapon_bar_id])
+        .id();
+
     let mut hud_entity = commands.spawn((
         NodeBundle {
             style: Style {
@@ -61,7 +88,12 @@ fn build_hud(
         HUD {},
     ));
 
-    hud_entity.push_children(&[health_bar_id, fps_counter_id, score_tracker_id]);
+    hud_entity.push_children(&[
+        player_status_id,
+        fps_counter_id,
+        wave_tracker_id,
+        score_tracker_id,
+    ]);
 }
 
 fn despawn_hud(mut commands: Commands, hud_query: Query<Entity, With<HUD>>) {
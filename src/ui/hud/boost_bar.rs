@@ -0,0 +1,81 @@
+use crate::game::player::components::{Boost, BoostFuel, Player};
+use bevy::prelude::*;
+use bevy_progressbar::{ProgressBar, ProgressBarBundle, ProgressBarMaterial};
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct BoostBarPlugin;
+
+impl Plugin for BoostBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_boost_bar);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+struct BoostBar;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+const BOOST_BAR_AMOUNT: u32 = 1000;
+
+const FUEL_COLOR: Color = Color::rgb(1.0, 0.6, 0.1);
+const BOOSTING_COLOR: Color = Color::rgb(1.0, 0.85, 0.3);
+
+pub fn build(
+    commands: &mut Commands,
+    materials: &mut ResMut<Assets<ProgressBarMaterial>>,
+) -> Entity {
+    let bar = ProgressBar::new(vec![(BOOST_BAR_AMOUNT, Color::DARK_GRAY)]);
+
+    let style = Style {
+        width: Val::Px(160.0),
+        height: Val::Px(8.0),
+        ..default()
+    };
+
+    commands
+        .spawn(BoostBar)
+        .insert(ProgressBarBundle::new(style, bar, materials))
+        .insert(Visibility::Hidden)
+        .id()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn update_boost_bar(
+    mut boost_bar_query: Query<(&mut ProgressBar, &mut Visibility), With<BoostBar>>,
+    player_query: Query<(&Boost, &BoostFuel), With<Player>>,
+) {
+    let player = player_query.get_single().ok();
+
+    for (mut boost_bar, mut visibility) in boost_bar_query.iter_mut() {
+        let Some((boost, fuel)) = player else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Inherited;
+
+        let color = if boost.is_active() {
+            BOOSTING_COLOR
+        } else {
+            FUEL_COLOR
+        };
+        let filled = (fuel.fraction() * BOOST_BAR_AMOUNT as f32) as u32;
+
+        boost_bar.sections = vec![
+            (filled, color),
+            (BOOST_BAR_AMOUNT - filled, Color::DARK_GRAY),
+        ];
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    game::{player::components::Player, vitality::Health},
+    game::{accessibility::ColorPalette, player::components::Player, vitality::Health},
     ui::assets::GameFonts,
 };
 use bevy::prelude::*;
@@ -57,19 +57,14 @@ pub fn build(commands: &mut Commands, asset_server: &Res<AssetServer>) -> Entity
 ////////////////////////////////////////////////////////////////////////////////
 
 fn update_health_bar(
+    palette: Res<ColorPalette>,
     mut text_query: Query<&mut Text, With<HealthBar>>,
     player_health_query: Query<&Health, With<Player>>,
 ) {
     if let Ok(player_health) = player_health_query.get_single() {
         for mut text in text_query.iter_mut() {
             text.sections[0].value = player_health.current().to_string();
-            if player_health.current() < 33 {
-                text.sections[0].style.color = Color::RED;
-            } else if player_health.current() < 66 {
-                text.sections[0].style.color = Color::ORANGE;
-            } else {
-                text.sections[0].style.color = Color::WHITE;
-            }
+            text.sections[0].style.color = palette.0.colors().health(player_health.current());
         }
     }
 }
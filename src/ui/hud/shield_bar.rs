@@ -0,0 +1,81 @@
+use crate::game::{player::components::Player, vitality::Shield};
+use bevy::prelude::*;
+use bevy_progressbar::{ProgressBar, ProgressBarBundle, ProgressBarMaterial};
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ShieldBarPlugin;
+
+impl Plugin for ShieldBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_shield_bar);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+struct ShieldBar;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+const SHIELD_BAR_AMOUNT: u32 = 1000;
+
+const SHIELD_COLOR: Color = Color::rgb(0.3, 0.7, 1.0);
+
+pub fn build(
+    commands: &mut Commands,
+    materials: &mut ResMut<Assets<ProgressBarMaterial>>,
+) -> Entity {
+    let bar = ProgressBar::new(vec![(SHIELD_BAR_AMOUNT, Color::DARK_GRAY)]);
+
+    let style = Style {
+        width: Val::Px(160.0),
+        height: Val::Px(8.0),
+        ..default()
+    };
+
+    commands
+        .spawn(ShieldBar)
+        .insert(ProgressBarBundle::new(style, bar, materials))
+        .insert(Visibility::Hidden)
+        .id()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn update_shield_bar(
+    mut shield_bar_query: Query<(&mut ProgressBar, &mut Visibility), With<ShieldBar>>,
+    player_query: Query<Option<&Shield>, With<Player>>,
+) {
+    let shield = player_query.get_single().ok().flatten();
+
+    for (mut shield_bar, mut visibility) in shield_bar_query.iter_mut() {
+        let Some(shield) = shield else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Inherited;
+
+        // A broken shield is shown in grey until it is fully recharged
+        let color = if shield.is_broken() {
+            Color::GRAY
+        } else {
+            SHIELD_COLOR
+        };
+        let charged = (shield.fraction() * SHIELD_BAR_AMOUNT as f32) as u32;
+
+        shield_bar.sections = vec![
+            (charged, color),
+            (SHIELD_BAR_AMOUNT - charged, Color::DARK_GRAY),
+        ];
+    }
+}
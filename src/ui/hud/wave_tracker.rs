@@ -0,0 +1,94 @@
+use crate::{
+    game::wave::{WavePhase, WaveState},
+    ui::assets::GameFonts,
+};
+use bevy::prelude::*;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+pub struct WaveTrackerPlugin;
+
+impl Plugin for WaveTrackerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_wave_tracker);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+struct WaveTracker;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn build(commands: &mut Commands, asset_server: &Res<AssetServer>) -> Entity {
+    let title_style = TextStyle {
+        font: asset_server.font_future(),
+        font_size: 32.0,
+        color: Color::WHITE,
+    };
+
+    let detail_style = TextStyle {
+        font: asset_server.font_future_thin(),
+        font_size: 24.0,
+        color: Color::GRAY,
+    };
+
+    commands
+        .spawn((
+            WaveTracker,
+            TextBundle {
+                style: Style { ..default() },
+                text: Text {
+                    sections: vec![
+                        TextSection::new("", title_style),
+                        TextSection::new("", detail_style),
+                    ],
+                    alignment: TextAlignment::Center,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .id()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn update_wave_tracker(
+    mut text_query: Query<(&mut Text, &mut Visibility), With<WaveTracker>>,
+    wave_state: Option<Res<WaveState>>,
+) {
+    for (mut text, mut visibility) in text_query.iter_mut() {
+        let Some(wave_state) = wave_state.as_ref() else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        *visibility = Visibility::Inherited;
+
+        match wave_state.phase() {
+            WavePhase::InProgress => {
+                text.sections[0].value = format!("WAVE {}", wave_state.wave());
+                text.sections[1].value = format!("\n{} LEFT", wave_state.remaining_enemies());
+            }
+            WavePhase::Resting => {
+                text.sections[0].value = if wave_state.wave() == 0 {
+                    String::from("GET READY")
+                } else {
+                    format!("WAVE {} CLEARED", wave_state.wave())
+                };
+                let seconds = wave_state.time_until_next_wave().unwrap_or(0.0);
+                text.sections[1].value = format!("\nNEXT WAVE IN {:.0}", seconds.ceil());
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{
+    game::{player::components::Player, weapon::Weapon},
+    ui::assets::GameFonts,
+};
+use bevy::prelude::*;
+use bevy_progressbar::{ProgressBar, ProgressBarBundle, ProgressBarMaterial};
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct WeaponBarPlugin;
+
+impl Plugin for WeaponBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_weapon_bar);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+struct WeaponBar;
+
+#[derive(Component)]
+struct HeatBar;
+
+#[derive(Component)]
+struct AmmoText;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+const HEAT_BAR_AMOUNT: u32 = 1000;
+
+/// Above this much heat the bar warns that the weapon is about to overheat.
+const HEAT_WARNING: f32 = 0.75;
+
+pub fn build(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    materials: &mut ResMut<Assets<ProgressBarMaterial>>,
+) -> Entity {
+    let bar = ProgressBar::new(vec![(HEAT_BAR_AMOUNT, Color::DARK_GRAY)]);
+
+    let style = Style {
+        width: Val::Px(160.0),
+        height: Val::Px(8.0),
+        ..default()
+    };
+
+    let heat_bar_id = commands
+        .spawn(HeatBar)
+        .insert(ProgressBarBundle::new(style, bar, materials))
+        .id();
+
+    let ammo_text_id = commands
+        .spawn((
+            AmmoText,
+            TextBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.font_future_thin(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                ..default()
+            },
+        ))
+        .id();
+
+    commands
+        .spawn((
+            WeaponBar,
+            NodeBundle {
+                style: Style {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(10.0),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .push_children(&[heat_bar_id, ammo_text_id])
+        .id()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn update_weapon_bar(
+    mut weapon_bar_query: Query<&mut Visibility, With<WeaponBar>>,
+    mut heat_bar_query: Query<(&mut ProgressBar, &mut Style), With<HeatBar>>,
+    mut ammo_text_query: Query<&mut Text, With<AmmoText>>,
+    weapon_query: Query<&Weapon, (With<Player>, Changed<Weapon>)>,
+    player_query: Query<(), With<Player>>,
+) {
+    if player_query.is_empty() {
+        for mut visibility in weapon_bar_query.iter_mut() {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    }
+
+    let Ok(weapon) = weapon_query.get_single() else {
+        return;
+    };
+    let status = weapon.status();
+
+    for mut visibility in weapon_bar_query.iter_mut() {
+        *visibility = if status.heat.is_some() || status.ammo.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    for (mut heat_bar, mut style) in heat_bar_query.iter_mut() {
+        let Some(heat) = status.heat else {
+            style.display = Display::None;
+            continue;
+        };
+        style.display = Display::Flex;
+
+        let color = if status.overheated {
+            Color::RED
+        } else if heat > HEAT_WARNING {
+            Color::ORANGE
+        } else {
+            Color::WHITE
+        };
+        let hot = (heat * HEAT_BAR_AMOUNT as f32) as u32;
+
+        heat_bar.sections = vec![(hot, color), (HEAT_BAR_AMOUNT - hot, Color::DARK_GRAY)];
+    }
+
+    for mut text in ammo_text_query.iter_mut() {
+        text.sections[0].value = match (status.ammo, status.reload, status.overheated) {
+            (_, _, true) => String::from("OVERHEATED"),
+            (_, Some(_), _) => String::from("RELOADING"),
+            (Some((rounds, capacity)), None, _) => format!("{}/{}", rounds, capacity),
+            (None, None, _) => String::new(),
+        };
+    }
+}
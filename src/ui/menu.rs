@@ -0,0 +1,233 @@
+mod controls;
+mod game_over;
+mod leaderboard;
+mod main_menu;
+mod name_entry;
+mod pause_menu;
+mod settings_menu;
+
+use crate::state::{AppState, QuitToMainMenu, RestartGame};
+use crate::ui::assets::GameFonts;
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use controls::ControlsEvent;
+use leaderboard::LeaderboardEvent;
+use settings_menu::SettingsMenuEvent;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            main_menu::MainMenuPlugin,
+            controls::ControlsPlugin,
+            settings_menu::SettingsMenuPlugin,
+            pause_menu::PauseMenuPlugin,
+            game_over::GameOverPlugin,
+            leaderboard::LeaderboardPlugin,
+            name_entry::NameEntryPlugin,
+        ))
+        .add_systems(Update, (press_menu_buttons, highlight_menu_buttons));
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+/// What a menu button does when it is pressed.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuButton {
+    Play,
+    Resume,
+    Restart,
+    MainMenu,
+    HighScores,
+    Settings,
+    Controls,
+    ResetControls,
+    Back,
+    Quit,
+}
+
+impl MenuButton {
+    fn label(&self) -> &'static str {
+        match self {
+            MenuButton::Play => "PLAY",
+            MenuButton::Resume => "RESUME",
+            MenuButton::Restart => "RESTART",
+            MenuButton::MainMenu => "MAIN MENU",
+            MenuButton::HighScores => "HIGH SCORES",
+            MenuButton::Settings => "SETTINGS",
+            MenuButton::Controls => "CONTROLS",
+            MenuButton::ResetControls => "RESET",
+            MenuButton::Back => "BACK",
+            MenuButton::Quit => "QUIT",
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+const SCREEN_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const BUTTON_COLOR: Color = Color::rgba(0.25, 0.25, 0.25, 0.8);
+const BUTTON_HOVER_COLOR: Color = Color::rgba(0.4, 0.4, 0.4, 0.9);
+const BUTTON_PRESSED_COLOR: Color = Color::rgba(0.3, 0.5, 0.8, 0.9);
+
+/// A screen covering the whole window with a title on top. Children are
+/// stacked below the title.
+fn build_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    label: impl Component,
+    title: &str,
+) -> Entity {
+    let title_style = TextStyle {
+        font: asset_server.font_future(),
+        font_size: 64.0,
+        color: Color::WHITE,
+    };
+
+    let title_id = commands
+        .spawn(TextBundle::from_section(title, title_style))
+        .id();
+
+    commands
+        .spawn((
+            label,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(16.0),
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                background_color: SCREEN_COLOR.into(),
+                // Above the HUD
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+        ))
+        .add_child(title_id)
+        .id()
+}
+
+fn build_text(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    text: &str,
+    font_size: f32,
+) -> Entity {
+    let style = TextStyle {
+        font: asset_server.font_future_thin(),
+        font_size,
+        color: Color::GRAY,
+    };
+
+    commands.spawn(TextBundle::from_section(text, style)).id()
+}
+
+fn build_button(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    button: MenuButton,
+) -> Entity {
+    let text_style = TextStyle {
+        font: asset_server.font_future(),
+        font_size: 28.0,
+        color: Color::WHITE,
+    };
+
+    let text_id = commands
+        .spawn(TextBundle::from_section(button.label(), text_style))
+        .id();
+
+    commands
+        .spawn((
+            button,
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(280.0),
+                    height: Val::Px(56.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+        ))
+        .add_child(text_id)
+        .id()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+/// The screens that open on top of a menu.
+#[derive(SystemParam)]
+struct OverlayEvents<'w> {
+    leaderboard: EventWriter<'w, LeaderboardEvent>,
+    settings: EventWriter<'w, SettingsMenuEvent>,
+    controls: EventWriter<'w, ControlsEvent>,
+}
+
+fn press_menu_buttons(
+    interaction_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut restart_events: EventWriter<RestartGame>,
+    mut quit_to_main_menu_events: EventWriter<QuitToMainMenu>,
+    mut overlay_events: OverlayEvents,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            MenuButton::Play | MenuButton::Restart => restart_events.send(RestartGame),
+            MenuButton::Resume => next_state.set(AppState::Playing),
+            MenuButton::MainMenu => quit_to_main_menu_events.send(QuitToMainMenu),
+            MenuButton::HighScores => overlay_events
+                .leaderboard
+                .send(LeaderboardEvent::Open { highlight: None }),
+            MenuButton::Settings => overlay_events.settings.send(SettingsMenuEvent::Open),
+            MenuButton::Controls => overlay_events.controls.send(ControlsEvent::Open),
+            MenuButton::ResetControls => overlay_events.controls.send(ControlsEvent::Reset),
+            MenuButton::Back => {
+                overlay_events.leaderboard.send(LeaderboardEvent::Close);
+                overlay_events.settings.send(SettingsMenuEvent::Close);
+                overlay_events.controls.send(ControlsEvent::Close);
+            }
+            MenuButton::Quit => app_exit_events.send(AppExit),
+        }
+    }
+}
+
+type ButtonColorQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<Button>),
+>;
+
+fn highlight_menu_buttons(mut button_query: ButtonColorQuery) {
+    for (interaction, mut background_color) in button_query.iter_mut() {
+        *background_color = match interaction {
+            Interaction::Pressed => BUTTON_PRESSED_COLOR,
+            Interaction::Hovered => BUTTON_HOVER_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        }
+        .into();
+    }
+}
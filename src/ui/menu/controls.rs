@@ -0,0 +1,365 @@
+use super::{build_button, build_screen, build_text, MenuButton};
+use crate::game::player::PlayerShipAction;
+use crate::settings::keybindings::{Binding, Rebindable};
+use crate::settings::{Settings, SettingsFile};
+use crate::state::AppState;
+use crate::ui::assets::GameFonts;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use leafwing_input_manager::plugin::InputManagerSystem;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ControlsEvent>()
+            .add_systems(OnExit(AppState::MainMenu), close_controls)
+            .add_systems(OnExit(AppState::Paused), close_controls)
+            .add_systems(
+                PreUpdate,
+                // Before anything else sees the input, so it is only used to rebind
+                (
+                    capture_binding.run_if(resource_exists::<Rebinding>()),
+                    close_controls_on_escape.run_if(controls_are_open),
+                )
+                    .chain()
+                    .after(InputSystem)
+                    .before(InputManagerSystem::Update),
+            )
+            .add_systems(
+                Update,
+                (
+                    open_and_close_controls,
+                    start_rebinding.run_if(controls_are_open),
+                    update_action_rows.run_if(controls_are_open),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Condition for systems that should not react to input while the controls
+/// are being changed.
+pub fn controls_are_open(query: Query<(), With<ControlsScreen>>) -> bool {
+    !query.is_empty()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlsEvent {
+    Open,
+    /// Close the controls and save the keybindings.
+    Close,
+    /// Go back to the default keybindings for the game.
+    Reset,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Resources
+////////////////////////////////////////////////////////////////////////////////
+
+/// Waiting for the player to press the input to bind to `action`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+struct Rebinding {
+    action: PlayerShipAction,
+}
+
+/// The actions on the controls screen. Turning with the stick is an axis and
+/// can only be changed in the settings file.
+const ACTIONS: [PlayerShipAction; 6] = [
+    PlayerShipAction::ThrottleForward,
+    PlayerShipAction::ThrottleBackwards,
+    PlayerShipAction::RotateShipLeft,
+    PlayerShipAction::RotateShipRight,
+    PlayerShipAction::FireWeapon,
+    PlayerShipAction::Boost,
+];
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+pub struct ControlsScreen;
+
+/// Press to rebind the action.
+#[derive(Component)]
+struct ActionButton(PlayerShipAction);
+
+#[derive(Component)]
+struct BindingsText(PlayerShipAction);
+
+#[derive(Component)]
+struct ConflictsText;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+const CONFLICT_COLOR: Color = Color::rgb(1.0, 0.3, 0.3);
+const WAITING_COLOR: Color = Color::rgb(1.0, 0.9, 0.2);
+
+fn spawn(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+    let screen_id = build_screen(commands, asset_server, ControlsScreen, "CONTROLS");
+    commands
+        .entity(screen_id)
+        .insert(ZIndex::Global(20))
+        .insert(BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.9)));
+
+    let row_ids: Vec<Entity> = ACTIONS
+        .iter()
+        .map(|action| build_action_row(commands, asset_server, *action))
+        .collect();
+    let conflicts_id = commands
+        .spawn((
+            ConflictsText,
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.font_future_thin(),
+                    font_size: 20.0,
+                    color: CONFLICT_COLOR,
+                },
+            ),
+        ))
+        .id();
+    let hint_id = build_text(
+        commands,
+        asset_server,
+        "Click an action, then press a key or button. Escape to cancel",
+        20.0,
+    );
+    let reset_id = build_button(commands, asset_server, MenuButton::ResetControls);
+    let back_id = build_button(commands, asset_server, MenuButton::Back);
+
+    commands.entity(screen_id).push_children(&row_ids);
+    commands
+        .entity(screen_id)
+        .push_children(&[conflicts_id, hint_id, reset_id, back_id]);
+}
+
+fn build_action_row(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    action: PlayerShipAction,
+) -> Entity {
+    let style = TextStyle {
+        font: asset_server.font_future_thin(),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+
+    let name_id = commands
+        .spawn(
+            TextBundle::from_section(action_label(action), style.clone()).with_style(Style {
+                width: Val::Px(300.0),
+                ..default()
+            }),
+        )
+        .id();
+    let bindings_id = commands
+        .spawn((
+            BindingsText(action),
+            TextBundle::from_section("", style).with_style(Style {
+                width: Val::Px(400.0),
+                ..default()
+            }),
+        ))
+        .id();
+
+    commands
+        .spawn((
+            ActionButton(action),
+            ButtonBundle {
+                style: Style {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::horizontal(Val::Px(16.0)),
+                    height: Val::Px(40.0),
+                    ..default()
+                },
+                background_color: super::BUTTON_COLOR.into(),
+                ..default()
+            },
+        ))
+        .push_children(&[name_id, bindings_id])
+        .id()
+}
+
+/// `ThrottleForward` as `THROTTLE FORWARD`.
+fn action_label(action: PlayerShipAction) -> String {
+    let mut label = String::new();
+    for (index, char) in format!("{:?}", action).chars().enumerate() {
+        if index > 0 && char.is_uppercase() {
+            label.push(' ');
+        }
+        label.push(char.to_ascii_uppercase());
+    }
+    label
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn open_and_close_controls(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut controls_events: EventReader<ControlsEvent>,
+    mut settings: ResMut<Settings>,
+    settings_file: Res<SettingsFile>,
+    screen_query: Query<Entity, With<ControlsScreen>>,
+) {
+    for event in controls_events.read() {
+        match event {
+            ControlsEvent::Open => {
+                if screen_query.is_empty() {
+                    spawn(&mut commands, &asset_server);
+                }
+            }
+            ControlsEvent::Close => {
+                if screen_query.is_empty() {
+                    continue;
+                }
+                commands.remove_resource::<Rebinding>();
+                for entity in screen_query.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+
+                let keybindings = settings.keybindings.clone();
+                if let Err(err) = settings_file.update(|saved| saved.keybindings = keybindings) {
+                    eprintln!("Error saving the keybindings: {}", err);
+                }
+            }
+            ControlsEvent::Reset => {
+                commands.remove_resource::<Rebinding>();
+                settings.keybindings.game = PlayerShipAction::default_bindings();
+            }
+        }
+    }
+}
+
+fn start_rebinding(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &ActionButton), Changed<Interaction>>,
+) {
+    for (interaction, ActionButton(action)) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            commands.insert_resource(Rebinding { action: *action });
+        }
+    }
+}
+
+/// Bind the first key or button pressed to the action being rebound. The
+/// input is used up, so it does not also do something else this frame.
+fn capture_binding(
+    mut commands: Commands,
+    rebinding: Res<Rebinding>,
+    mut settings: ResMut<Settings>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+) {
+    // The click that started rebinding was last frame, so it is not pressed
+    // again here
+    if rebinding.is_added() {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        keyboard_input.reset(KeyCode::Escape);
+        commands.remove_resource::<Rebinding>();
+        return;
+    }
+
+    let key = keyboard_input.get_just_pressed().next().copied();
+    let mouse_button = mouse_input.get_just_pressed().next().copied();
+    let binding = if let Some(key) = key {
+        keyboard_input.reset(key);
+        Binding::Key(key)
+    } else if let Some(button) = mouse_button {
+        mouse_input.reset(button);
+        Binding::Mouse(button)
+    } else if let Some(button) = gamepad_input.get_just_pressed().next() {
+        Binding::GamepadButton(button.button_type)
+    } else {
+        return;
+    };
+
+    settings.keybindings.game.rebind(rebinding.action, binding);
+    commands.remove_resource::<Rebinding>();
+}
+
+fn close_controls_on_escape(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut controls_events: EventWriter<ControlsEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        keyboard_input.reset(KeyCode::Escape);
+        controls_events.send(ControlsEvent::Close);
+    }
+}
+
+fn close_controls(mut controls_events: EventWriter<ControlsEvent>) {
+    controls_events.send(ControlsEvent::Close);
+}
+
+fn update_action_rows(
+    settings: Res<Settings>,
+    rebinding: Option<Res<Rebinding>>,
+    mut bindings_query: Query<(&BindingsText, &mut Text), Without<ConflictsText>>,
+    mut conflicts_query: Query<&mut Text, With<ConflictsText>>,
+) {
+    let bindings = &settings.keybindings.game;
+    let conflicts = bindings.conflicts();
+
+    for (BindingsText(action), mut text) in bindings_query.iter_mut() {
+        let waiting = rebinding
+            .as_ref()
+            .is_some_and(|rebinding| rebinding.action == *action);
+        let conflicting = conflicts
+            .iter()
+            .any(|(_, actions)| actions.contains(&format!("{:?}", action)));
+
+        let (value, color) = if waiting {
+            ("PRESS A KEY OR BUTTON".to_string(), WAITING_COLOR)
+        } else {
+            let names: Vec<String> = bindings
+                .get(*action)
+                .iter()
+                .map(Binding::to_string)
+                .collect();
+            let color = if conflicting {
+                CONFLICT_COLOR
+            } else {
+                Color::WHITE
+            };
+            (names.join(", "), color)
+        };
+
+        let section = &text.sections[0];
+        if section.value != value || section.style.color != color {
+            text.sections[0].value = value;
+            text.sections[0].style.color = color;
+        }
+    }
+
+    for mut text in conflicts_query.iter_mut() {
+        let value: Vec<String> = conflicts
+            .iter()
+            .map(|(binding, actions)| format!("{} is bound to {}", binding, actions.join(" and ")))
+            .collect();
+        let value = value.join("\n");
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
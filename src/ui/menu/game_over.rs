@@ -0,0 +1,73 @@
+use super::name_entry::is_entering_name;
+use super::{build_button, build_screen, build_text, MenuButton};
+use crate::game::score::GameScore;
+use crate::state::{AppState, RestartGame};
+use crate::utility_systems::cleanup;
+use bevy::prelude::*;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameOver), spawn)
+            .add_systems(OnExit(AppState::GameOver), cleanup::<GameOverScreen>)
+            .add_systems(
+                Update,
+                restart_on_key
+                    .run_if(in_state(AppState::GameOver))
+                    .run_if(not(is_entering_name)),
+            );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+struct GameOverScreen;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+fn spawn(mut commands: Commands, asset_server: Res<AssetServer>, game_score: Res<GameScore>) {
+    let screen_id = build_screen(&mut commands, &asset_server, GameOverScreen, "GAME OVER");
+    let score_id = build_text(
+        &mut commands,
+        &asset_server,
+        &format!("SCORE {}", game_score.total()),
+        40.0,
+    );
+    let restart_id = build_button(&mut commands, &asset_server, MenuButton::Restart);
+    let main_menu_id = build_button(&mut commands, &asset_server, MenuButton::MainMenu);
+    let high_scores_id = build_button(&mut commands, &asset_server, MenuButton::HighScores);
+    let quit_id = build_button(&mut commands, &asset_server, MenuButton::Quit);
+    let hint_id = build_text(&mut commands, &asset_server, "R to restart", 20.0);
+
+    commands.entity(screen_id).push_children(&[
+        score_id,
+        restart_id,
+        main_menu_id,
+        high_scores_id,
+        quit_id,
+        hint_id,
+    ]);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn restart_on_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut restart_events: EventWriter<RestartGame>,
+) {
+    if keyboard_input.just_pressed(KeyCode::R) {
+        restart_events.send(RestartGame);
+    }
+}
@@ -0,0 +1,222 @@
+use super::{build_button, build_screen, MenuButton};
+use crate::game::score::{HighScore, HighScores};
+use crate::state::AppState;
+use crate::ui::assets::GameFonts;
+use crate::utility_systems::cleanup;
+use bevy::prelude::*;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LeaderboardEvent>()
+            .add_systems(OnExit(AppState::MainMenu), cleanup::<LeaderboardScreen>)
+            .add_systems(OnExit(AppState::GameOver), cleanup::<LeaderboardScreen>)
+            .add_systems(
+                Update,
+                (
+                    open_and_close_leaderboard,
+                    close_leaderboard_on_escape.run_if(leaderboard_is_open),
+                    update_leaderboard_rows.after(open_and_close_leaderboard),
+                ),
+            );
+    }
+}
+
+/// Condition for systems that should not react to input while the
+/// leaderboard is open.
+pub fn leaderboard_is_open(query: Query<(), With<LeaderboardScreen>>) -> bool {
+    !query.is_empty()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaderboardEvent {
+    /// Show the leaderboard, with the score at `highlight` stood out.
+    Open {
+        highlight: Option<usize>,
+    },
+    Close,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+pub struct LeaderboardScreen;
+
+/// The rows of the leaderboard are rebuilt whenever the high scores change.
+#[derive(Component)]
+struct LeaderboardRows {
+    highlight: Option<usize>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+const HIGHLIGHT_COLOR: Color = Color::rgb(1.0, 0.9, 0.2);
+
+/// The width of the rank, name, score, date, level and seed columns.
+const COLUMN_WIDTHS: [f32; 6] = [60.0, 220.0, 140.0, 160.0, 140.0, 200.0];
+
+fn spawn(commands: &mut Commands, asset_server: &Res<AssetServer>, highlight: Option<usize>) {
+    let screen_id = build_screen(commands, asset_server, LeaderboardScreen, "HIGH SCORES");
+    commands
+        .entity(screen_id)
+        .insert(ZIndex::Global(20))
+        .insert(BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.9)));
+
+    let header_id = build_row(
+        commands,
+        asset_server,
+        ["#", "NAME", "SCORE", "DATE", "LEVEL", "SEED"].map(String::from),
+        Color::GRAY,
+    );
+    let rows_id = commands
+        .spawn((
+            LeaderboardRows { highlight },
+            NodeBundle {
+                style: Style {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .id();
+    let back_id = build_button(commands, asset_server, MenuButton::Back);
+
+    commands
+        .entity(screen_id)
+        .push_children(&[header_id, rows_id, back_id]);
+}
+
+fn build_row(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    columns: [String; 6],
+    color: Color,
+) -> Entity {
+    let style = TextStyle {
+        font: asset_server.font_future_thin(),
+        font_size: 24.0,
+        color,
+    };
+
+    let column_ids: Vec<Entity> = columns
+        .into_iter()
+        .zip(COLUMN_WIDTHS)
+        .map(|(text, width)| {
+            commands
+                .spawn(
+                    TextBundle::from_section(text, style.clone()).with_style(Style {
+                        width: Val::Px(width),
+                        ..default()
+                    }),
+                )
+                .id()
+        })
+        .collect();
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            ..default()
+        })
+        .push_children(&column_ids)
+        .id()
+}
+
+fn columns(rank: usize, high_score: &HighScore) -> [String; 6] {
+    [
+        format!("{}", rank + 1),
+        high_score.player_name.clone(),
+        format!("{}", high_score.score),
+        high_score.date_string(),
+        high_score.level.clone(),
+        high_score
+            .seed
+            .map(|seed| seed.to_string())
+            .unwrap_or_default(),
+    ]
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn open_and_close_leaderboard(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut leaderboard_events: EventReader<LeaderboardEvent>,
+    screen_query: Query<Entity, With<LeaderboardScreen>>,
+) {
+    for event in leaderboard_events.read() {
+        for entity in screen_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        if let LeaderboardEvent::Open { highlight } = event {
+            spawn(&mut commands, &asset_server, *highlight);
+        }
+    }
+}
+
+fn close_leaderboard_on_escape(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut leaderboard_events: EventWriter<LeaderboardEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        leaderboard_events.send(LeaderboardEvent::Close);
+    }
+}
+
+fn update_leaderboard_rows(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    high_scores: Res<HighScores>,
+    rows_query: Query<(Entity, Ref<LeaderboardRows>)>,
+) {
+    for (entity, rows) in rows_query.iter() {
+        if !rows.is_added() && !high_scores.is_changed() {
+            continue;
+        }
+
+        commands.entity(entity).despawn_descendants();
+
+        let row_ids: Vec<Entity> = high_scores
+            .iter()
+            .enumerate()
+            .map(|(rank, high_score)| {
+                let color = if rows.highlight == Some(rank) {
+                    HIGHLIGHT_COLOR
+                } else {
+                    Color::WHITE
+                };
+                build_row(
+                    &mut commands,
+                    &asset_server,
+                    columns(rank, high_score),
+                    color,
+                )
+            })
+            .collect();
+
+        commands.entity(entity).push_children(&row_ids);
+    }
+}
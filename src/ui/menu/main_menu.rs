@@ -0,0 +1,79 @@
+use super::controls::controls_are_open;
+use super::leaderboard::leaderboard_is_open;
+use super::settings_menu::settings_menu_is_open;
+use super::{build_button, build_screen, build_text, MenuButton};
+use crate::state::{AppState, RestartGame};
+use crate::utility_systems::{cleanup, exit_game};
+use bevy::prelude::*;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::MainMenu), spawn)
+            .add_systems(OnExit(AppState::MainMenu), cleanup::<MainMenuScreen>)
+            .add_systems(
+                Update,
+                (play_on_enter, exit_game)
+                    .run_if(in_state(AppState::MainMenu))
+                    .run_if(not(leaderboard_is_open))
+                    .run_if(not(controls_are_open))
+                    .run_if(not(settings_menu_is_open)),
+            );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+struct MainMenuScreen;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // There is no scene, and so no camera, behind the main menu
+    commands.spawn((MainMenuScreen, Camera2dBundle::default()));
+
+    let screen_id = build_screen(&mut commands, &asset_server, MainMenuScreen, "SPACE GAME");
+    let play_id = build_button(&mut commands, &asset_server, MenuButton::Play);
+    let high_scores_id = build_button(&mut commands, &asset_server, MenuButton::HighScores);
+    let settings_id = build_button(&mut commands, &asset_server, MenuButton::Settings);
+    let controls_id = build_button(&mut commands, &asset_server, MenuButton::Controls);
+    let quit_id = build_button(&mut commands, &asset_server, MenuButton::Quit);
+    let hint_id = build_text(
+        &mut commands,
+        &asset_server,
+        "Enter to play, Escape to quit",
+        20.0,
+    );
+
+    commands.entity(screen_id).push_children(&[
+        play_id,
+        high_scores_id,
+        settings_id,
+        controls_id,
+        quit_id,
+        hint_id,
+    ]);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn play_on_enter(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut restart_events: EventWriter<RestartGame>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        restart_events.send(RestartGame);
+    }
+}
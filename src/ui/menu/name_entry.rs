@@ -0,0 +1,175 @@
+use super::leaderboard::LeaderboardEvent;
+use super::{build_screen, build_text};
+use crate::game::events::{HighScoreEvent, HighScoreSubmitted};
+use crate::state::AppState;
+use crate::ui::assets::GameFonts;
+use bevy::prelude::*;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct NameEntryPlugin;
+
+impl Plugin for NameEntryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnExit(AppState::GameOver), stop_name_entry)
+            .add_systems(
+                Update,
+                (
+                    start_name_entry,
+                    spawn.run_if(resource_added::<NameEntry>()),
+                    type_name.run_if(resource_exists::<NameEntry>()),
+                    update_name_text.run_if(resource_exists_and_changed::<NameEntry>()),
+                )
+                    .chain(),
+            );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Resources
+////////////////////////////////////////////////////////////////////////////////
+
+/// A high score waiting for the player to enter a name.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct NameEntry {
+    placement: usize,
+    score: u64,
+    name: String,
+}
+
+const MAX_NAME_LENGTH: usize = 12;
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+struct NameEntryScreen;
+
+#[derive(Component)]
+struct NameText;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+fn spawn(mut commands: Commands, asset_server: Res<AssetServer>, name_entry: Res<NameEntry>) {
+    let screen_id = build_screen(
+        &mut commands,
+        &asset_server,
+        NameEntryScreen,
+        "NEW HIGH SCORE",
+    );
+    commands
+        .entity(screen_id)
+        .insert(ZIndex::Global(20))
+        .insert(BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.9)));
+
+    let placement_id = build_text(
+        &mut commands,
+        &asset_server,
+        &format!("#{}   {}", name_entry.placement + 1, name_entry.score),
+        40.0,
+    );
+    let name_id = commands
+        .spawn((
+            NameText,
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.font_future(),
+                    font_size: 48.0,
+                    color: Color::WHITE,
+                },
+            ),
+        ))
+        .id();
+    let hint_id = build_text(
+        &mut commands,
+        &asset_server,
+        "Type your name, Enter to save, Escape to skip",
+        20.0,
+    );
+
+    commands
+        .entity(screen_id)
+        .push_children(&[placement_id, name_id, hint_id]);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn start_name_entry(mut commands: Commands, mut high_score_events: EventReader<HighScoreEvent>) {
+    if let Some(event) = high_score_events.read().last() {
+        commands.insert_resource(NameEntry {
+            placement: event.placement,
+            score: event.score,
+            name: event.player_name.clone(),
+        });
+    }
+}
+
+fn type_name(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut character_events: EventReader<ReceivedCharacter>,
+    mut name_entry: ResMut<NameEntry>,
+    mut submitted_events: EventWriter<HighScoreSubmitted>,
+    mut leaderboard_events: EventWriter<LeaderboardEvent>,
+    screen_query: Query<Entity, With<NameEntryScreen>>,
+) {
+    for event in character_events.read() {
+        let allowed = event.char.is_alphanumeric() || event.char == ' ' || event.char == '-';
+        if allowed && name_entry.name.chars().count() < MAX_NAME_LENGTH {
+            name_entry.name.push(event.char);
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        name_entry.name.pop();
+    }
+
+    let submit = keyboard_input.just_pressed(KeyCode::Return);
+    let skip = keyboard_input.just_pressed(KeyCode::Escape);
+    if !submit && !skip {
+        return;
+    }
+
+    if submit {
+        let name = name_entry.name.trim();
+        submitted_events.send(HighScoreSubmitted {
+            player_name: if name.is_empty() { "?" } else { name }.to_string(),
+            score: name_entry.score,
+        });
+        leaderboard_events.send(LeaderboardEvent::Open {
+            highlight: Some(name_entry.placement),
+        });
+    }
+
+    commands.remove_resource::<NameEntry>();
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn update_name_text(name_entry: Res<NameEntry>, mut text_query: Query<&mut Text, With<NameText>>) {
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!("{}_", name_entry.name);
+    }
+}
+
+fn stop_name_entry(mut commands: Commands, screen_query: Query<Entity, With<NameEntryScreen>>) {
+    commands.remove_resource::<NameEntry>();
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Condition for systems that should not react to input while a name is
+/// being typed.
+pub fn is_entering_name(name_entry: Option<Res<NameEntry>>) -> bool {
+    name_entry.is_some()
+}
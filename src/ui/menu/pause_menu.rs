@@ -0,0 +1,74 @@
+use super::controls::controls_are_open;
+use super::settings_menu::settings_menu_is_open;
+use super::{build_button, build_screen, build_text, MenuButton};
+use crate::state::{AppState, RestartGame};
+use crate::utility_systems::cleanup;
+use bevy::prelude::*;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Paused), spawn)
+            .add_systems(OnExit(AppState::Paused), cleanup::<PauseScreen>)
+            .add_systems(
+                Update,
+                restart_on_key
+                    .run_if(in_state(AppState::Paused))
+                    .run_if(not(controls_are_open))
+                    .run_if(not(settings_menu_is_open)),
+            );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+struct PauseScreen;
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let screen_id = build_screen(&mut commands, &asset_server, PauseScreen, "PAUSED");
+    let resume_id = build_button(&mut commands, &asset_server, MenuButton::Resume);
+    let restart_id = build_button(&mut commands, &asset_server, MenuButton::Restart);
+    let settings_id = build_button(&mut commands, &asset_server, MenuButton::Settings);
+    let controls_id = build_button(&mut commands, &asset_server, MenuButton::Controls);
+    let main_menu_id = build_button(&mut commands, &asset_server, MenuButton::MainMenu);
+    let hint_id = build_text(
+        &mut commands,
+        &asset_server,
+        "Escape to resume, R to restart",
+        20.0,
+    );
+
+    commands.entity(screen_id).push_children(&[
+        resume_id,
+        restart_id,
+        settings_id,
+        controls_id,
+        main_menu_id,
+        hint_id,
+    ]);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn restart_on_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut restart_events: EventWriter<RestartGame>,
+) {
+    if keyboard_input.just_pressed(KeyCode::R) {
+        restart_events.send(RestartGame);
+    }
+}
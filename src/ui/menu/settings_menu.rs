@@ -0,0 +1,463 @@
+use super::{build_button, build_screen, build_text, MenuButton, BUTTON_COLOR};
+use crate::game::{accessibility::Palette, player::FireMode};
+use crate::settings::{MsaaSetting, Settings, SettingsFile, WindowModeSetting};
+use crate::state::AppState;
+use crate::ui::assets::GameFonts;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+////////////////////////////////////////////////////////////////////////////////
+// Plugin
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SettingsMenuEvent>()
+            .add_systems(OnExit(AppState::MainMenu), close_settings_menu)
+            .add_systems(OnExit(AppState::Paused), close_settings_menu)
+            .add_systems(
+                PreUpdate,
+                // Before the pause menu sees the Escape and resumes the game
+                close_settings_menu_on_escape
+                    .run_if(settings_menu_is_open)
+                    .after(InputSystem),
+            )
+            .add_systems(
+                Update,
+                (
+                    open_and_close_settings_menu,
+                    press_setting_buttons.run_if(settings_menu_is_open),
+                    update_setting_values.run_if(settings_menu_is_open),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Condition for systems that should not react to input while the settings
+/// menu is open.
+pub fn settings_menu_is_open(query: Query<(), With<SettingsMenuScreen>>) -> bool {
+    !query.is_empty()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Events
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsMenuEvent {
+    Open,
+    /// Close the menu and save the settings.
+    Close,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Options
+////////////////////////////////////////////////////////////////////////////////
+
+/// A setting in the menu. Changes are applied right away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingOption {
+    WindowMode,
+    Vsync,
+    Msaa,
+    UiScale,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    ScreenShake,
+    Palette,
+    HighContrast,
+    FireMode,
+}
+
+const OPTIONS: [SettingOption; 11] = [
+    SettingOption::WindowMode,
+    SettingOption::Vsync,
+    SettingOption::Msaa,
+    SettingOption::UiScale,
+    SettingOption::MasterVolume,
+    SettingOption::MusicVolume,
+    SettingOption::SfxVolume,
+    SettingOption::ScreenShake,
+    SettingOption::Palette,
+    SettingOption::HighContrast,
+    SettingOption::FireMode,
+];
+
+const WINDOW_MODES: [WindowModeSetting; 3] = [
+    WindowModeSetting::Windowed,
+    WindowModeSetting::Borderless,
+    WindowModeSetting::Fullscreen,
+];
+
+const MSAA_LEVELS: [MsaaSetting; 4] = [
+    MsaaSetting::Off,
+    MsaaSetting::Sample2,
+    MsaaSetting::Sample4,
+    MsaaSetting::Sample8,
+];
+
+const PALETTES: [Palette; 4] = [
+    Palette::Default,
+    Palette::Deuteranopia,
+    Palette::Protanopia,
+    Palette::Tritanopia,
+];
+
+const FIRE_MODES: [FireMode; 2] = [FireMode::Hold, FireMode::Toggle];
+
+impl SettingOption {
+    fn label(&self) -> &'static str {
+        match self {
+            SettingOption::WindowMode => "WINDOW",
+            SettingOption::Vsync => "VSYNC",
+            SettingOption::Msaa => "ANTI-ALIASING",
+            SettingOption::UiScale => "UI SCALE",
+            SettingOption::MasterVolume => "MASTER VOLUME",
+            SettingOption::MusicVolume => "MUSIC VOLUME",
+            SettingOption::SfxVolume => "EFFECTS VOLUME",
+            SettingOption::ScreenShake => "SCREEN SHAKE",
+            SettingOption::Palette => "COLORS",
+            SettingOption::HighContrast => "OUTLINES",
+            SettingOption::FireMode => "FIRE BUTTON",
+        }
+    }
+
+    fn value(&self, settings: &Settings) -> String {
+        let percent = |value: f32| format!("{}%", (value * 100.0).round());
+
+        match self {
+            SettingOption::WindowMode => match settings.window.mode {
+                WindowModeSetting::Windowed => "WINDOWED",
+                WindowModeSetting::Borderless => "BORDERLESS",
+                WindowModeSetting::Fullscreen => "FULLSCREEN",
+            }
+            .to_string(),
+            SettingOption::Vsync => on_off(settings.window.vsync),
+            SettingOption::Msaa => match settings.window.msaa {
+                MsaaSetting::Off => "OFF",
+                MsaaSetting::Sample2 => "2X",
+                MsaaSetting::Sample4 => "4X",
+                MsaaSetting::Sample8 => "8X",
+            }
+            .to_string(),
+            SettingOption::UiScale => percent(settings.window.ui_scale),
+            SettingOption::MasterVolume => percent(settings.audio.master_volume),
+            SettingOption::MusicVolume => percent(settings.audio.music_volume),
+            SettingOption::SfxVolume => percent(settings.audio.sfx_volume),
+            SettingOption::ScreenShake => percent(settings.accessibility.screen_shake),
+            SettingOption::Palette => match settings.accessibility.palette {
+                Palette::Default => "DEFAULT",
+                Palette::Deuteranopia => "DEUTERANOPIA",
+                Palette::Protanopia => "PROTANOPIA",
+                Palette::Tritanopia => "TRITANOPIA",
+            }
+            .to_string(),
+            SettingOption::HighContrast => on_off(settings.accessibility.high_contrast),
+            SettingOption::FireMode => match settings.accessibility.fire_mode {
+                FireMode::Hold => "HOLD",
+                FireMode::Toggle => "TOGGLE",
+            }
+            .to_string(),
+        }
+    }
+
+    /// Change the setting `step` places up or down. Lists wrap around,
+    /// numbers stop at their limits.
+    fn adjust(&self, settings: &mut Settings, step: i32) {
+        match self {
+            SettingOption::WindowMode => {
+                settings.window.mode = cycle(&WINDOW_MODES, settings.window.mode, step)
+            }
+            SettingOption::Vsync => settings.window.vsync = !settings.window.vsync,
+            SettingOption::Msaa => {
+                settings.window.msaa = cycle(&MSAA_LEVELS, settings.window.msaa, step)
+            }
+            SettingOption::UiScale => {
+                settings.window.ui_scale =
+                    adjust_percent(settings.window.ui_scale, 25 * step, 50, 200)
+            }
+            SettingOption::MasterVolume => {
+                settings.audio.master_volume =
+                    adjust_percent(settings.audio.master_volume, 10 * step, 0, 100)
+            }
+            SettingOption::MusicVolume => {
+                settings.audio.music_volume =
+                    adjust_percent(settings.audio.music_volume, 10 * step, 0, 100)
+            }
+            SettingOption::SfxVolume => {
+                settings.audio.sfx_volume =
+                    adjust_percent(settings.audio.sfx_volume, 10 * step, 0, 100)
+            }
+            SettingOption::ScreenShake => {
+                settings.accessibility.screen_shake =
+                    adjust_percent(settings.accessibility.screen_shake, 10 * step, 0, 100)
+            }
+            SettingOption::Palette => {
+                settings.accessibility.palette =
+                    cycle(&PALETTES, settings.accessibility.palette, step)
+            }
+            SettingOption::HighContrast => {
+                settings.accessibility.high_contrast = !settings.accessibility.high_contrast
+            }
+            SettingOption::FireMode => {
+                settings.accessibility.fire_mode =
+                    cycle(&FIRE_MODES, settings.accessibility.fire_mode, step)
+            }
+        }
+    }
+}
+
+fn on_off(value: bool) -> String {
+    if value { "ON" } else { "OFF" }.to_string()
+}
+
+fn cycle<T: Copy + PartialEq>(values: &[T], current: T, step: i32) -> T {
+    let index = values
+        .iter()
+        .position(|value| *value == current)
+        .unwrap_or(0) as i32;
+    values[(index + step).rem_euclid(values.len() as i32) as usize]
+}
+
+/// Add `step` percent to a fraction, staying between `min` and `max` percent. The result is rounded to a whole percent.
+fn adjust_percent(value: f32, step: i32, min: i32, max: i32) -> f32 {
+    let percent = (value * 100.0).round() as i32 + step;
+    percent.clamp(min, max) as f32 / 100.0
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Components
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Component)]
+pub struct SettingsMenuScreen;
+
+/// Changes the setting by `step` when pressed.
+#[derive(Component)]
+struct SettingButton {
+    option: SettingOption,
+    step: i32,
+}
+
+#[derive(Component)]
+struct SettingValueText(SettingOption);
+
+////////////////////////////////////////////////////////////////////////////////
+// Builders
+////////////////////////////////////////////////////////////////////////////////
+
+fn spawn(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+    let screen_id = build_screen(commands, asset_server, SettingsMenuScreen, "SETTINGS");
+    commands
+        .entity(screen_id)
+        .insert(ZIndex::Global(20))
+        .insert(BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.9)));
+
+    let row_ids: Vec<Entity> = OPTIONS
+        .iter()
+        .map(|option| build_option_row(commands, asset_server, *option))
+        .collect();
+    // Closer together than the rest of the screen, so all rows fit
+    let rows_id = commands
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .push_children(&row_ids)
+        .id();
+    let hint_id = build_text(
+        commands,
+        asset_server,
+        "Changes are applied right away and saved when you go back",
+        20.0,
+    );
+    let back_id = build_button(commands, asset_server, MenuButton::Back);
+
+    commands
+        .entity(screen_id)
+        .push_children(&[rows_id, hint_id, back_id]);
+}
+
+fn build_option_row(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    option: SettingOption,
+) -> Entity {
+    let style = TextStyle {
+        font: asset_server.font_future_thin(),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+
+    let label_id = commands
+        .spawn(
+            TextBundle::from_section(option.label(), style.clone()).with_style(Style {
+                width: Val::Px(300.0),
+                ..default()
+            }),
+        )
+        .id();
+    let decrease_id = build_arrow_button(commands, style.clone(), option, -1);
+    let value_id = commands
+        .spawn((
+            SettingValueText(option),
+            TextBundle::from_section("", style.clone())
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    width: Val::Px(200.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                }),
+        ))
+        .id();
+    let increase_id = build_arrow_button(commands, style, option, 1);
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(8.0),
+                ..default()
+            },
+            ..default()
+        })
+        .push_children(&[label_id, decrease_id, value_id, increase_id])
+        .id()
+}
+
+fn build_arrow_button(
+    commands: &mut Commands,
+    style: TextStyle,
+    option: SettingOption,
+    step: i32,
+) -> Entity {
+    let text_id = commands
+        .spawn(TextBundle::from_section(
+            if step < 0 { "<" } else { ">" },
+            style,
+        ))
+        .id();
+
+    commands
+        .spawn((
+            SettingButton { option, step },
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(40.0),
+                    height: Val::Px(40.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+        ))
+        .add_child(text_id)
+        .id()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Systems
+////////////////////////////////////////////////////////////////////////////////
+
+fn open_and_close_settings_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut menu_events: EventReader<SettingsMenuEvent>,
+    settings: Res<Settings>,
+    settings_file: Res<SettingsFile>,
+    screen_query: Query<Entity, With<SettingsMenuScreen>>,
+) {
+    for event in menu_events.read() {
+        match event {
+            SettingsMenuEvent::Open => {
+                if screen_query.is_empty() {
+                    spawn(&mut commands, &asset_server);
+                }
+            }
+            SettingsMenuEvent::Close => {
+                if screen_query.is_empty() {
+                    continue;
+                }
+                for entity in screen_query.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+
+                if let Err(err) = settings_file.update(|saved| saved.copy_menu_settings(&settings))
+                {
+                    eprintln!("Error saving the settings: {}", err);
+                }
+            }
+        }
+    }
+}
+
+fn press_setting_buttons(
+    mut settings: ResMut<Settings>,
+    interaction_query: Query<(&Interaction, &SettingButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            button.option.adjust(&mut settings, button.step);
+        }
+    }
+}
+
+fn update_setting_values(
+    settings: Res<Settings>,
+    mut text_query: Query<(&SettingValueText, &mut Text)>,
+) {
+    for (SettingValueText(option), mut text) in text_query.iter_mut() {
+        let value = option.value(&settings);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+fn close_settings_menu_on_escape(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut menu_events: EventWriter<SettingsMenuEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        keyboard_input.reset(KeyCode::Escape);
+        menu_events.send(SettingsMenuEvent::Close);
+    }
+}
+
+fn close_settings_menu(mut menu_events: EventWriter<SettingsMenuEvent>) {
+    menu_events.send(SettingsMenuEvent::Close);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lists_wrap_and_numbers_stop_at_their_limits() {
+        let mut settings = Settings::default();
+
+        SettingOption::WindowMode.adjust(&mut settings, -1);
+        assert_eq!(settings.window.mode, WindowModeSetting::Fullscreen);
+
+        settings.audio.music_volume = 0.95;
+        SettingOption::MusicVolume.adjust(&mut settings, 1);
+        assert_eq!(settings.audio.music_volume, 1.0);
+        assert_eq!(SettingOption::MusicVolume.value(&settings), "100%");
+
+        SettingOption::UiScale.adjust(&mut settings, -3);
+        assert_eq!(settings.window.ui_scale, 0.5);
+    }
+}
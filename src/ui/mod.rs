@@ -1,2 +1,3 @@
 pub mod assets;
 pub mod hud;
+pub mod menu;